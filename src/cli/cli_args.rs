@@ -1,9 +1,42 @@
 use crate::cli::error_handling::CliResult;
+use crate::cli::replay_args::ReplayArgs;
+use crate::cli::report::ReportFormat;
+use crate::collections::collect_collection::CollectVec;
 use crate::command_gen::CommandGen;
+use crate::parasect::probe_context::ProbeContext;
+use crate::probe::artifacts::{Artifacts, CollectFor};
+use crate::probe::log_dir::LogDir;
+use crate::probe::metric::{Metric, MetricTest, Threshold};
+use crate::probe::output_diff::OutputDiff;
+use crate::probe::output_rules::{OutputRules, OutputStream};
+use crate::probe::predicate::{is_valid_name, Predicate};
+use crate::probe::probe::{Check, FailureAction};
+use crate::probe::resource_limits::ResourceLimits;
+use crate::probe::scratch::{ScratchDirs, ScratchMode};
+use crate::probe::template_file::TemplateFile;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
+use crate::task::cancellable_subprocess::Cancellation;
+use crate::task::child_process::parse_signal;
+use crate::task::pty::PtySize;
+use crate::task::resource_usage::{parse_bytes, parse_duration};
+use crate::template::template_string::TemplateString;
+use crate::ui::event_log::EventSink;
 use crate::ui::line::mkline;
-use clap::Parser;
-use ibig::IBig;
+use crate::ui::segment::{Attributes, Color};
+use clap::{Parser, Subcommand, ValueEnum};
+use ibig::{IBig, UBig};
+use regex::Regex;
+
+/// How the result of the search is printed.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum OutputFormat {
+    /// The TTY interface or the log, followed by the result.
+    #[default]
+    Human,
+    /// A single JSON document with the result and every probe, once the search is done.
+    Json,
+}
 
 /// Parasect searches the given command in parallel to find a point where it transitions from good (returning 0) to bad (returning != 0).
 ///
@@ -11,20 +44,71 @@ use ibig::IBig;
 ///
 /// Make sure you put your command after `--` and put `$X` in single quotes.
 ///
+/// To play back a search recorded with --events, run `parasect replay FILE` (see `parasect replay --help`).
+///
 /// By default, the magic string "$X" in the given command is replaced with the current number. This can be overriden with --substitution-string.
 /// If your range includes negative numbers, take care to make sure that the negative numbers are not parsed as flags.
 /// The command must return a non-empty sequence of 0 followed by a non-empty sequence of != 0 within [low, high]. If it doesn't, the search will fail or give erroneous results.
 #[derive(Parser, Debug)]
-#[command(version)]
+#[command(
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub subcommand: Option<CliCommand>,
+
+    /// The search to run, unless a subcommand is given.
+    #[command(flatten)]
+    pub search: Option<CliArgs>,
+}
+
+/// What parasect can do besides a search.
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    Replay(ReplayArgs),
+}
+
+/// The arguments of a search.
+#[derive(Parser, Debug)]
 pub struct CliArgs {
     /// The command to parasect, along with its arguments.
     ///
     /// The magic string "$X" will be replaced with the number. To change this string, use --substitution-string=NEW_STRING
     ///
+    /// Derived values can be substituted with `${EXPR}` or `${EXPR:FORMAT}`, e.g. `${X*4096}`, `${X+1}`, `${X:x}` (hex), or `${X:08}` (zero-padded to 8 digits).
+    ///
+    /// The context variables PARASECT_VALUE, PARASECT_WORKER_ID, PARASECT_LOW, PARASECT_HIGH, PARASECT_ATTEMPT, PARASECT_LATEST_GOOD and PARASECT_EARLIEST_BAD are set in the command's environment, and can be used in expressions, e.g. `${PARASECT_WORKER_ID}`.
+    ///
     /// This command should return 0 if good, != 0 if bad.
+    ///
+    /// It can be left out if --check is given. Otherwise, it's run as one more check, named "command".
     #[arg()]
     pub command: Vec<String>,
 
+    /// A shell command named NAME that helps decide if a point is bad, templated like the command. Can be given multiple times.
+    ///
+    /// All checks of a point run at once. By default, the point is bad if any check fails, which can be changed with --bad-if.
+    ///
+    /// Example: parasect --low=1 --high=100 --check='unit=make test REV=$X' --check='lint=make lint REV=$X'
+    #[arg(long = "check", value_name = "NAME=CMD")]
+    pub checks: Vec<String>,
+
+    /// When a point is bad, as a boolean expression over the check names, where each name means "that check failed".
+    ///
+    /// Supports `!`, `&&`, `||` and parentheses. As soon as the answer is known, the checks still running are stopped.
+    ///
+    /// Example: --bad-if='test_a && !test_b' is bad if test_a fails and test_b passes.
+    #[arg(long, value_name = "EXPR")]
+    bad_if: Option<String>,
+
+    /// The directory to run the command in, templated like the command, e.g. `--cwd='/builds/$X'`. Created if it doesn't exist.
+    ///
+    /// By default, the command runs in the current directory.
+    #[arg(long)]
+    cwd: Option<String>,
+
     /// The highest number to search, inclusive.
     ///
     /// This value, given to the command, should return != 0. It must also be greater than low.
@@ -49,6 +133,223 @@ pub struct CliArgs {
     #[arg(short = 't', long, default_value_t = false)]
     pub no_tty: bool,
 
+    /// How to print the result. With `json`, nothing else is printed to stdout, and the output of the probes that isn't captured goes to stderr.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    pub output: OutputFormat,
+
+    /// Print only the first bad index, for scripts. Errors are printed to stderr.
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    pub porcelain: bool,
+
+    /// Write every event of the search as a line of JSON, e.g. for dashboards: `jsonl` to stdout, `jsonl:PATH` to a file, or `jsonl:fd:N` to a file descriptor parasect was started with (e.g. with `3>events.jsonl`).
+    ///
+    /// The TTY interface keeps working unless the events go to stdout, in which case the result is printed to stderr.
+    #[arg(long, value_name = "FORMAT[:TO]")]
+    events: Option<String>,
+
+    /// Write a JUnit XML report to FILE once the search is done, e.g. for CI, with every probe as a testcase named after its command.
+    ///
+    /// Good points pass, bad ones fail, and skipped and cancelled ones are skipped. What the probes print is kept for the report, so their stderr is captured instead of shown.
+    #[arg(long, value_name = "FILE")]
+    pub junit: Option<String>,
+
+    /// Write a self-contained report of the search to FILE once it's done, for postmortems: HTML if FILE ends in `.html`, Markdown if it ends in `.md`.
+    ///
+    /// It has the answer and what it's based on, the final progress bar, a timeline of each worker's probes, every probe with its verdict, duration and logs, and any inconsistent results.
+    #[arg(long, value_name = "FILE")]
+    report: Option<String>,
+
+    /// Write a trace of the search to FILE once it's done, in Chrome's Trace Event format, e.g. to open it in Perfetto.
+    ///
+    /// Each worker is a thread with a span for every probe it ran. Cancellations, range invalidations and notes are instant events.
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<String>,
+
+    /// Only probe numbers that are `offset` more than a multiple of `step`.
+    ///
+    /// For example, `--step=64` only probes multiples of 64, and `--step=2 --offset=1` only probes odd numbers. The first bad index reported will also be aligned.
+    #[arg(long, default_value = "1")]
+    step: UBig,
+
+    /// Used with --step to shift the probed numbers away from multiples of the step.
+    ///
+    /// By default, this is 0.
+    #[arg(long, default_value = "0", allow_negative_numbers = true)]
+    offset: IBig,
+
+    /// Whether each worker reuses one scratch directory, or each probe gets a fresh one.
+    ///
+    /// The scratch directory is given to the command as TMPDIR and PARASECT_SCRATCH_DIR, and is deleted when parasect exits.
+    #[arg(long, value_enum, default_value_t = ScratchMode::Worker)]
+    scratch: ScratchMode,
+
+    /// Keep the scratch directories of probes that were bad or failed to run, and print where they are at the end.
+    #[arg(long, default_value_t = false)]
+    keep_failed_scratch: bool,
+
+    /// Mark a point bad if a line of its output matches REGEX, even if the command exits with 0. Can be given multiple times.
+    ///
+    /// Output rules win over the exit code: --skip-if-output-matches first, then --bad-if-output-matches, then --good-if-output-matches. With --check, they apply to each check's output. The matched line is shown in the log.
+    #[arg(long, value_name = "REGEX")]
+    bad_if_output_matches: Vec<String>,
+
+    /// Mark a point good if a line of its output matches REGEX, even if the command exits with != 0. Can be given multiple times.
+    #[arg(long, value_name = "REGEX")]
+    good_if_output_matches: Vec<String>,
+
+    /// Skip a point if a line of its output matches REGEX. Can be given multiple times.
+    #[arg(long, value_name = "REGEX")]
+    skip_if_output_matches: Vec<String>,
+
+    /// Which output the *-if-output-matches rules look at.
+    ///
+    /// stderr is only captured (and hidden) when it's looked at.
+    #[arg(long, value_enum, default_value_t = OutputStream::Both)]
+    match_stream: OutputStream,
+
+    /// Run the command (and setup, teardown and checks) in a pseudo-terminal instead of with pipes, for tools that only print their diagnostics or flush their output when attached to a terminal.
+    ///
+    /// Their stdout and stderr are captured together, with escape sequences stripped, so --match-stream=stderr can't be used.
+    #[arg(long, default_value_t = false)]
+    pty: bool,
+
+    /// The window size of the pseudo-terminal given by --pty, e.g. `120x40`.
+    ///
+    /// By default, this is the size of parasect's terminal, or 80x24 if it isn't running in one.
+    #[arg(long, value_name = "COLSxROWS", requires = "pty")]
+    pty_size: Option<String>,
+
+    /// The signal sent to a probe's command when the probe is cancelled, e.g. because a search elsewhere made its point unnecessary: a name like TERM or INT, or a number.
+    ///
+    /// It's sent to the command's whole process group, so it also reaches whatever the command started. If the group hasn't exited after --cancel-grace, it's killed with SIGKILL.
+    #[arg(long, value_name = "SIGNAL", default_value = "TERM")]
+    cancel_signal: String,
+
+    /// How long a cancelled probe's command has to exit after --cancel-signal before it's killed, e.g. `5s` or `500ms`.
+    #[arg(long, value_name = "DURATION", default_value = "5s")]
+    cancel_grace: String,
+
+    /// Mark a point bad if its command's peak memory (max RSS) goes over SIZE, e.g. `2G` or `512M`, whatever its exit code.
+    ///
+    /// The wall time, CPU time and max RSS of every probe are shown in the log either way.
+    #[arg(long, value_name = "SIZE")]
+    bad_if_max_rss: Option<String>,
+
+    /// Mark a point bad if its command takes longer than DURATION, e.g. `10s` or `500ms`, whatever its exit code.
+    ///
+    /// The command's process group is killed as soon as it reaches the limit.
+    #[arg(long, value_name = "DURATION")]
+    bad_if_wall: Option<String>,
+
+    /// Stream each probe's stdout and stderr to `DIR/<point>.out` and `DIR/<point>.err` instead of showing stderr, creating DIR if needed.
+    ///
+    /// Retries go to `<point>.attemptN.out` and so on, each check to `<point>.<check>.out`, and setup and teardown to `<point>.setup.out` and `<point>.teardown.out`. The logs of the latest good and first bad points are listed at the end.
+    #[arg(long, value_name = "DIR")]
+    log_dir: Option<String>,
+
+    /// How much of each command's stdout and stderr is kept for output rules and metrics, and written to --log-dir, e.g. `1M`. The rest is dropped.
+    #[arg(long, value_name = "SIZE", default_value = "16M")]
+    max_output: String,
+
+    /// After the search, show a unified diff of the stdout and stderr of the latest good and first bad points.
+    ///
+    /// Their output is kept while searching, and they're probed again if it wasn't, e.g. because the latest good point is --low and was never probed. Stderr is captured instead of shown.
+    #[arg(long, default_value_t = false)]
+    diff_output: bool,
+
+    /// Copy the files matching any of GLOBS (separated by commas) out of each probe's working directory into `--collect-dir/<point>/` after the probe, e.g. `**/*.core,test-results/*.xml`. Can be given multiple times.
+    ///
+    /// The working directory is --cwd, or the probe's scratch directory (`$TMPDIR`) if it isn't given. Files under --collect-dir never match. Retries go to `<point>.attemptN/`.
+    #[arg(long, value_name = "GLOBS", value_delimiter = ',')]
+    collect: Vec<String>,
+
+    /// Where --collect copies files to.
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "results",
+        requires = "collect"
+    )]
+    collect_dir: String,
+
+    /// Which probes --collect keeps the files of.
+    #[arg(long, value_enum, default_value_t = CollectFor::Bad, requires = "collect")]
+    collect_for: CollectFor,
+
+    /// Leave lines matching REGEX out of --diff-output, e.g. timestamps and PIDs. Can be given multiple times.
+    #[arg(long, value_name = "REGEX", requires = "diff_output")]
+    diff_ignore: Vec<String>,
+
+    /// Decide points by a number measured from the command's output instead of by its exit code: a point is bad if the number crosses THRESHOLD.
+    ///
+    /// `>N` and `<N` compare against a fixed number. `>N%` and `<N%` compare against the value measured at the low end of the range before the search starts, e.g. `>+10%` is bad if the metric grew by more than 10%, and `<-5%` if it dropped by more than 5%.
+    ///
+    /// By default, the whole output is the number (see --metric-regex). A point whose command exits with != 0 is skipped. The measured value is shown in the log.
+    #[arg(long, value_name = "THRESHOLD")]
+    bad_if_metric: Option<String>,
+
+    /// Read the metric from the first match of REGEX in the output, or from its first capture group if it has one, e.g. `took ([0-9.]+)ms`.
+    #[arg(long, value_name = "REGEX")]
+    metric_regex: Option<String>,
+
+    /// How many times to run the command per point (and for the baseline) to measure --bad-if-metric.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    samples: usize,
+
+    /// How the samples of a point are compared against the --bad-if-metric threshold.
+    #[arg(long, value_enum, default_value_t = MetricTest::Mean)]
+    metric_test: MetricTest,
+
+    /// A shell command to run before each probe, e.g. to check out code. Templated like the command, with the same environment and working directory.
+    #[arg(long, value_name = "CMD")]
+    setup_cmd: Option<String>,
+
+    /// A shell command to run after each probe, e.g. to stop a service. It runs even if the probe was cancelled or its setup failed.
+    ///
+    /// If it fails, the search is aborted.
+    #[arg(long, value_name = "CMD")]
+    teardown_cmd: Option<String>,
+
+    /// Whether to skip the point or abort the search if --setup-cmd fails.
+    ///
+    /// Skipped points are never reported as the first bad index. If one of them could be, parasect says so instead of guessing.
+    #[arg(long, value_enum, default_value_t = FailureAction::Abort)]
+    pub on_setup_failure: FailureAction,
+
+    /// A shell command that builds the point before it's probed, e.g. `make REV="$X" OUT="$PARASECT_ARTIFACT_DIR"`.
+    ///
+    /// Builds run in their own pool (see --build-parallelism) and put their output in PARASECT_ARTIFACT_DIR, which is given to the setup command, the command, and the teardown command of the point. Each point is built once, builds for the points likely to be probed next are started ahead of time while build slots are free, and builds for points that no longer need probing are cancelled.
+    ///
+    /// Only `$X` and ${PARASECT_VALUE} can be used in it, since a build isn't tied to a worker.
+    #[arg(long, value_name = "CMD")]
+    build_cmd: Option<String>,
+
+    /// The maximum amount of builds to run at any time.
+    ///
+    /// Defaults to --max-parallelism.
+    #[arg(long, value_name = "N")]
+    build_parallelism: Option<usize>,
+
+    /// Whether to skip the point or abort the search if --build-cmd fails.
+    #[arg(long, value_enum, default_value_t = FailureAction::Abort)]
+    pub on_build_failure: FailureAction,
+
+    /// Run the command through `sh -c`, so pipes, `&&`, redirections, etc. can be used.
+    ///
+    /// The arguments are joined with spaces into a script. `$X` becomes a shell variable holding the number (safely quoted), while `${...}` expressions are substituted before the script runs.
+    ///
+    /// Example: parasect --shell --low=1 --high=100 -- 'make -j8 REV="$X" && ./run-tests | grep -q PASS'
+    #[arg(long, default_value_t = false)]
+    pub shell: bool,
+
+    /// Render the file SRC into DEST within the worker's scratch directory before each probe. Can be given multiple times.
+    ///
+    /// SRC is templated like the command, e.g. `size: ${X*4096}`. The rendered paths are given to the command as the environment variables PARASECT_TEMPLATE_FILE_0, PARASECT_TEMPLATE_FILE_1, etc., and the scratch directory as PARASECT_SCRATCH_DIR.
+    ///
+    /// Example: parasect --low=1 --high=100 --template-file=config.tmpl:config.yaml --shell -- './test --config="$PARASECT_TEMPLATE_FILE_0"'
+    #[arg(long = "template-file", value_name = "SRC:DEST")]
+    pub template_files: Vec<String>,
+
     /// The string that will be replaced with the current number in the given command's execution.
     ///
     /// By default, this is "$X".
@@ -57,8 +358,401 @@ pub struct CliArgs {
 }
 
 impl CliArgs {
+    /// Whether stdout is for a script rather than a person, so neither the interface nor the probes may print to it.
+    pub fn machine_output(&self) -> bool {
+        self.porcelain
+            || self.output != OutputFormat::Human
+            || self
+                .events()
+                .is_ok_and(|sink| sink == Some(EventSink::Stdout))
+    }
+
+    pub fn alignment(&self) -> CliResult<Alignment> {
+        if self.step == UBig::from(0usize) {
+            Err(vec![mkline!(
+                "The step cannot be 0. Specify a value >= 1 for --step."
+            )])
+        } else {
+            Ok(Alignment::new(self.step.clone(), self.offset.clone()))
+        }
+    }
+
     pub fn command_gen(&self) -> CliResult<CommandGen> {
-        CommandGen::new(self.command.clone(), self.substitution_string.clone())
+        let value_in_template_file = self
+            .template_files()
+            .is_ok_and(|files| files.iter().any(|f| f.references_value()));
+
+        // with a build command, the value can reach the command through its artifacts instead
+        if value_in_template_file || self.build_cmd.is_some() {
+            CommandGen::new_without_value(self.command.clone(), self.substitution_string.clone())
+        } else {
+            CommandGen::new(self.command.clone(), self.substitution_string.clone())
+        }
+        .map(|c| c.with_shell(self.shell))
+    }
+
+    pub fn template_files(&self) -> CliResult<Vec<TemplateFile>> {
+        self.template_files
+            .iter()
+            .map(|spec| {
+                TemplateFile::load(
+                    spec,
+                    &self.substitution_string,
+                    &ProbeContext::VARIABLE_NAMES,
+                )
+            })
+            .collect()
+    }
+
+    fn shell_command(&self, cmd: &str) -> CliResult<CommandGen> {
+        CommandGen::new_without_value([cmd.to_string()], self.substitution_string.clone())
+            .map(|c| c.with_shell(true))
+    }
+
+    fn hook(&self, cmd: &Option<String>) -> CliResult<Option<CommandGen>> {
+        cmd.as_ref().map(|cmd| self.shell_command(cmd)).transpose()
+    }
+
+    /// The command and every --check, in order.
+    pub fn checks(&self) -> CliResult<Vec<Check>> {
+        let mut checks = Vec::new();
+        if !self.command.is_empty() || self.checks.is_empty() {
+            checks.push(Check::new("command", self.command_gen()?));
+        }
+
+        for spec in &self.checks {
+            let (name, cmd) = spec
+                .split_once('=')
+                .filter(|(name, _)| is_valid_name(name))
+                .ok_or_else(|| {
+                    vec![mkline!(
+                        "--check must be of the form NAME=CMD, where NAME starts with a letter and only has letters, digits, `_` and `-` (got \"",
+                        spec,
+                        "\")."
+                    )]
+                })?;
+
+            if checks.iter().any(|c| c.name == name) {
+                return Err(vec![mkline!(
+                    "The check name \"",
+                    (name, Color::Blue, Attributes::Bold),
+                    "\" is used more than once."
+                )]);
+            }
+
+            checks.push(Check::new(name, self.shell_command(cmd)?));
+        }
+
+        Ok(checks)
+    }
+
+    pub fn bad_if(&self, checks: &[Check]) -> CliResult<Option<Predicate>> {
+        let names = checks.iter().map(|c| c.name.as_str()).collect_vec();
+
+        self.bad_if
+            .as_ref()
+            .map(|expr| {
+                Predicate::parse(expr, &names).map_err(|e| {
+                    vec![mkline!(
+                        "Invalid --bad-if ",
+                        (expr, Color::Blue, Attributes::Bold),
+                        ": ",
+                        e
+                    )]
+                })
+            })
+            .transpose()
+    }
+
+    fn regex(flag: &str, pattern: &str) -> CliResult<Regex> {
+        Regex::new(pattern).map_err(|e| {
+            // the regex crate draws a multi-line diagram; its last line says what's wrong
+            let e = e.to_string();
+            let message = e.lines().last().unwrap_or_default();
+            let message = message.strip_prefix("error: ").unwrap_or(message);
+
+            vec![mkline!(
+                "Invalid regex ",
+                (pattern, Color::Blue, Attributes::Bold),
+                " in ",
+                flag,
+                ": ",
+                message
+            )]
+        })
+    }
+
+    pub fn output_rules(&self) -> CliResult<OutputRules> {
+        let mut rules = OutputRules::new(self.match_stream);
+        for p in &self.skip_if_output_matches {
+            rules = rules.with_skip(Self::regex("--skip-if-output-matches", p)?);
+        }
+        for p in &self.bad_if_output_matches {
+            rules = rules.with_bad(Self::regex("--bad-if-output-matches", p)?);
+        }
+        for p in &self.good_if_output_matches {
+            rules = rules.with_good(Self::regex("--good-if-output-matches", p)?);
+        }
+
+        Ok(rules)
+    }
+
+    pub fn artifacts(&self) -> CliResult<Option<Artifacts>> {
+        if self.collect.is_empty() {
+            return Ok(None);
+        }
+
+        Artifacts::new(&self.collect_dir, self.collect.clone(), self.collect_for)
+            .map(Some)
+            .map_err(|e| vec![mkline!("Invalid --collect: ", e)])
+    }
+
+    pub fn output_diff(&self) -> CliResult<Option<OutputDiff>> {
+        if !self.diff_output {
+            return Ok(None);
+        }
+
+        let ignore = self
+            .diff_ignore
+            .iter()
+            .map(|p| Self::regex("--diff-ignore", p))
+            .collect::<CliResult<Vec<_>>>()?;
+        Ok(Some(OutputDiff::new(ignore)))
+    }
+
+    pub fn pty(&self) -> CliResult<Option<PtySize>> {
+        if !self.pty {
+            return Ok(None);
+        }
+        if self.match_stream == OutputStream::Stderr {
+            return Err(vec![mkline!(
+                "--pty captures stdout and stderr together, so it can't be used with --match-stream=stderr"
+            )]);
+        }
+
+        match &self.pty_size {
+            Some(size) => PtySize::parse(size).map(Some).map_err(|e| {
+                vec![mkline!(
+                    "Invalid --pty-size ",
+                    (size, Color::Blue, Attributes::Bold),
+                    ": ",
+                    e
+                )]
+            }),
+            None => Ok(Some(PtySize::of_terminal().unwrap_or_default())),
+        }
+    }
+
+    pub fn cancellation(&self) -> CliResult<Cancellation> {
+        let invalid = |flag: &str, value: &String, e: String| {
+            vec![mkline!(
+                "Invalid ",
+                flag,
+                " ",
+                (value, Color::Blue, Attributes::Bold),
+                ": ",
+                e
+            )]
+        };
+
+        Ok(Cancellation {
+            signal: parse_signal(&self.cancel_signal)
+                .map_err(|e| invalid("--cancel-signal", &self.cancel_signal, e))?,
+            grace: parse_duration(&self.cancel_grace)
+                .map_err(|e| invalid("--cancel-grace", &self.cancel_grace, e))?,
+        })
+    }
+
+    pub fn limits(&self) -> CliResult<ResourceLimits> {
+        let invalid = |flag: &str, value: &String, e: String| {
+            vec![mkline!(
+                "Invalid ",
+                flag,
+                " ",
+                (value, Color::Blue, Attributes::Bold),
+                ": ",
+                e
+            )]
+        };
+
+        Ok(ResourceLimits {
+            max_rss: self
+                .bad_if_max_rss
+                .as_ref()
+                .map(|v| parse_bytes(v).map_err(|e| invalid("--bad-if-max-rss", v, e)))
+                .transpose()?,
+            wall: self
+                .bad_if_wall
+                .as_ref()
+                .map(|v| parse_duration(v).map_err(|e| invalid("--bad-if-wall", v, e)))
+                .transpose()?,
+        })
+    }
+
+    pub fn report(&self) -> CliResult<Option<(String, ReportFormat)>> {
+        self.report
+            .as_ref()
+            .map(|path| match ReportFormat::from_path(path) {
+                Some(format) => Ok((path.clone(), format)),
+                None => Err(vec![mkline!(
+                    "Can't tell the format of --report from ",
+                    (path, Color::Blue, Attributes::Bold),
+                    ". Give it a file that ends in .html or .md."
+                )]),
+            })
+            .transpose()
+    }
+
+    pub fn events(&self) -> CliResult<Option<EventSink>> {
+        let sink = match &self.events {
+            Some(events) => {
+                EventSink::parse(events).map_err(|e| vec![mkline!("Invalid --events: ", e)])?
+            }
+            None => return Ok(None),
+        };
+
+        if sink == EventSink::Stdout && (self.porcelain || self.output != OutputFormat::Human) {
+            return Err(vec![mkline!(
+                "--events=jsonl writes to stdout, which --output and --porcelain need for themselves. Give it a file instead, e.g. --events=jsonl:events.jsonl."
+            )]);
+        }
+        Ok(Some(sink))
+    }
+
+    pub fn log_dir(&self) -> CliResult<Option<LogDir>> {
+        self.log_dir
+            .as_ref()
+            .map(|dir| {
+                LogDir::new(dir).map_err(|e| {
+                    vec![mkline!(
+                        "Failed to create the log directory ",
+                        (dir, Color::Blue, Attributes::Bold),
+                        ": ",
+                        e.to_string()
+                    )]
+                })
+            })
+            .transpose()
+    }
+
+    pub fn output_limit(&self) -> CliResult<usize> {
+        parse_bytes(&self.max_output)
+            .map(|limit| limit as usize)
+            .map_err(|e| {
+                vec![mkline!(
+                    "Invalid --max-output ",
+                    (&self.max_output, Color::Blue, Attributes::Bold),
+                    ": ",
+                    e
+                )]
+            })
+    }
+
+    pub fn metric(&self) -> CliResult<Option<Metric>> {
+        let spec = match &self.bad_if_metric {
+            Some(spec) => spec,
+            None if self.metric_regex.is_some()
+                || self.samples != 1
+                || self.metric_test != MetricTest::Mean =>
+            {
+                return Err(vec![mkline!(
+                    "--metric-regex, --samples and --metric-test only apply with --bad-if-metric."
+                )])
+            }
+            None => return Ok(None),
+        };
+
+        let threshold = Threshold::parse(spec).map_err(|e| {
+            vec![mkline!(
+                "Invalid --bad-if-metric ",
+                (spec, Color::Blue, Attributes::Bold),
+                ": ",
+                e
+            )]
+        })?;
+
+        let error = if !self.checks.is_empty() {
+            Some("--bad-if-metric can't be used with --check.")
+        } else if self.samples == 0 {
+            Some("The number of samples cannot be 0. Specify a value >= 1 for --samples.")
+        } else if self.metric_test == MetricTest::Ci && self.samples < 2 {
+            Some("--metric-test=ci needs at least 2 --samples.")
+        } else if self.metric_test == MetricTest::MannWhitney && self.samples < 3 {
+            Some("--metric-test=mann-whitney needs at least 3 --samples.")
+        } else if self.metric_test == MetricTest::MannWhitney && !threshold.relative {
+            Some("--metric-test=mann-whitney needs a threshold relative to the low end of the range, e.g. --bad-if-metric='>+10%'.")
+        } else {
+            None
+        };
+        if let Some(e) = error {
+            return Err(vec![mkline!(e)]);
+        }
+
+        let pattern = self
+            .metric_regex
+            .as_ref()
+            .map(|p| Self::regex("--metric-regex", p))
+            .transpose()?;
+
+        Ok(Some(
+            Metric::new(threshold)
+                .with_pattern(pattern)
+                .with_samples(self.samples)
+                .with_test(self.metric_test),
+        ))
+    }
+
+    /// The command along with any --check, for display.
+    pub fn display_command(&self) -> Vec<String> {
+        self.command
+            .iter()
+            .cloned()
+            .chain(self.checks.iter().map(|c| format!("--check={}", c)))
+            .collect_vec()
+    }
+
+    pub fn setup_cmd(&self) -> CliResult<Option<CommandGen>> {
+        self.hook(&self.setup_cmd)
+    }
+
+    pub fn teardown_cmd(&self) -> CliResult<Option<CommandGen>> {
+        self.hook(&self.teardown_cmd)
+    }
+
+    pub fn build_cmd(&self) -> CliResult<Option<CommandGen>> {
+        self.hook(&self.build_cmd)
+    }
+
+    pub fn cwd(&self) -> CliResult<Option<TemplateString>> {
+        self.cwd
+            .as_ref()
+            .map(|cwd| {
+                TemplateString::parse(
+                    cwd,
+                    &self.substitution_string,
+                    &ProbeContext::VARIABLE_NAMES,
+                )
+                .map_err(|e| {
+                    vec![mkline!(
+                        "Invalid substitution ",
+                        (&e.source, Color::Blue, Attributes::Bold),
+                        " in --cwd: ",
+                        e.message
+                    )]
+                })
+            })
+            .transpose()
+    }
+
+    pub fn scratch_dirs(&self) -> CliResult<ScratchDirs> {
+        ScratchDirs::new(self.scratch)
+            .map(|s| s.with_keep_failed(self.keep_failed_scratch))
+            .map_err(|e| {
+                vec![mkline!(
+                    "Failed to create a scratch directory: ",
+                    e.to_string()
+                )]
+            })
     }
 
     pub fn max_parallelism(&self) -> CliResult<usize> {
@@ -72,6 +766,16 @@ impl CliArgs {
         }
     }
 
+    pub fn build_parallelism(&self) -> CliResult<usize> {
+        match self.build_parallelism {
+            Some(0) => Err(vec![mkline!(
+                "The build parallelism cannot be 0. Specify a value >= 1 for --build-parallelism."
+            )]),
+            Some(n) => Ok(n),
+            None => self.max_parallelism(),
+        }
+    }
+
     pub fn range(&self) -> CliResult<NumericRange> {
         if self.low >= self.high {
             return Err(vec![mkline!(
@@ -83,19 +787,43 @@ impl CliArgs {
             )]);
         }
 
-        Ok(NumericRange::from_endpoints_inclusive(
-            self.low.clone(),
-            self.high.clone(),
-        ))
+        let alignment = self.alignment()?;
+        let range = NumericRange::from_endpoints_inclusive(self.low.clone(), self.high.clone())
+            .align(&alignment);
+
+        if range.aligned_len(&alignment) < UBig::from(2usize) {
+            return Err(vec![mkline!(
+                "There must be at least two numbers matching the alignment ",
+                alignment.to_string(),
+                " between ",
+                &self.low,
+                " and ",
+                &self.high,
+                "."
+            )]);
+        }
+
+        Ok(range)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cli::cli_args::CliArgs;
+    use crate::cli::cli_args::{Cli, CliArgs, CliCommand, OutputFormat};
+    use crate::collections::collect_collection::CollectVec;
+    use crate::probe::predicate::Predicate;
+    use crate::probe::probe::FailureAction;
+    use crate::probe::resource_limits::ResourceLimits;
+    use crate::probe::scratch::ScratchMode;
+    use crate::range::alignment::Alignment;
+    use crate::task::cancellable_subprocess::Cancellation;
+    use crate::task::pty::PtySize;
     use crate::test_util::test_util::test_util::{ib, r};
+    use crate::ui::event_log::EventSink;
     use crate::ui::line::mkline;
+    use crate::ui::segment::{Attributes, Color};
     use clap::Parser;
+    use std::time::Duration;
 
     #[test]
     fn test_basic_parse() {
@@ -107,106 +835,803 @@ mod tests {
         assert_eq!(args.high, ib(10));
         assert_eq!(args.max_parallelism, None);
         assert_eq!(args.no_tty, false);
+        assert!(!args.shell);
         assert_eq!(args.substitution_string, "$X");
     }
 
     #[test]
-    fn test_notty_parse() {
+    fn test_shell_parse() {
         let args = CliArgs::parse_from([
             "parasect",
             "--low=5",
             "--high=10",
-            "--no-tty",
+            "--shell",
             "--",
-            "foo",
-            "--bar=$X",
+            "foo $X | bar",
         ]);
 
-        assert_eq!(args.command, vec!["foo", "--bar=$X"]);
-        assert_eq!(args.low, ib(5));
-        assert_eq!(args.high, ib(10));
-        assert_eq!(args.max_parallelism, None);
-        assert_eq!(args.no_tty, true);
-        assert_eq!(args.substitution_string, "$X");
+        assert_eq!(args.command, vec!["foo $X | bar"]);
+        assert!(args.shell);
     }
 
     #[test]
-    fn test_max_parallelism_parse() {
+    fn test_template_file_parse() {
         let args = CliArgs::parse_from([
             "parasect",
             "--low=5",
             "--high=10",
-            "--max-parallelism=2",
+            "--template-file=a.tmpl:a.yaml",
+            "--template-file",
+            "b.tmpl:b/b.yaml",
             "--",
             "foo",
-            "--bar=$X",
+            "$X",
         ]);
 
-        assert_eq!(args.command, vec!["foo", "--bar=$X"]);
-        assert_eq!(args.low, ib(5));
-        assert_eq!(args.high, ib(10));
-        assert_eq!(args.max_parallelism, Some(2usize));
-        assert_eq!(args.no_tty, false);
-        assert_eq!(args.substitution_string, "$X");
+        assert_eq!(
+            args.template_files,
+            vec!["a.tmpl:a.yaml", "b.tmpl:b/b.yaml"]
+        );
+        assert!(args.template_files().is_err());
     }
 
     #[test]
-    fn test_command_gen_ok() {
-        let args =
-            CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "--bar=$X"]);
-
-        assert!(args.command_gen().is_ok());
-    }
+    fn test_scratch_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--cwd=/builds/${X:04}",
+            "--scratch=point",
+            "--keep-failed-scratch",
+            "--",
+            "foo",
+            "$X",
+        ]);
 
-    #[test]
-    fn test_command_gen_err() {
-        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10"]);
+        assert_eq!(args.scratch, ScratchMode::Point);
+        assert!(args.keep_failed_scratch);
+        assert!(args.cwd().unwrap().is_some());
 
-        assert!(args.command_gen().is_err());
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.scratch, ScratchMode::Worker);
+        assert_eq!(args.cwd(), Ok(None));
     }
 
     #[test]
-    fn test_max_parallelism_ok() {
+    fn test_cwd_invalid_expression() {
         let args = CliArgs::parse_from([
             "parasect",
             "--low=5",
             "--high=10",
-            "--max-parallelism=2",
+            "--cwd=/builds/${X:q}",
             "--",
             "foo",
-            "--bar=$X",
+            "$X",
         ]);
 
-        assert_eq!(args.max_parallelism(), Ok(2));
+        assert_eq!(
+            args.cwd().unwrap_err(),
+            vec![mkline!(
+                "Invalid substitution ",
+                ("${X:q}", Color::Blue, Attributes::Bold),
+                " in --cwd: unknown format \"q\""
+            )]
+        );
     }
 
     #[test]
-    fn test_max_parallelism_not_specified() {
-        let args =
-            CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "--bar=$X"]);
+    fn test_hooks_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--setup-cmd=git checkout \"$X\"",
+            "--teardown-cmd=make clean",
+            "--on-setup-failure=skip",
+            "--",
+            "foo",
+            "$X",
+        ]);
 
-        assert!(args.max_parallelism().is_ok());
+        assert_eq!(args.on_setup_failure, FailureAction::Skip);
+        assert_eq!(
+            args.setup_cmd()
+                .unwrap()
+                .unwrap()
+                .command_for_number(&ib(7))
+                .unwrap(),
+            vec!["sh", "-c", "X='7'\ngit checkout \"$X\""]
+        );
+        assert!(args.teardown_cmd().unwrap().is_some());
+
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.on_setup_failure, FailureAction::Abort);
+        assert!(args.setup_cmd().unwrap().is_none());
     }
 
     #[test]
-    fn test_max_parallelism_zero_err() {
+    fn test_build_parse() {
         let args = CliArgs::parse_from([
             "parasect",
             "--low=5",
             "--high=10",
-            "--max-parallelism=0",
+            "--max-parallelism=32",
+            "--build-cmd=make REV=\"$X\"",
+            "--build-parallelism=2",
+            "--on-build-failure=skip",
             "--",
             "foo",
-            "--bar=$X",
+            "$X",
         ]);
 
+        assert_eq!(args.on_build_failure, FailureAction::Skip);
+        assert_eq!(args.build_parallelism(), Ok(2));
         assert_eq!(
-            args.max_parallelism(),
-            Err(vec![mkline!(
-                "The max parallelism cannot be 0. Specify a value >= 1 for --max-parallelism."
-            )])
+            args.build_cmd()
+                .unwrap()
+                .unwrap()
+                .command_for_number(&ib(7))
+                .unwrap(),
+            vec!["sh", "-c", "X='7'\nmake REV=\"$X\""]
         );
-    }
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--max-parallelism=32",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(args.build_parallelism(), Ok(32));
+        assert!(args.build_cmd().unwrap().is_none());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--build-parallelism=0",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.build_parallelism(),
+            Err(vec![mkline!(
+                "The build parallelism cannot be 0. Specify a value >= 1 for --build-parallelism."
+            )])
+        );
+    }
+
+    #[test]
+    fn test_checks_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--check=unit=make test REV=$X",
+            "--check=lint=make lint",
+            "--bad-if=unit && !lint",
+        ]);
+
+        let checks = args.checks().unwrap();
+        assert_eq!(
+            checks.iter().map(|c| c.name.as_str()).collect_vec(),
+            vec!["unit", "lint"]
+        );
+        assert_eq!(
+            checks[0].command_gen.command_for_number(&ib(7)).unwrap(),
+            vec!["sh", "-c", "X='7'\nmake test REV=$X"]
+        );
+        assert_eq!(
+            args.bad_if(&checks),
+            Ok(Some(
+                Predicate::parse("unit && !lint", &["unit", "lint"]).unwrap()
+            ))
+        );
+        assert_eq!(
+            args.display_command(),
+            vec!["--check=unit=make test REV=$X", "--check=lint=make lint"]
+        );
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--check=lint=make lint",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        let checks = args.checks().unwrap();
+        assert_eq!(
+            checks.iter().map(|c| c.name.as_str()).collect_vec(),
+            vec!["command", "lint"]
+        );
+        assert_eq!(args.bad_if(&checks), Ok(None));
+    }
+
+    #[test]
+    fn test_checks_errors() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--check=make lint"]);
+        assert_eq!(
+            args.checks().err(),
+            Some(vec![mkline!(
+                "--check must be of the form NAME=CMD, where NAME starts with a letter and only has letters, digits, `_` and `-` (got \"make lint\")."
+            )])
+        );
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--check=a=true",
+            "--check=a=false",
+        ]);
+        assert_eq!(
+            args.checks().err(),
+            Some(vec![mkline!(
+                "The check name \"",
+                ("a", Color::Blue, Attributes::Bold),
+                "\" is used more than once."
+            )])
+        );
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--check=a=true",
+            "--bad-if=b",
+        ]);
+        assert_eq!(
+            args.bad_if(&args.checks().unwrap()),
+            Err(vec![mkline!(
+                "Invalid --bad-if ",
+                ("b", Color::Blue, Attributes::Bold),
+                ": Unknown check \"b\" (the checks are a)"
+            )])
+        );
+    }
+
+    #[test]
+    fn test_output_rules_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--bad-if-output-matches=FAIL",
+            "--bad-if-output-matches=^error:",
+            "--skip-if-output-matches=SKIP",
+            "--match-stream=stderr",
+            "--",
+            "foo",
+            "$X",
+        ]);
+
+        let rules = args.output_rules().unwrap();
+        assert!(!rules.is_empty());
+        assert!(rules.needs_stderr());
+
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert!(args.output_rules().unwrap().is_empty());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--good-if-output-matches=(",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.output_rules().unwrap_err(),
+            vec![mkline!(
+                "Invalid regex ",
+                ("(", Color::Blue, Attributes::Bold),
+                " in --good-if-output-matches: unclosed group"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_limits_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--bad-if-max-rss=2G",
+            "--bad-if-wall=10s",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.limits(),
+            Ok(ResourceLimits {
+                max_rss: Some(2 << 30),
+                wall: Some(Duration::from_secs(10)),
+            })
+        );
+
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.limits(), Ok(ResourceLimits::default()));
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--bad-if-wall=soon",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.limits().unwrap_err(),
+            vec![mkline!(
+                "Invalid --bad-if-wall ",
+                ("soon", Color::Blue, Attributes::Bold),
+                ": Unknown unit \"soon\" (expected ms, s, m or h)"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_output_limit_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.output_limit(), Ok(16 << 20));
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--max-output=lots",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.output_limit().unwrap_err(),
+            vec![mkline!(
+                "Invalid --max-output ",
+                ("lots", Color::Blue, Attributes::Bold),
+                ": Unknown unit \"lots\" (expected K, M, G or T)"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_output_format_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.output, OutputFormat::Human);
+        assert!(!args.machine_output());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--output=json",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(args.output, OutputFormat::Json);
+        assert!(args.machine_output());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--porcelain",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert!(args.porcelain);
+        assert!(args.machine_output());
+
+        assert!(CliArgs::try_parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--porcelain",
+            "--output=json",
+            "--",
+            "foo",
+            "$X",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_events_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.events(), Ok(None));
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--events=jsonl:fd:3",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(args.events(), Ok(Some(EventSink::Fd(3))));
+        assert!(!args.machine_output());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--events=jsonl",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(args.events(), Ok(Some(EventSink::Stdout)));
+        assert!(args.machine_output());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--events=jsonl",
+            "--porcelain",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert!(args.events().is_err());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--events=xml",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.events(),
+            Err(vec![mkline!(
+                "Invalid --events: ",
+                "Unknown format \"xml\" (expected jsonl, jsonl:PATH or jsonl:fd:N)"
+            )])
+        );
+    }
+
+    #[test]
+    fn test_output_diff_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert!(args.output_diff().unwrap().is_none());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--diff-output",
+            "--diff-ignore=(",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.output_diff().unwrap_err(),
+            vec![mkline!(
+                "Invalid regex ",
+                ("(", Color::Blue, Attributes::Bold),
+                " in --diff-ignore: unclosed group"
+            )]
+        );
+
+        assert!(CliArgs::try_parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--diff-ignore=x",
+            "--",
+            "foo",
+            "$X",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_artifacts_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert!(args.artifacts().unwrap().is_none());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--collect=**/*.core,test-results/*.xml",
+            "--collect=*.png",
+            "--collect-for=boundary",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.collect,
+            vec!["**/*.core", "test-results/*.xml", "*.png"]
+        );
+        assert!(args.artifacts().unwrap().is_some());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--collect=a/***",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.artifacts().unwrap_err(),
+            vec![mkline!(
+                "Invalid --collect: Invalid glob \"a/***\": wildcards are either regular `*` or recursive `**`"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_cancellation_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.cancellation(), Ok(Cancellation::default()));
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--cancel-signal=SIGINT",
+            "--cancel-grace=500ms",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.cancellation(),
+            Ok(Cancellation {
+                signal: libc::SIGINT,
+                grace: Duration::from_millis(500),
+            })
+        );
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--cancel-signal=BOOM",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert!(args.cancellation().is_err());
+    }
+
+    #[test]
+    fn test_pty_parse() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "$X"]);
+        assert_eq!(args.pty(), Ok(None));
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--pty",
+            "--pty-size=120x40",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert_eq!(
+            args.pty(),
+            Ok(Some(PtySize {
+                cols: 120,
+                rows: 40
+            }))
+        );
+
+        assert!(CliArgs::try_parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--pty-size=120x40",
+            "--",
+            "foo",
+            "$X",
+        ])
+        .is_err());
+
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--pty",
+            "--match-stream=stderr",
+            "--",
+            "foo",
+            "$X",
+        ]);
+        assert!(args.pty().is_err());
+    }
+
+    #[test]
+    fn test_subcommand_parse() {
+        let cli = Cli::parse_from(["parasect", "replay", "events.jsonl"]);
+        assert!(matches!(cli.subcommand, Some(CliCommand::Replay(_))));
+        assert!(cli.search.is_none());
+
+        let cli = Cli::parse_from(["parasect", "--low=5", "--high=10", "--", "replay", "$X"]);
+        assert!(cli.subcommand.is_none());
+        assert_eq!(cli.search.unwrap().command, vec!["replay", "$X"]);
+
+        assert!(Cli::try_parse_from(["parasect", "--low=5", "replay", "events.jsonl"]).is_err());
+        assert!(Cli::try_parse_from(["parasect"]).is_err());
+    }
+
+    #[test]
+    fn test_metric_parse() {
+        let parse = |flags: &[&str]| {
+            CliArgs::parse_from(
+                ["parasect", "--low=5", "--high=10"]
+                    .iter()
+                    .chain(flags)
+                    .chain(&["--", "foo", "$X"]),
+            )
+            .metric()
+        };
+
+        assert!(parse(&[]).unwrap().is_none());
+
+        let metric = parse(&[
+            "--bad-if-metric=>+10%",
+            "--metric-regex=took ([0-9.]+)ms",
+            "--samples=3",
+            "--metric-test=mann-whitney",
+        ])
+        .unwrap()
+        .unwrap();
+        assert!(metric.needs_baseline());
+        assert_eq!(metric.samples(), 3);
+        assert_eq!(metric.extract("took 4.5ms"), Ok(4.5));
+
+        assert_eq!(
+            parse(&["--bad-if-metric=250"]).unwrap_err(),
+            vec![mkline!(
+                "Invalid --bad-if-metric ",
+                ("250", Color::Blue, Attributes::Bold),
+                ": Expected it to start with \">\" or \"<\""
+            )]
+        );
+        assert_eq!(
+            parse(&["--bad-if-metric=>250", "--metric-test=ci"]).unwrap_err(),
+            vec![mkline!("--metric-test=ci needs at least 2 --samples.")]
+        );
+        assert_eq!(
+            parse(&[
+                "--bad-if-metric=>+10%",
+                "--samples=2",
+                "--metric-test=mann-whitney"
+            ])
+            .unwrap_err(),
+            vec![mkline!(
+                "--metric-test=mann-whitney needs at least 3 --samples."
+            )]
+        );
+        assert!(parse(&[
+            "--bad-if-metric=>250",
+            "--samples=3",
+            "--metric-test=mann-whitney"
+        ])
+        .is_err());
+        assert!(parse(&["--bad-if-metric=>250", "--samples=0"]).is_err());
+        assert!(parse(&["--samples=3"]).is_err());
+    }
+
+    #[test]
+    fn test_notty_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--no-tty",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(args.command, vec!["foo", "--bar=$X"]);
+        assert_eq!(args.low, ib(5));
+        assert_eq!(args.high, ib(10));
+        assert_eq!(args.max_parallelism, None);
+        assert_eq!(args.no_tty, true);
+        assert_eq!(args.substitution_string, "$X");
+    }
+
+    #[test]
+    fn test_max_parallelism_parse() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--max-parallelism=2",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(args.command, vec!["foo", "--bar=$X"]);
+        assert_eq!(args.low, ib(5));
+        assert_eq!(args.high, ib(10));
+        assert_eq!(args.max_parallelism, Some(2usize));
+        assert_eq!(args.no_tty, false);
+        assert_eq!(args.substitution_string, "$X");
+    }
+
+    #[test]
+    fn test_command_gen_ok() {
+        let args =
+            CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "--bar=$X"]);
+
+        assert!(args.command_gen().is_ok());
+    }
+
+    #[test]
+    fn test_command_gen_err() {
+        let args = CliArgs::parse_from(["parasect", "--low=5", "--high=10"]);
+
+        assert!(args.command_gen().is_err());
+    }
+
+    #[test]
+    fn test_max_parallelism_ok() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--max-parallelism=2",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(args.max_parallelism(), Ok(2));
+    }
+
+    #[test]
+    fn test_max_parallelism_not_specified() {
+        let args =
+            CliArgs::parse_from(["parasect", "--low=5", "--high=10", "--", "foo", "--bar=$X"]);
+
+        assert!(args.max_parallelism().is_ok());
+    }
+
+    #[test]
+    fn test_max_parallelism_zero_err() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--max-parallelism=0",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(
+            args.max_parallelism(),
+            Err(vec![mkline!(
+                "The max parallelism cannot be 0. Specify a value >= 1 for --max-parallelism."
+            )])
+        );
+    }
 
     #[test]
     fn test_range() {
@@ -216,6 +1641,59 @@ mod tests {
         assert_eq!(args.range(), Ok(r(5, 10)));
     }
 
+    #[test]
+    fn test_range_aligned() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=100",
+            "--step=8",
+            "--offset=1",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(args.range(), Ok(r(9, 97)));
+        assert_eq!(args.alignment(), Ok(Alignment::new(8u8, 1)));
+    }
+
+    #[test]
+    fn test_range_aligned_too_few_err() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--step=8",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert_eq!(
+            args.range(),
+            Err(vec![mkline!(
+                "There must be at least two numbers matching the alignment 8k + 0 between 5 and 10."
+            )])
+        );
+    }
+
+    #[test]
+    fn test_step_zero_err() {
+        let args = CliArgs::parse_from([
+            "parasect",
+            "--low=5",
+            "--high=10",
+            "--step=0",
+            "--",
+            "foo",
+            "--bar=$X",
+        ]);
+
+        assert!(args.alignment().is_err());
+        assert!(args.range().is_err());
+    }
+
     #[test]
     fn test_range_invalid_err() {
         let args =
@@ -1,17 +1,18 @@
 use crate::parasect::types::ParasectError;
 use crate::ui::line::{mkline, Line};
-use crate::ui::segment::{Attributes, Color};
+use crate::ui::segment::{Attributes, Color, Segment};
+use ibig::IBig;
 
 pub type CliResult<T> = Result<T, Vec<Line>>;
 
 pub fn parasect_error_to_cli_error(e: ParasectError) -> Vec<Line> {
-    vec![match e {
-        ParasectError::PayloadError(e) => mkline!(
+    match e {
+        ParasectError::PayloadError(e) => vec![mkline!(
             ("Subprocess error", Color::Red, Attributes::Bold),
             ": ",
             (e, Color::Red)
-        ),
-        ParasectError::InconsistencyError(e) => mkline!(
+        )],
+        ParasectError::InconsistencyError(e) => vec![mkline!(
             (
                 "Inconsistent results from subprocess",
                 Color::Red,
@@ -19,8 +20,31 @@ pub fn parasect_error_to_cli_error(e: ParasectError) -> Vec<Line> {
             ),
             ": ",
             (e, Color::Red)
-        ),
-    }]
+        )],
+        ParasectError::AmbiguityError(e) => vec![mkline!(
+            (
+                "Could not pinpoint the first bad index",
+                Color::Yellow,
+                Attributes::Bold
+            ),
+            ": ",
+            (e, Color::Yellow)
+        )],
+        ParasectError::Interrupted {
+            latest_good,
+            earliest_bad,
+        } => {
+            let point = |p: Option<IBig>| match p {
+                Some(p) => Segment::new(p.to_string(), Color::Blue, Attributes::Bold),
+                None => Segment::from("none found yet"),
+            };
+            vec![
+                mkline!(("Interrupted", Color::Yellow, Attributes::Bold)),
+                mkline!("Latest good: ", point(latest_good)),
+                mkline!("Earliest bad: ", point(earliest_bad)),
+            ]
+        }
+    }
 }
 
 #[cfg(test)]
@@ -58,4 +82,39 @@ mod tests {
             )]
         )
     }
+
+    #[test]
+    fn test_parasect_error_to_cli_error_interrupted() {
+        let err = ParasectError::Interrupted {
+            latest_good: Some(IBig::from(13)),
+            earliest_bad: None,
+        };
+
+        assert_eq!(
+            parasect_error_to_cli_error(err),
+            vec![
+                mkline!(("Interrupted", Color::Yellow, Attributes::Bold)),
+                mkline!("Latest good: ", ("13", Color::Blue, Attributes::Bold)),
+                mkline!("Earliest bad: ", "none found yet"),
+            ]
+        )
+    }
+
+    #[test]
+    fn test_parasect_error_to_cli_error_ambiguity_error() {
+        let err = ParasectError::AmbiguityError("nope".into());
+
+        assert_eq!(
+            parasect_error_to_cli_error(err),
+            vec![mkline!(
+                (
+                    "Could not pinpoint the first bad index",
+                    Color::Yellow,
+                    Attributes::Bold
+                ),
+                ": ",
+                ("nope", Color::Yellow)
+            )]
+        )
+    }
 }
@@ -0,0 +1,37 @@
+use crate::threading::async_value::AsyncValue;
+use std::{io, process, ptr, thread};
+
+/// Catches SIGINT and SIGTERM for the rest of the process's life, and returns a value that's sent on the first one, so the search can stop cleanly.
+///
+/// A second signal exits right away. Children die with parasect in that case, since they're killed when the thread that spawned them exits.
+///
+/// Must be called before any other thread starts, since the signals are blocked in every thread (and waited for on a thread of their own) by way of the inherited signal mask. Children get a clean signal mask when they're spawned.
+pub fn catch_interrupts() -> AsyncValue<()> {
+    let interrupt = AsyncValue::new();
+
+    let signals = unsafe {
+        let mut signals = std::mem::zeroed();
+        libc::sigemptyset(&mut signals);
+        libc::sigaddset(&mut signals, libc::SIGINT);
+        libc::sigaddset(&mut signals, libc::SIGTERM);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &signals, ptr::null_mut());
+        signals
+    };
+
+    let interrupt_clone = interrupt.clone();
+    thread::spawn(move || loop {
+        let mut signal = 0;
+        if unsafe { libc::sigwait(&signals, &mut signal) } != 0 || interrupt_clone.send(()) {
+            continue;
+        }
+
+        // the UI doesn't get a chance to clean up, so at least give the cursor back
+        if termion::is_tty(&io::stdout()) {
+            print!("{}", termion::cursor::Show);
+        }
+        println!();
+        process::exit(128 + signal);
+    });
+
+    interrupt
+}
@@ -0,0 +1,288 @@
+use crate::cli::cli_args::CliArgs;
+use crate::cli::error_handling::CliResult;
+use crate::cli::outcome::SearchOutcome;
+use crate::parasect::probe_context::ProbeContext;
+use crate::parasect::types::ParasectError;
+use crate::probe::probe::ProbeRecord;
+use crate::ui::line::Line;
+use crate::util::json::{ibig_to_json, result_to_json};
+use ibig::IBig;
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn optional_point(p: Option<&IBig>) -> Value {
+    p.map_or(Value::Null, ibig_to_json)
+}
+
+fn path(p: &Path) -> Value {
+    json!(p.display().to_string())
+}
+
+fn error(e: &ParasectError) -> Value {
+    let (kind, message) = match e {
+        ParasectError::PayloadError(e) => ("payload_error", e.as_str()),
+        ParasectError::InconsistencyError(e) => ("inconsistency", e.as_str()),
+        ParasectError::AmbiguityError(e) => ("ambiguity", e.as_str()),
+        ParasectError::Interrupted { .. } => ("interrupted", "Interrupted"),
+    };
+    json!({"kind": kind, "message": message})
+}
+
+/// A probe's result is `cancelled` if it was stopped before it finished.
+fn probe(record: &ProbeRecord, outcome: &SearchOutcome) -> Value {
+    let result = record
+        .result
+        .as_ref()
+        .map(|r| result_to_json(r, &record.annotations));
+    let mut fields = match result {
+        Some(Value::Object(fields)) => fields,
+        _ => Map::from_iter([
+            ("verdict".into(), json!("cancelled")),
+            ("detail".into(), Value::Null),
+            ("annotations".into(), Value::Null),
+        ]),
+    };
+    fields.insert("point".into(), ibig_to_json(&record.point));
+    fields.insert("attempt".into(), json!(record.attempt));
+    fields.insert("worker".into(), json!(record.worker_id));
+    fields.insert(
+        "start_seconds".into(),
+        json!(record
+            .started
+            .saturating_duration_since(outcome.started)
+            .as_secs_f64()),
+    );
+    fields.insert(
+        "duration_seconds".into(),
+        json!(record.duration.as_secs_f64()),
+    );
+    Value::Object(fields)
+}
+
+fn by_point<T>(values: impl IntoIterator<Item = (T, Value)>) -> Map<String, Value>
+where
+    T: ToString,
+{
+    values
+        .into_iter()
+        .map(|(p, v)| (p.to_string(), v))
+        .collect()
+}
+
+fn outcome_fields(outcome: &SearchOutcome) -> Map<String, Value> {
+    let (latest_good, earliest_bad) = outcome.bracket();
+    let probe_time: Duration = outcome.probes.iter().map(|p| p.duration).sum();
+    let started_at = outcome
+        .started_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let diff = outcome.diff.as_ref().map(|(good, diff)| {
+        let (key, text) = match diff {
+            Ok(d) => ("diff", d),
+            Err(e) => ("error", e),
+        };
+        json!({"latest_good": ibig_to_json(good), key: text})
+    });
+
+    let fields = json!({
+        "first_bad": outcome.search.as_ref().ok().map(ibig_to_json),
+        "error": outcome.search.as_ref().err().map(error),
+        "bracket": {
+            "latest_good": optional_point(latest_good.as_ref()),
+            "earliest_bad": optional_point(earliest_bad.as_ref()),
+        },
+        "range": {
+            "low": optional_point(outcome.range.first().as_ref()),
+            "high": optional_point(outcome.range.last().as_ref()),
+        },
+        "probes": outcome.probes.iter().map(|p| probe(p, outcome)).collect::<Vec<_>>(),
+        "logs": by_point(outcome.logs.iter().map(|(p, logs)| {
+            (p, logs.written().into_iter().map(path).collect())
+        })),
+        "diff": diff,
+        "artifacts": by_point(outcome.artifacts.iter().map(|(p, dir)| (p, path(dir)))),
+        "timings": {
+            "started_at": started_at.as_secs_f64(),
+            "wall_seconds": outcome.elapsed.as_secs_f64(),
+            "probe_seconds": probe_time.as_secs_f64(),
+        },
+    });
+
+    match fields {
+        Value::Object(fields) => fields,
+        _ => unreachable!(),
+    }
+}
+
+/// Describes the whole run as a single JSON document, for `--output=json`.
+///
+/// `first_bad` is null if the search failed, and `error` says why. Its `kind` is one of `payload_error`, `inconsistency`, `ambiguity` and `interrupted`, or `setup` if the search couldn't start, in which case only the fields that don't depend on the search are there.
+pub fn run_to_json(
+    args: &CliArgs,
+    result: &CliResult<SearchOutcome>,
+    kept_scratch: &[(ProbeContext, PathBuf)],
+    warnings: &[Line],
+) -> Value {
+    let mut fields = match result {
+        Ok(outcome) => outcome_fields(outcome),
+        Err(e) => Map::from_iter([
+            ("first_bad".into(), Value::Null),
+            (
+                "error".into(),
+                json!({
+                    "kind": "setup",
+                    "message": e.iter().map(Line::plaintext).collect::<Vec<_>>().join("\n"),
+                }),
+            ),
+        ]),
+    };
+
+    fields.insert("command".into(), json!(args.display_command()));
+    fields.insert(
+        "substitution_string".into(),
+        json!(args.substitution_string),
+    );
+    fields.insert(
+        "kept_scratch".into(),
+        kept_scratch
+            .iter()
+            .map(|(ctx, dir)| {
+                json!({"point": ibig_to_json(&ctx.point), "attempt": ctx.attempt, "path": path(dir)})
+            })
+            .collect(),
+    );
+    fields.insert(
+        "warnings".into(),
+        warnings.iter().map(|w| json!(w.plaintext())).collect(),
+    );
+
+    Value::Object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::outcome::tests::outcome;
+    use crate::parasect::probe_context::SearchState;
+    use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+    use crate::parasect::types::ParasectPayloadResult::{Continue, Skip};
+    use crate::parasect::types::{Annotations, ParasectPayloadResult};
+    use crate::test_util::test_util::test_util::{ib, r};
+    use crate::ui::line::mkline;
+    use clap::Parser;
+    use std::collections::BTreeMap;
+
+    fn args() -> CliArgs {
+        CliArgs::parse_from([
+            "parasect",
+            "--low=0",
+            "--high=10",
+            "--output=json",
+            "--",
+            "foo",
+            "$X",
+        ])
+    }
+
+    #[test]
+    fn test_run_to_json() {
+        let mut outcome = outcome(Ok(ib(5)), &[(4, Continue(Good)), (5, Continue(Bad))]);
+        let record = |p: i32, attempt, result: Option<ParasectPayloadResult>| ProbeRecord {
+            point: ib(p),
+            attempt,
+            worker_id: 1,
+            started: outcome.started + Duration::from_secs(1),
+            duration: Duration::from_millis(500),
+            result,
+            annotations: Annotations::default(),
+            command: None,
+            output: Vec::new(),
+        };
+        outcome.probes = vec![
+            record(4, 1, Some(Continue(Good))),
+            record(5, 1, Some(Skip("flaky".into()))),
+            ProbeRecord {
+                annotations: Annotations {
+                    note: Some("segfault".into()),
+                    metrics: BTreeMap::new(),
+                },
+                ..record(5, 2, Some(Continue(Bad)))
+            },
+            record(7, 1, None),
+        ];
+        outcome.elapsed = Duration::from_secs(3);
+        let mut ctx = SearchState::new(r(0, 10)).start_probe(ib(5), 0);
+        ctx.attempt = 2;
+
+        let json = run_to_json(
+            &args(),
+            &Ok(outcome),
+            &[(ctx, PathBuf::from("/tmp/scratch"))],
+            &[mkline!("careful")],
+        );
+
+        assert_eq!(json["first_bad"], json!(5));
+        assert_eq!(json["error"], Value::Null);
+        assert_eq!(
+            json["bracket"],
+            json!({"latest_good": 4, "earliest_bad": 5})
+        );
+        assert_eq!(json["range"], json!({"low": 0, "high": 10}));
+        assert_eq!(json["command"], json!(["foo", "$X"]));
+        assert_eq!(json["substitution_string"], json!("$X"));
+        assert_eq!(
+            json["probes"][1],
+            json!({
+                "point": 5,
+                "attempt": 1,
+                "worker": 1,
+                "verdict": "skip",
+                "detail": "flaky",
+                "annotations": null,
+                "start_seconds": 1.0,
+                "duration_seconds": 0.5,
+            })
+        );
+        assert_eq!(json["probes"][2]["verdict"], json!("bad"));
+        assert_eq!(
+            json["probes"][2]["annotations"],
+            json!({"note": "segfault", "metrics": {}})
+        );
+        assert_eq!(json["probes"][3]["verdict"], json!("cancelled"));
+        assert_eq!(json["timings"]["wall_seconds"], json!(3.0));
+        assert_eq!(json["timings"]["probe_seconds"], json!(2.0));
+        assert_eq!(
+            json["kept_scratch"],
+            json!([{"point": 5, "attempt": 2, "path": "/tmp/scratch"}])
+        );
+        assert_eq!(json["warnings"], json!(["careful"]));
+    }
+
+    #[test]
+    fn test_run_to_json_errors() {
+        let interrupted = outcome(
+            Err(ParasectError::Interrupted {
+                latest_good: None,
+                earliest_bad: Some(ib(1) << 80),
+            }),
+            &[],
+        );
+        let json = run_to_json(&args(), &Ok(interrupted), &[], &[]);
+        assert_eq!(json["first_bad"], Value::Null);
+        assert_eq!(json["error"]["kind"], json!("interrupted"));
+        assert_eq!(
+            json["bracket"],
+            json!({"latest_good": null, "earliest_bad": "1208925819614629174706176"})
+        );
+
+        let json = run_to_json(&args(), &Err(vec![mkline!("no range")]), &[], &[]);
+        assert_eq!(
+            json["error"],
+            json!({"kind": "setup", "message": "no range"})
+        );
+        assert_eq!(json["probes"], Value::Null);
+        assert_eq!(json["command"], json!(["foo", "$X"]));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::cli::cli_args::CliArgs;
+use crate::cli::error_handling::parasect_error_to_cli_error;
+use crate::cli::outcome::SearchOutcome;
+use crate::cli::utils::parasect_result_to_lines;
+use crate::parasect::types::ParasectError;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadResult::{Continue, Skip, Stop};
+use crate::probe::output_diff::CommandOutput;
+use crate::probe::probe::ProbeRecord;
+use crate::util::string::xml_escape;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `time` as `YYYY-MM-DDTHH:MM:SS` in UTC, which is how JUnit wants its timestamps.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn property(name: &str, value: &str) -> String {
+    format!(
+        "<property name=\"{}\" value=\"{}\"/>",
+        xml_escape(name),
+        xml_escape(value)
+    )
+}
+
+/// What the search found, in a line: the first bad index, or why there isn't one.
+fn summary(args: &CliArgs, outcome: &SearchOutcome) -> String {
+    let lines = match &outcome.search {
+        Ok(first_bad) => parasect_result_to_lines(args, first_bad),
+        Err(e) => parasect_error_to_cli_error(e.clone()),
+    };
+    lines
+        .iter()
+        .map(|l| l.plaintext())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The name of a probe's testcase: its command, which tells the points apart.
+fn name(record: &ProbeRecord) -> String {
+    let name = match &record.command {
+        Some(command) => command.join(" "),
+        None => format!("point {}", record.point),
+    };
+    match record.attempt {
+        1 => name,
+        attempt => format!("{} (attempt {})", name, attempt),
+    }
+}
+
+/// What the checks printed to one stream, each under a header with its name if there are several.
+fn output(output: &[(String, CommandOutput)], stream: fn(&CommandOutput) -> &str) -> String {
+    match output {
+        [(_, o)] => stream(o).to_string(),
+        _ => output
+            .iter()
+            .map(|(name, o)| format!("==> {} <==\n{}", name, stream(o)))
+            .collect(),
+    }
+}
+
+/// The tag, type and message of the element that gives a probe its verdict, if it didn't pass.
+fn verdict(
+    record: &ProbeRecord,
+    interrupted: bool,
+) -> Option<(&'static str, &'static str, String)> {
+    let element = |tag, kind, message: &str| (tag, kind, message.to_string());
+    let note = record.annotations.note.as_deref();
+
+    match &record.result {
+        Some(Continue(Good)) => None,
+        Some(Continue(Bad)) => Some(element(
+            "failure",
+            "bad",
+            note.unwrap_or("The point is bad"),
+        )),
+        Some(Skip(detail)) => Some(element("skipped", "skip", detail)),
+        Some(Stop(detail)) => Some(element("error", "stop", detail)),
+        None if interrupted => Some(element(
+            "skipped",
+            "cancelled",
+            "Cancelled because the search was interrupted",
+        )),
+        None => Some(element(
+            "skipped",
+            "cancelled",
+            "Cancelled because the search no longer needed this point",
+        )),
+    }
+}
+
+fn testcase(record: &ProbeRecord, interrupted: bool) -> String {
+    let mut xml = format!(
+        "    <testcase name=\"{}\" classname=\"parasect\" time=\"{:.3}\">\n",
+        xml_escape(&name(record)),
+        record.duration.as_secs_f64()
+    );
+
+    let metrics = record.annotations.metrics.clone();
+    xml += "      <properties>\n";
+    for (name, value) in [
+        ("point".to_string(), record.point.to_string()),
+        ("worker".to_string(), record.worker_id.to_string()),
+    ]
+    .into_iter()
+    .chain(metrics)
+    {
+        let _ = writeln!(xml, "        {}", property(&name, &value));
+    }
+    xml += "      </properties>\n";
+
+    if let Some((tag, kind, message)) = verdict(record, interrupted) {
+        let _ = writeln!(
+            xml,
+            "      <{} type=\"{}\" message=\"{}\"/>",
+            tag,
+            kind,
+            xml_escape(&message)
+        );
+    }
+    for (tag, stream) in [
+        ("system-out", (|o| &o.stdout) as fn(&CommandOutput) -> &str),
+        ("system-err", |o| &o.stderr),
+    ] {
+        let text = output(&record.output, stream);
+        if !text.is_empty() {
+            let _ = writeln!(xml, "      <{}>{}</{}>", tag, xml_escape(&text), tag);
+        }
+    }
+
+    xml + "    </testcase>\n"
+}
+
+/// A JUnit XML report of a search, with every probe (including retries and cancelled ones) as a testcase.
+///
+/// Good points pass, bad ones fail, skipped and cancelled ones are skipped, and points that stopped the search are errors. What the search found is a property of the suite.
+pub fn outcome_to_junit(args: &CliArgs, outcome: &SearchOutcome) -> String {
+    let interrupted = matches!(outcome.search, Err(ParasectError::Interrupted { .. }));
+    let count = |tag: &str| {
+        outcome
+            .probes
+            .iter()
+            .filter_map(|p| verdict(p, interrupted))
+            .filter(|(t, _, _)| *t == tag)
+            .count()
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n");
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"parasect\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">",
+        outcome.probes.len(),
+        count("failure"),
+        count("error"),
+        count("skipped"),
+        outcome.elapsed.as_secs_f64(),
+        utc_timestamp(outcome.started_at)
+    );
+
+    let mut properties = vec![
+        ("command", args.display_command().join(" ")),
+        ("range", format!("{}", outcome.range)),
+        ("result", summary(args, outcome)),
+    ];
+    if let Ok(first_bad) = &outcome.search {
+        properties.push(("first_bad", first_bad.to_string()));
+    }
+    xml += "    <properties>\n";
+    for (name, value) in properties {
+        let _ = writeln!(xml, "      {}", property(name, &value));
+    }
+    xml += "    </properties>\n";
+
+    for record in &outcome.probes {
+        xml += &testcase(record, interrupted);
+    }
+    xml + "  </testsuite>\n</testsuites>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::outcome::tests::outcome;
+    use crate::parasect::types::{Annotations, ParasectPayloadResult};
+    use crate::test_util::test_util::test_util::ib;
+    use clap::Parser;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00");
+        assert_eq!(
+            utc_timestamp(UNIX_EPOCH + Duration::from_secs(1709210096)),
+            "2024-02-29T12:34:56"
+        );
+    }
+
+    #[test]
+    fn test_outcome_to_junit() {
+        let args = CliArgs::parse_from(["parasect", "--low=0", "--high=10", "--", "foo", "$X"]);
+        let mut outcome = outcome(Ok(ib(5)), &[(4, Continue(Good)), (5, Continue(Bad))]);
+        outcome.elapsed = Duration::from_millis(1500);
+        let record = |p: i32, attempt, result: Option<ParasectPayloadResult>| ProbeRecord {
+            point: ib(p),
+            attempt,
+            worker_id: 0,
+            started: outcome.started,
+            duration: Duration::from_millis(250),
+            result,
+            annotations: Annotations::default(),
+            command: Some(vec!["foo".into(), p.to_string()]),
+            output: Vec::new(),
+        };
+        outcome.probes = vec![
+            ProbeRecord {
+                output: vec![(
+                    "command".into(),
+                    CommandOutput {
+                        stdout: "ok <3\n".into(),
+                        stderr: String::new(),
+                    },
+                )],
+                ..record(4, 1, Some(Continue(Good)))
+            },
+            record(5, 1, Some(Skip("flaky".into()))),
+            ProbeRecord {
+                annotations: Annotations {
+                    note: Some("segfault".into()),
+                    metrics: [("rss".to_string(), "1M".to_string())].into(),
+                },
+                ..record(5, 2, Some(Continue(Bad)))
+            },
+            record(7, 1, None),
+        ];
+
+        assert_eq!(
+            outcome_to_junit(&args, &outcome),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="parasect" tests="4" failures="1" errors="0" skipped="2" time="1.500" timestamp="1970-01-01T00:00:00">
+    <properties>
+      <property name="command" value="foo $X"/>
+      <property name="range" value="[0, 10]"/>
+      <property name="result" value="Successfully parasected foo $X First bad index: 5"/>
+      <property name="first_bad" value="5"/>
+    </properties>
+    <testcase name="foo 4" classname="parasect" time="0.250">
+      <properties>
+        <property name="point" value="4"/>
+        <property name="worker" value="0"/>
+      </properties>
+      <system-out>ok &lt;3
+</system-out>
+    </testcase>
+    <testcase name="foo 5" classname="parasect" time="0.250">
+      <properties>
+        <property name="point" value="5"/>
+        <property name="worker" value="0"/>
+      </properties>
+      <skipped type="skip" message="flaky"/>
+    </testcase>
+    <testcase name="foo 5 (attempt 2)" classname="parasect" time="0.250">
+      <properties>
+        <property name="point" value="5"/>
+        <property name="worker" value="0"/>
+        <property name="rss" value="1M"/>
+      </properties>
+      <failure type="bad" message="segfault"/>
+    </testcase>
+    <testcase name="foo 7" classname="parasect" time="0.250">
+      <properties>
+        <property name="point" value="7"/>
+        <property name="worker" value="0"/>
+      </properties>
+      <skipped type="cancelled" message="Cancelled because the search no longer needed this point"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+
+    #[test]
+    fn test_outcome_to_junit_errors() {
+        let args = CliArgs::parse_from(["parasect", "--low=0", "--high=10", "--", "foo", "$X"]);
+        let mut outcome = outcome(
+            Err(ParasectError::Interrupted {
+                latest_good: None,
+                earliest_bad: None,
+            }),
+            &[],
+        );
+        outcome.probes = vec![
+            ProbeRecord {
+                point: ib(3),
+                attempt: 1,
+                worker_id: 0,
+                started: outcome.started,
+                duration: Duration::ZERO,
+                result: Some(Stop("no such file".into())),
+                annotations: Annotations::default(),
+                command: None,
+                output: Vec::new(),
+            },
+            ProbeRecord {
+                point: ib(6),
+                attempt: 1,
+                worker_id: 1,
+                started: outcome.started,
+                duration: Duration::ZERO,
+                result: None,
+                annotations: Annotations::default(),
+                command: None,
+                output: vec![
+                    ("a".into(), CommandOutput::default()),
+                    (
+                        "b".into(),
+                        CommandOutput {
+                            stdout: "out".into(),
+                            stderr: String::new(),
+                        },
+                    ),
+                ],
+            },
+        ];
+
+        let xml = outcome_to_junit(&args, &outcome);
+        assert!(xml.contains(r#"tests="2" failures="0" errors="1" skipped="1""#));
+        assert!(xml.contains(r#"<testcase name="point 3""#));
+        assert!(xml.contains(r#"<error type="stop" message="no such file"/>"#));
+        assert!(xml.contains(r#"message="Cancelled because the search was interrupted""#));
+        assert!(xml.contains("<system-out>==&gt; a &lt;==\n==&gt; b &lt;==\nout</system-out>"));
+        assert!(!xml.contains("first_bad"));
+    }
+}
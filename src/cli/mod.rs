@@ -1,3 +1,10 @@
 pub mod cli_args;
 pub mod error_handling;
+pub mod interrupt;
+pub mod json_output;
+pub mod junit_output;
+pub mod outcome;
+pub mod replay_args;
+pub mod report;
+pub mod trace;
 pub mod utils;
@@ -0,0 +1,284 @@
+use crate::cli::utils::latest_good;
+use crate::parasect::event::Event;
+use crate::parasect::event::Event::WorkerMessageSent;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadResult::{Continue, Skip, Stop};
+use crate::parasect::types::{Annotations, ParasectError, ParasectPayloadResult};
+use crate::parasect::worker::PointCompletionMessageType::{Cancelled, Completed, Started};
+use crate::probe::log_dir::ProbeLogs;
+use crate::probe::probe::ProbeRecord;
+use crate::range::numeric_range::NumericRange;
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// What a search found, whether or not it finished.
+pub struct SearchOutcome {
+    pub range: NumericRange,
+    /// The first bad point, or why the search failed.
+    pub search: Result<IBig, ParasectError>,
+    /// The result of every point that was probed.
+    pub results: BTreeMap<IBig, ParasectPayloadResult>,
+    /// The annotations of every point whose latest probe had any.
+    pub annotations: BTreeMap<IBig, Annotations>,
+    /// Every probe that ran, including retries and cancelled ones, in the order they finished.
+    pub probes: Vec<ProbeRecord>,
+    /// The log files of every point that was probed, if there's a log directory.
+    pub logs: BTreeMap<IBig, ProbeLogs>,
+    /// The latest good point and the diff of its output to that of the first bad point, if it was asked for.
+    pub diff: Option<(IBig, Result<String, String>)>,
+    /// Where the artifacts of each point that had any were collected to.
+    pub artifacts: BTreeMap<IBig, PathBuf>,
+    /// When the search started, for the start times of the probes.
+    pub started: Instant,
+    /// The same, as wall-clock time.
+    pub started_at: SystemTime,
+    pub elapsed: Duration,
+    /// Why the event log couldn't be written, if it couldn't.
+    pub event_log_error: Option<String>,
+    /// Every event of the search with when it happened, if they were recorded for a report or a trace.
+    pub events: Vec<(Duration, Event)>,
+}
+
+/// One probe, pieced together from the events of the worker that ran it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ProbeSpan {
+    pub worker: usize,
+    pub point: IBig,
+    pub start: Duration,
+    /// None if the probe never finished, e.g. because the search was interrupted.
+    pub end: Option<Duration>,
+    /// None if the probe was cancelled or never finished.
+    pub result: Option<ParasectPayloadResult>,
+    pub annotations: Annotations,
+}
+
+impl ProbeSpan {
+    /// The verdict's name, and the reason or note that goes with it, if any.
+    pub fn verdict(&self) -> (&'static str, Option<String>) {
+        let note = self.annotations.note.clone();
+
+        match (&self.result, self.end) {
+            (Some(Continue(Good)), _) => ("good", note),
+            (Some(Continue(Bad)), _) => ("bad", note),
+            (Some(Skip(detail)), _) => ("skip", Some(detail.clone())),
+            (Some(Stop(detail)), _) => ("stop", Some(detail.clone())),
+            (None, Some(_)) => ("cancelled", None),
+            (None, None) => ("unfinished", None),
+        }
+    }
+}
+
+impl SearchOutcome {
+    /// The latest good and earliest bad points known when the search ended.
+    ///
+    /// Once the search succeeded, the low end of the range counts as good even if it was never probed.
+    pub fn bracket(&self) -> (Option<IBig>, Option<IBig>) {
+        match &self.search {
+            Ok(first_bad) => (
+                latest_good(first_bad, &self.results)
+                    .cloned()
+                    .or(self.range.first())
+                    .filter(|good| good < first_bad),
+                Some(first_bad.clone()),
+            ),
+            Err(ParasectError::Interrupted {
+                latest_good,
+                earliest_bad,
+            }) => (latest_good.clone(), earliest_bad.clone()),
+            Err(_) => {
+                let with = |answer| {
+                    self.results
+                        .iter()
+                        .filter(move |(_, r)| **r == Continue(answer))
+                        .map(|(point, _)| point.clone())
+                };
+                (with(Good).max(), with(Bad).min())
+            }
+        }
+    }
+
+    /// Every probe as the interface saw it, from the recorded events: the start of each paired with its end, in the order they started.
+    pub fn probe_spans(&self) -> Vec<ProbeSpan> {
+        let mut spans: Vec<ProbeSpan> = Vec::new();
+        let mut running = BTreeMap::new();
+
+        for (time, event) in &self.events {
+            let message = match event {
+                WorkerMessageSent(message) => message,
+                _ => continue,
+            };
+            let key = (message.thread_id, message.point.clone());
+            match &message.msg_type {
+                Started => {
+                    running.insert(key, spans.len());
+                    spans.push(ProbeSpan {
+                        worker: message.thread_id,
+                        point: message.point.clone(),
+                        start: *time,
+                        end: None,
+                        result: None,
+                        annotations: Annotations::default(),
+                    });
+                }
+                Completed(..) | Cancelled => {
+                    if let Some(span) = running.remove(&key).map(|i| &mut spans[i]) {
+                        span.end = Some(*time);
+                        if let Completed(result, annotations) = &message.msg_type {
+                            span.result = Some(result.clone());
+                            span.annotations = annotations.clone();
+                        }
+                    }
+                }
+            }
+        }
+
+        spans
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::parasect::event::Event::RangeInvalidated;
+    use crate::parasect::worker::{PointCompletionMessageType, WorkerMessage};
+    use crate::test_util::test_util::test_util::{ib, r};
+
+    /// An outcome of a search of [0, 10] with the given results and nothing else.
+    pub fn outcome(
+        search: Result<IBig, ParasectError>,
+        results: &[(i32, ParasectPayloadResult)],
+    ) -> SearchOutcome {
+        SearchOutcome {
+            range: r(0, 10),
+            search,
+            results: results.iter().map(|(p, r)| (ib(*p), r.clone())).collect(),
+            annotations: BTreeMap::new(),
+            probes: Vec::new(),
+            logs: BTreeMap::new(),
+            diff: None,
+            artifacts: BTreeMap::new(),
+            started: Instant::now(),
+            started_at: SystemTime::UNIX_EPOCH,
+            elapsed: Duration::ZERO,
+            event_log_error: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    pub fn message(worker: usize, point: i32, msg_type: PointCompletionMessageType) -> Event {
+        WorkerMessageSent(WorkerMessage {
+            thread_id: worker,
+            point: ib(point),
+            left: r(0, point - 1),
+            right: r(point + 1, 10),
+            msg_type,
+        })
+    }
+
+    /// Two workers probing a search of [0, 10], up to when worker 0 started probing 4.
+    pub fn events() -> Vec<(Duration, Event)> {
+        vec![
+            (ms(0), message(0, 5, Started)),
+            (ms(0), message(1, 8, Started)),
+            (
+                ms(100),
+                message(0, 5, Completed(Continue(Bad), Annotations::default())),
+            ),
+            (ms(100), RangeInvalidated(r(5, 10), Bad)),
+            (ms(110), message(1, 8, Cancelled)),
+            (ms(120), message(0, 2, Started)),
+            (
+                ms(200),
+                message(
+                    0,
+                    2,
+                    Completed(Skip("flaky".into()), Annotations::default()),
+                ),
+            ),
+            (ms(210), message(1, 3, Started)),
+            (
+                ms(300),
+                message(1, 3, Completed(Continue(Good), Annotations::default())),
+            ),
+            (ms(300), RangeInvalidated(r(0, 3), Good)),
+            (ms(310), message(0, 4, Started)),
+        ]
+    }
+
+    fn outcome_with_events() -> SearchOutcome {
+        let mut outcome = outcome(Ok(ib(5)), &[]);
+        outcome.events = events();
+        outcome
+    }
+
+    #[test]
+    fn test_probe_spans() {
+        let verdicts = outcome_with_events()
+            .probe_spans()
+            .iter()
+            .map(|s| (s.worker, s.point.clone(), s.start, s.end, s.verdict()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            verdicts,
+            vec![
+                (0, ib(5), ms(0), Some(ms(100)), ("bad", None)),
+                (1, ib(8), ms(0), Some(ms(110)), ("cancelled", None)),
+                (
+                    0,
+                    ib(2),
+                    ms(120),
+                    Some(ms(200)),
+                    ("skip", Some("flaky".into()))
+                ),
+                (1, ib(3), ms(210), Some(ms(300)), ("good", None)),
+                (0, ib(4), ms(310), None, ("unfinished", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bracket() {
+        let results = [
+            (2, Continue(Good)),
+            (4, Skip("no".into())),
+            (6, Continue(Bad)),
+            (8, Continue(Bad)),
+        ];
+
+        assert_eq!(
+            outcome(Ok(ib(5)), &results).bracket(),
+            (Some(ib(2)), Some(ib(5)))
+        );
+        assert_eq!(
+            outcome(Ok(ib(1)), &[]).bracket(),
+            (Some(ib(0)), Some(ib(1)))
+        );
+        assert_eq!(outcome(Ok(ib(0)), &[]).bracket(), (None, Some(ib(0))));
+        assert_eq!(
+            outcome(
+                Err(ParasectError::AmbiguityError("skipped".into())),
+                &results
+            )
+            .bracket(),
+            (Some(ib(2)), Some(ib(6)))
+        );
+        assert_eq!(
+            outcome(
+                Err(ParasectError::Interrupted {
+                    latest_good: Some(ib(3)),
+                    earliest_bad: None
+                }),
+                &results
+            )
+            .bracket(),
+            (Some(ib(3)), None)
+        );
+    }
+}
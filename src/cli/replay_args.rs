@@ -0,0 +1,88 @@
+use crate::cli::error_handling::CliResult;
+use crate::ui::line::mkline;
+use crate::ui::replay::Recording;
+use clap::Parser;
+use std::fs;
+use std::path::PathBuf;
+
+/// Plays back a search recorded with --events=jsonl:FILE in the TTY interface.
+///
+/// Space pauses, the left and right arrow keys step through the events one at a time, up and down step by 10, Home and End jump to the start and the end, + and - change the speed, and q quits.
+#[derive(Parser, Debug)]
+pub struct ReplayArgs {
+    /// The recorded events.
+    file: PathBuf,
+
+    /// How many times faster than it was recorded to play the search back, e.g. 10 or 0.5.
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// Start paused, to step through the events one at a time.
+    #[arg(long, default_value_t = false)]
+    pub step_by_step: bool,
+}
+
+impl ReplayArgs {
+    pub fn recording(&self) -> CliResult<Recording> {
+        let text = fs::read_to_string(&self.file).map_err(|e| {
+            vec![mkline!(
+                "Failed to read ",
+                self.file.display().to_string(),
+                ": ",
+                e.to_string()
+            )]
+        })?;
+
+        Recording::parse(&text).map_err(|e| {
+            vec![mkline!(
+                "Failed to read ",
+                self.file.display().to_string(),
+                ": ",
+                e
+            )]
+        })
+    }
+
+    pub fn speed(&self) -> CliResult<f64> {
+        if self.speed.is_finite() && self.speed > 0.0 {
+            Ok(self.speed)
+        } else {
+            Err(vec![mkline!(
+                "The speed must be a positive number (got ",
+                self.speed.to_string(),
+                ")."
+            )])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_args_parse() {
+        let args = ReplayArgs::parse_from(["parasect replay", "events.jsonl"]);
+        assert_eq!(args.file, PathBuf::from("events.jsonl"));
+        assert_eq!(args.speed(), Ok(1.0));
+        assert!(!args.step_by_step);
+
+        let args = ReplayArgs::parse_from([
+            "parasect replay",
+            "--speed=0.5",
+            "--step-by-step",
+            "events.jsonl",
+        ]);
+        assert_eq!(args.speed(), Ok(0.5));
+        assert!(args.step_by_step);
+
+        let args = ReplayArgs::parse_from(["parasect replay", "--speed=0", "events.jsonl"]);
+        assert_eq!(
+            args.speed(),
+            Err(vec![mkline!(
+                "The speed must be a positive number (got 0)."
+            )])
+        );
+        assert!(args.recording().is_err());
+    }
+}
@@ -0,0 +1,595 @@
+use crate::cli::cli_args::CliArgs;
+use crate::cli::error_handling::parasect_error_to_cli_error;
+use crate::cli::outcome::{ProbeSpan, SearchOutcome};
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadResult;
+use crate::parasect::types::ParasectPayloadResult::Continue;
+use crate::parasect::types::{Annotations, ParasectError};
+use crate::range::numeric_range::NumericRange;
+use crate::ui::line::Line;
+use crate::ui::progress_bar::final_color_bar;
+use crate::ui::segment::Color;
+use crate::util::string::xml_escape;
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{self, Path, PathBuf};
+use std::time::Duration;
+
+/// How a report is written, picked by the extension of its file.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "html" | "htm" => Some(Self::Html),
+            "md" | "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Results that can't all be right if the command is monotonic: good points after bad ones, and points that were both.
+fn inconsistencies(spans: &[ProbeSpan], search: &Result<IBig, ParasectError>) -> Vec<String> {
+    let mut answers: BTreeMap<&IBig, Vec<_>> = BTreeMap::new();
+    for span in spans {
+        if let Some(Continue(answer)) = &span.result {
+            answers.entry(&span.point).or_default().push(*answer);
+        }
+    }
+
+    let mut found = Vec::new();
+    if let Err(ParasectError::InconsistencyError(e)) = search {
+        found.push(e.clone());
+    }
+    for (point, a) in &answers {
+        if a.contains(&Good) && a.contains(&Bad) {
+            found.push(format!("{} was probed as both good and bad", point));
+        }
+    }
+    let earliest_bad = answers.iter().find(|(_, a)| a.contains(&Bad));
+    if let Some((bad, _)) = earliest_bad {
+        for (good, _) in answers
+            .iter()
+            .filter(|(p, a)| p > &bad && a.contains(&Good))
+        {
+            found.push(format!("{} was good, but {} before it was bad", good, bad));
+        }
+    }
+    found
+}
+
+fn seconds(d: Duration) -> String {
+    format!("{:.2}s", d.as_secs_f64())
+}
+
+fn link(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// What a point was found to be, for the evidence of the answer.
+struct Evidence {
+    label: &'static str,
+    point: IBig,
+    result: Option<ParasectPayloadResult>,
+    annotations: Annotations,
+    logs: Vec<PathBuf>,
+}
+
+/// Everything a report shows, in the order it shows it.
+struct Report {
+    command: String,
+    range: NumericRange,
+    elapsed: Duration,
+    answer: Vec<String>,
+    evidence: Vec<Evidence>,
+    diff: Option<(IBig, IBig, Result<String, String>)>,
+    bar: Line,
+    spans: Vec<ProbeSpan>,
+    logs: BTreeMap<IBig, Vec<PathBuf>>,
+    inconsistencies: Vec<String>,
+}
+
+impl Report {
+    fn new(args: &CliArgs, outcome: &SearchOutcome, bar_width: usize) -> Self {
+        let (latest_good, earliest_bad) = outcome.bracket();
+        let logs: BTreeMap<_, _> = outcome
+            .logs
+            .iter()
+            .map(|(point, logs)| {
+                (
+                    point.clone(),
+                    logs.written().iter().map(|f| link(f)).collect(),
+                )
+            })
+            .collect();
+
+        let answer = match &outcome.search {
+            Ok(first_bad) => vec![format!("The first bad point is {}.", first_bad)],
+            Err(e) => parasect_error_to_cli_error(e.clone())
+                .iter()
+                .map(Line::plaintext)
+                .collect(),
+        };
+        let evidence = [("Latest good", latest_good), ("Earliest bad", earliest_bad)]
+            .into_iter()
+            .filter_map(|(label, point)| {
+                let point = point?;
+                Some(Evidence {
+                    label,
+                    result: outcome.results.get(&point).cloned(),
+                    annotations: outcome.annotations.get(&point).cloned().unwrap_or_default(),
+                    logs: logs.get(&point).cloned().unwrap_or_default(),
+                    point,
+                })
+            })
+            .collect();
+        let diff = match (&outcome.search, &outcome.diff) {
+            (Ok(bad), Some((good, diff))) => Some((good.clone(), bad.clone(), diff.clone())),
+            _ => None,
+        };
+
+        let spans = outcome.probe_spans();
+        Self {
+            command: args.display_command().join(" "),
+            range: outcome.range.clone(),
+            elapsed: outcome.elapsed,
+            answer,
+            evidence,
+            diff,
+            bar: final_color_bar(
+                &outcome.range,
+                &args.alignment().unwrap_or_default(),
+                outcome.events.iter().map(|(_, e)| e.clone()),
+                bar_width,
+            ),
+            inconsistencies: inconsistencies(&spans, &outcome.search),
+            spans,
+            logs,
+        }
+    }
+
+    /// How long the timeline lasts: until the last probe ended.
+    fn timeline_length(&self) -> Duration {
+        self.spans
+            .iter()
+            .flat_map(|s| [Some(s.start), s.end])
+            .flatten()
+            .max()
+            .unwrap_or_default()
+            .max(Duration::from_millis(1))
+    }
+
+    fn workers(&self) -> Vec<usize> {
+        let mut workers: Vec<_> = self.spans.iter().map(|s| s.worker).collect();
+        workers.sort();
+        workers.dedup();
+        workers
+    }
+}
+
+fn evidence_result(evidence: &Evidence) -> String {
+    match &evidence.result {
+        Some(r) if evidence.annotations.is_empty() => r.to_string(),
+        Some(r) => format!("{} ({})", r, evidence.annotations),
+        None => "not probed".into(),
+    }
+}
+
+fn css_color(color: Color) -> &'static str {
+    match color {
+        Color::Green => "#2da44e",
+        Color::Red => "#cf222e",
+        Color::Yellow => "#d4a72c",
+        Color::Blue => "#0969da",
+        Color::Magenta => "#8250df",
+        Color::Default => "#8c959f",
+    }
+}
+
+fn verdict_color(verdict: &str) -> Color {
+    match verdict {
+        "good" => Color::Green,
+        "bad" => Color::Red,
+        "skip" => Color::Yellow,
+        "stop" => Color::Magenta,
+        _ => Color::Default,
+    }
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+code, pre { font-family: monospace; }
+pre { background: #f6f8fa; padding: 1em; overflow-x: auto; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #d0d7de; padding: 0.25em 0.5em; text-align: left; }
+.bar { font-family: monospace; letter-spacing: -0.1em; }
+.worker { position: relative; height: 1.5em; margin: 0.25em 0; background: #f6f8fa; }
+.probe { position: absolute; top: 0; height: 100%; min-width: 2px; overflow: hidden; color: white; font-size: 0.75em; line-height: 2em; }";
+
+fn to_html(report: &Report) -> String {
+    let e = xml_escape;
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>parasect: {}</title>\n<style>\n{}\n</style>\n</head>\n<body>",
+        e(&report.command),
+        HTML_STYLE
+    );
+    let _ = writeln!(html, "<h1>parasect report</h1>");
+    let _ = writeln!(
+        html,
+        "<p>Command: <code>{}</code><br>Range: {}<br>Took {}</p>",
+        e(&report.command),
+        report.range,
+        seconds(report.elapsed)
+    );
+
+    let _ = writeln!(html, "<h2>Answer</h2>");
+    for line in &report.answer {
+        let _ = writeln!(html, "<p>{}</p>", e(line));
+    }
+    if !report.evidence.is_empty() {
+        html += "<ul>\n";
+        for evidence in &report.evidence {
+            let logs = evidence
+                .logs
+                .iter()
+                .map(|f| format!(" <a href=\"{0}\">{0}</a>", e(&f.display().to_string())))
+                .collect::<String>();
+            let _ = writeln!(
+                html,
+                "<li>{} {}: {}{}</li>",
+                evidence.label,
+                evidence.point,
+                e(&evidence_result(evidence)),
+                logs
+            );
+        }
+        html += "</ul>\n";
+    }
+    if let Some((good, bad, diff)) = &report.diff {
+        let _ = writeln!(html, "<h3>Output of {} compared to {}</h3>", good, bad);
+        let _ = match diff {
+            Ok(diff) if diff.is_empty() => writeln!(html, "<p>The outputs are the same.</p>"),
+            Ok(diff) => writeln!(html, "<pre>{}</pre>", e(diff)),
+            Err(err) => writeln!(html, "<p>{}</p>", e(err)),
+        };
+    }
+
+    let _ = writeln!(html, "<h2>Progress</h2>\n<div class=\"bar\">");
+    for segment in report.bar.iter() {
+        let _ = write!(
+            html,
+            "<span style=\"color: {}\">{}</span>",
+            css_color(segment.color()),
+            e(segment.content())
+        );
+    }
+    let (low, high) = report.range.as_tuple().unwrap_or_default();
+    let _ = writeln!(html, "</div>\n<p>{} to {}</p>", low, high);
+
+    let _ = writeln!(html, "<h2>Timeline</h2>");
+    let length = report.timeline_length().as_secs_f64();
+    for worker in report.workers() {
+        let _ = writeln!(html, "<div>Worker {}</div>\n<div class=\"worker\">", worker);
+        for span in report.spans.iter().filter(|s| s.worker == worker) {
+            let end = span.end.unwrap_or(report.timeline_length());
+            let (verdict, _) = span.verdict();
+            let _ = writeln!(
+                html,
+                "<div class=\"probe\" style=\"left: {:.2}%; width: {:.2}%; background: {}\" title=\"{}: {}, {} to {}\">{}</div>",
+                span.start.as_secs_f64() / length * 100.0,
+                (end - span.start).as_secs_f64() / length * 100.0,
+                css_color(verdict_color(verdict)),
+                span.point,
+                verdict,
+                seconds(span.start),
+                seconds(end),
+                span.point
+            );
+        }
+        html += "</div>\n";
+    }
+
+    let _ = writeln!(
+        html,
+        "<h2>Probes</h2>\n<table>\n<tr><th>Point</th><th>Worker</th><th>Verdict</th><th>Detail</th><th>Started</th><th>Duration</th><th>Logs</th></tr>"
+    );
+    for span in &report.spans {
+        let (verdict, detail) = span.verdict();
+        let logs = report
+            .logs
+            .get(&span.point)
+            .into_iter()
+            .flatten()
+            .map(|f| {
+                let name = f.file_name().unwrap_or_default().to_string_lossy();
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    e(&f.display().to_string()),
+                    e(&name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td style=\"color: {}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            span.point,
+            span.worker,
+            css_color(verdict_color(verdict)),
+            verdict,
+            e(&detail.unwrap_or_default()),
+            seconds(span.start),
+            span.end.map(|end| seconds(end - span.start)).unwrap_or_default(),
+            logs
+        );
+    }
+    html += "</table>\n";
+
+    let _ = writeln!(html, "<h2>Inconsistencies</h2>");
+    if report.inconsistencies.is_empty() {
+        html += "<p>None.</p>\n";
+    } else {
+        html += "<ul>\n";
+        for inconsistency in &report.inconsistencies {
+            let _ = writeln!(html, "<li>{}</li>", e(inconsistency));
+        }
+        html += "</ul>\n";
+    }
+
+    html + "</body>\n</html>\n"
+}
+
+/// Keeps `s` from breaking out of a Markdown table cell or inline code.
+fn md_cell(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+fn md_block(color: Color) -> &'static str {
+    match color {
+        Color::Green => "🟩",
+        Color::Red => "🟥",
+        Color::Yellow => "🟨",
+        Color::Blue => "🟦",
+        Color::Magenta => "🟪",
+        Color::Default => "⬜",
+    }
+}
+
+/// A code fence longer than any run of backticks in `text`, so nothing in it can close the fence early.
+fn md_fence(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn to_markdown(report: &Report) -> String {
+    let mut md = String::from("# parasect report\n\n");
+    let _ = writeln!(
+        md,
+        "- Command: `{}`\n- Range: {}\n- Took {}\n",
+        report.command.replace('`', "'"),
+        report.range,
+        seconds(report.elapsed)
+    );
+
+    md += "## Answer\n\n";
+    for line in &report.answer {
+        let _ = writeln!(md, "{}\n", line);
+    }
+    for evidence in &report.evidence {
+        let logs = evidence
+            .logs
+            .iter()
+            .map(|f| format!(" [{0}](<{0}>)", f.display()))
+            .collect::<String>();
+        let _ = writeln!(
+            md,
+            "- {} {}: {}{}",
+            evidence.label,
+            evidence.point,
+            evidence_result(evidence),
+            logs
+        );
+    }
+    if !report.evidence.is_empty() {
+        md += "\n";
+    }
+    if let Some((good, bad, diff)) = &report.diff {
+        let _ = writeln!(md, "### Output of {} compared to {}\n", good, bad);
+        let _ = match diff {
+            Ok(diff) if diff.is_empty() => writeln!(md, "The outputs are the same.\n"),
+            Ok(diff) => {
+                let fence = md_fence(diff);
+                writeln!(md, "{}diff\n{}{}\n", fence, diff, fence)
+            }
+            Err(err) => writeln!(md, "{}\n", err),
+        };
+    }
+
+    md += "## Progress\n\n";
+    for segment in report.bar.iter() {
+        for _ in 0..segment.len() {
+            md += md_block(segment.color());
+        }
+    }
+    let (low, high) = report.range.as_tuple().unwrap_or_default();
+    let _ = writeln!(md, "\n\n{} to {}\n", low, high);
+
+    md += "## Timeline\n\n";
+    for worker in report.workers() {
+        let probes = report
+            .spans
+            .iter()
+            .filter(|s| s.worker == worker)
+            .map(|s| {
+                let end = s.end.map(seconds).unwrap_or_else(|| "…".into());
+                format!(
+                    "{} {} ({}–{})",
+                    s.point,
+                    s.verdict().0,
+                    seconds(s.start),
+                    end
+                )
+            })
+            .collect::<Vec<_>>();
+        let _ = writeln!(md, "- Worker {}: {}", worker, probes.join(" → "));
+    }
+
+    md += "\n## Probes\n\n| Point | Worker | Verdict | Detail | Started | Duration | Logs |\n|---|---|---|---|---|---|---|\n";
+    for span in &report.spans {
+        let (verdict, detail) = span.verdict();
+        let logs = report
+            .logs
+            .get(&span.point)
+            .into_iter()
+            .flatten()
+            .map(|f| {
+                let name = f.file_name().unwrap_or_default().to_string_lossy();
+                format!("[{}](<{}>)", name, f.display())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            md,
+            "| {} | {} | {} {} | {} | {} | {} | {} |",
+            span.point,
+            span.worker,
+            md_block(verdict_color(verdict)),
+            verdict,
+            md_cell(&detail.unwrap_or_default()),
+            seconds(span.start),
+            span.end
+                .map(|end| seconds(end - span.start))
+                .unwrap_or_default(),
+            md_cell(&logs)
+        );
+    }
+
+    md += "\n## Inconsistencies\n\n";
+    if report.inconsistencies.is_empty() {
+        md += "None.\n";
+    } else {
+        for inconsistency in &report.inconsistencies {
+            let _ = writeln!(md, "- {}", inconsistency);
+        }
+    }
+    md
+}
+
+/// A report of a search for postmortems: the command, the answer and what it's based on, the final progress bar, a timeline of the probes of each worker, every probe, and the inconsistencies that were found.
+///
+/// The probes and the progress bar come from the events the interface showed, which `outcome` must have recorded.
+pub fn outcome_to_report(args: &CliArgs, outcome: &SearchOutcome, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Html => to_html(&Report::new(args, outcome, 100)),
+        ReportFormat::Markdown => to_markdown(&Report::new(args, outcome, 40)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::outcome::tests::{events, outcome};
+    use crate::test_util::test_util::test_util::ib;
+    use clap::Parser;
+
+    #[test]
+    fn test_report_format() {
+        assert_eq!(
+            ReportFormat::from_path("run.html"),
+            Some(ReportFormat::Html)
+        );
+        assert_eq!(
+            ReportFormat::from_path("a/run.MD"),
+            Some(ReportFormat::Markdown)
+        );
+        assert_eq!(ReportFormat::from_path("run.txt"), None);
+        assert_eq!(ReportFormat::from_path("run"), None);
+    }
+
+    #[test]
+    fn test_inconsistencies() {
+        let span = |point: i32, result| ProbeSpan {
+            worker: 0,
+            point: ib(point),
+            start: Duration::ZERO,
+            end: Some(Duration::ZERO),
+            result: Some(result),
+            annotations: Annotations::default(),
+        };
+        let spans = [
+            span(2, Continue(Good)),
+            span(4, Continue(Bad)),
+            span(6, Continue(Good)),
+            span(6, Continue(Bad)),
+            span(8, Continue(Good)),
+        ];
+
+        assert_eq!(
+            inconsistencies(&spans[..2], &Ok(ib(4))),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            inconsistencies(
+                &spans,
+                &Err(ParasectError::InconsistencyError("nope".into()))
+            ),
+            vec![
+                "nope".to_string(),
+                "6 was probed as both good and bad".into(),
+                "6 was good, but 4 before it was bad".into(),
+                "8 was good, but 4 before it was bad".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_md_fence() {
+        assert_eq!(md_fence("-a\n+b\n"), "```");
+        assert_eq!(md_fence("-`a`\n+```b````\n"), "`````");
+    }
+
+    #[test]
+    fn test_outcome_to_report() {
+        let args = CliArgs::parse_from(["parasect", "--low=0", "--high=10", "--", "foo", "$X"]);
+        let mut outcome = outcome(Ok(ib(5)), &[(3, Continue(Good)), (5, Continue(Bad))]);
+        outcome.events = events();
+        outcome.diff = Some((ib(3), Ok("-a\n+b\n".into())));
+
+        let md = outcome_to_report(&args, &outcome, ReportFormat::Markdown);
+        for expected in [
+            "- Command: `foo $X`\n- Range: [0, 10]\n",
+            "The first bad point is 5.\n\n- Latest good 3: Good\n- Earliest bad 5: Bad\n",
+            "### Output of 3 compared to 5\n\n```diff\n-a\n+b\n```\n",
+            "- Worker 0: 5 bad (0.00s–0.10s) → 2 skip (0.12s–0.20s) → 4 unfinished (0.31s–…)\n",
+            "| 8 | 1 | ⬜ cancelled |  | 0.00s | 0.11s |  |\n",
+            "| 2 | 0 | 🟨 skip | flaky | 0.12s | 0.08s |  |\n",
+            "## Inconsistencies\n\nNone.\n",
+        ] {
+            assert!(md.contains(expected), "{:?} isn't in\n{}", expected, md);
+        }
+        // [0, 3] is good and [5, 10] is bad, and 4 was never answered
+        assert!(md.contains(&format!(
+            "{}{}{}",
+            "🟩".repeat(16),
+            "🟦".repeat(4),
+            "🟥".repeat(20)
+        )));
+
+        let html = outcome_to_report(&args, &outcome, ReportFormat::Html);
+        for expected in [
+            "<title>parasect: foo $X</title>",
+            "<pre>-a\n+b\n</pre>",
+            "<tr><td>2</td><td>0</td><td style=\"color: #d4a72c\">skip</td><td>flaky</td><td>0.12s</td><td>0.08s</td><td></td></tr>",
+            "title=\"4: unfinished, 0.31s to 0.31s\"",
+        ] {
+            assert!(html.contains(expected), "{:?} isn't in\n{}", expected, html);
+        }
+    }
+}
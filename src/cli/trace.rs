@@ -0,0 +1,212 @@
+use crate::cli::cli_args::CliArgs;
+use crate::cli::outcome::SearchOutcome;
+use crate::parasect::event::Event::{ParasectCancelled, ProbeNoted, RangeInvalidated};
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::util::json::{ibig_to_json, range_to_json};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// The thread that events about the whole search go on. Each worker gets the thread after it, i.e. its id plus one.
+const SEARCH_THREAD: usize = 0;
+
+fn micros(d: Duration) -> u64 {
+    d.as_micros() as u64
+}
+
+/// Colors that Chrome's trace viewer reserves, for the verdicts.
+fn color(verdict: &str) -> &'static str {
+    match verdict {
+        "good" => "good",
+        "bad" => "bad",
+        "skip" => "yellow",
+        "stop" => "terrible",
+        "cancelled" => "grey",
+        _ => "generic_work",
+    }
+}
+
+fn instant(name: String, time: Duration, thread: usize, scope: &str, args: Value) -> Value {
+    json!({
+        "name": name,
+        "ph": "i",
+        "s": scope,
+        "ts": micros(time),
+        "pid": 1,
+        "tid": thread,
+        "args": args,
+    })
+}
+
+/// A Chrome Trace Event document of the search, which Perfetto and `chrome://tracing` can open: a span on each worker's thread for every probe it ran, and instant events for cancellations, notes, range invalidations and the search being aborted.
+///
+/// `otherData` says how busy the workers were and how much of their time went to probes that were cancelled. The spans come from the events the interface showed, which `outcome` must have recorded.
+pub fn outcome_to_trace(args: &CliArgs, outcome: &SearchOutcome) -> Value {
+    let spans = outcome.probe_spans();
+    let recording_end = outcome.events.last().map(|(t, _)| *t).unwrap_or_default();
+    let mut workers: Vec<_> = spans.iter().map(|s| s.worker).collect();
+    workers.sort();
+    workers.dedup();
+
+    let mut events = vec![
+        json!({"name": "process_name", "ph": "M", "pid": 1, "args": {"name": "parasect"}}),
+        json!({"name": "thread_name", "ph": "M", "pid": 1, "tid": SEARCH_THREAD, "args": {"name": "Search"}}),
+    ];
+    events.extend(workers.iter().map(|w| {
+        json!({"name": "thread_name", "ph": "M", "pid": 1, "tid": w + 1, "args": {"name": format!("Worker {}", w)}})
+    }));
+
+    let (mut busy, mut cancelled) = (Duration::ZERO, Duration::ZERO);
+    for span in &spans {
+        let (verdict, detail) = span.verdict();
+        let end = span.end.unwrap_or(recording_end);
+        busy += end - span.start;
+        if verdict == "cancelled" {
+            cancelled += end - span.start;
+        }
+
+        events.push(json!({
+            "name": format!("probe {}", span.point),
+            "cat": verdict,
+            "cname": color(verdict),
+            "ph": "X",
+            "ts": micros(span.start),
+            "dur": micros(end - span.start),
+            "pid": 1,
+            "tid": span.worker + 1,
+            "args": {"point": ibig_to_json(&span.point), "verdict": verdict, "detail": detail},
+        }));
+        if verdict == "cancelled" {
+            events.push(instant(
+                format!("cancelled {}", span.point),
+                end,
+                span.worker + 1,
+                "t",
+                json!({"point": ibig_to_json(&span.point)}),
+            ));
+        }
+    }
+
+    for (time, event) in &outcome.events {
+        events.push(match event {
+            RangeInvalidated(range, answer) => {
+                let answer = match answer {
+                    Good => "good",
+                    Bad => "bad",
+                };
+                instant(
+                    format!("{} {}", range, answer),
+                    *time,
+                    SEARCH_THREAD,
+                    "p",
+                    json!({"range": range_to_json(range), "answer": answer}),
+                )
+            }
+            ParasectCancelled(reason) => instant(
+                "aborted".into(),
+                *time,
+                SEARCH_THREAD,
+                "g",
+                json!({"reason": reason}),
+            ),
+            ProbeNoted(note) => instant(
+                note.source.clone(),
+                *time,
+                note.worker_id + 1,
+                "t",
+                json!({
+                    "point": ibig_to_json(&note.point),
+                    "answer": note.answer.map(|a| if a == Good { "good" } else { "bad" }),
+                    "detail": note.detail,
+                }),
+            ),
+            _ => continue,
+        });
+    }
+
+    let capacity = recording_end.as_secs_f64() * workers.len() as f64;
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+        "otherData": {
+            "command": args.display_command().join(" "),
+            "range": range_to_json(&outcome.range),
+            "wall_seconds": recording_end.as_secs_f64(),
+            "busy_seconds": busy.as_secs_f64(),
+            "cancelled_seconds": cancelled.as_secs_f64(),
+            "utilization": if capacity > 0.0 { busy.as_secs_f64() / capacity } else { 0.0 },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::outcome::tests::{events, ms, outcome};
+    use crate::parasect::event::ProbeNote;
+    use crate::test_util::test_util::test_util::{ib, r};
+    use clap::Parser;
+
+    #[test]
+    fn test_outcome_to_trace() {
+        let args = CliArgs::parse_from(["parasect", "--low=0", "--high=10", "--", "foo", "$X"]);
+        let mut outcome = outcome(Ok(ib(5)), &[]);
+        outcome.events = events();
+        outcome.events.push((
+            ms(320),
+            ProbeNoted(ProbeNote {
+                worker_id: 0,
+                point: ib(4),
+                source: "check lint".into(),
+                answer: Some(Bad),
+                detail: "exit status 1".into(),
+            }),
+        ));
+        outcome
+            .events
+            .push((ms(400), ParasectCancelled("Interrupted".into())));
+
+        let trace = outcome_to_trace(&args, &outcome);
+        let events = trace["traceEvents"].as_array().unwrap();
+        let find = |name: &str| {
+            events
+                .iter()
+                .find(|e| e["name"] == name)
+                .unwrap_or_else(|| panic!("no {} in {:#}", name, trace))
+        };
+
+        assert_eq!(find("thread_name")["args"]["name"], json!("Search"));
+        assert_eq!(events.iter().filter(|e| e["ph"] == "M").count(), 4);
+        assert_eq!(
+            find("probe 5"),
+            &json!({
+                "name": "probe 5",
+                "cat": "bad",
+                "cname": "bad",
+                "ph": "X",
+                "ts": 0,
+                "dur": 100000,
+                "pid": 1,
+                "tid": 1,
+                "args": {"point": 5, "verdict": "bad", "detail": null},
+            })
+        );
+        assert_eq!(find("probe 8")["cat"], json!("cancelled"));
+        assert_eq!(find("cancelled 8")["ts"], json!(110000));
+        assert_eq!(find("cancelled 8")["tid"], json!(2));
+        // 4 never finished, so it lasts until the end of the recording
+        assert_eq!(find("probe 4")["dur"], json!(90000));
+        assert_eq!(
+            find(&format!("{} good", r(0, 3)))["args"],
+            json!({"range": [0, 3], "answer": "good"})
+        );
+        assert_eq!(find("check lint")["args"]["answer"], json!("bad"));
+        assert_eq!(find("aborted")["s"], json!("g"));
+
+        // 100 + 110 + 80 + 90 + 90 ms over 2 workers for 400ms
+        let other = &trace["otherData"];
+        assert_eq!(other["busy_seconds"], json!(0.47));
+        assert_eq!(other["cancelled_seconds"], json!(0.11));
+        assert_eq!(other["wall_seconds"], json!(0.4));
+        assert!((other["utilization"].as_f64().unwrap() - 0.5875).abs() < 1e-9);
+    }
+}
@@ -1,8 +1,18 @@
 use crate::cli::cli_args::CliArgs;
 use crate::collections::collect_collection::CollectVec;
+use crate::parasect::probe_context::ProbeContext;
+use crate::parasect::types::ParasectPayloadAnswer::Good;
+use crate::parasect::types::ParasectPayloadResult::Continue;
+use crate::parasect::types::{Annotations, ParasectPayloadResult};
+use crate::probe::log_dir::ProbeLogs;
+use crate::template::template_string::TemplatePiece::{Literal, Substitution};
+use crate::template::template_string::TemplateString;
 use crate::ui::line::{mkline, Line};
 use crate::ui::segment::{Attributes, Color, Segment};
+use crate::util::string::shell_quote;
 use ibig::IBig;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 pub fn command_line_unhighlighted(cmd: &Vec<String>) -> Line {
     let mut v = cmd
@@ -29,12 +39,31 @@ pub fn command_line(cmd: &Vec<String>, substitution_string: &str) -> Line {
     let mut segs = cmd
         .iter()
         .flat_map(|s| {
-            let mut v = s
-                .split(substitution_string)
-                .flat_map(|s2| [Segment::from(s2), insertion_segment.clone()])
-                .collect_vec();
+            let mut v = match TemplateString::parse(
+                s,
+                substitution_string,
+                &ProbeContext::VARIABLE_NAMES,
+            ) {
+                Ok(template) => template
+                    .pieces()
+                    .iter()
+                    .map(|piece| match piece {
+                        Literal(l) => Segment::from(l),
+                        Substitution(p) => {
+                            Segment::new(p.source().to_string(), Color::Blue, Attributes::Bold)
+                        }
+                    })
+                    .collect_vec(),
+                Err(_) => {
+                    let mut v = s
+                        .split(substitution_string)
+                        .flat_map(|s2| [Segment::from(s2), insertion_segment.clone()])
+                        .collect_vec();
+                    v.pop();
+                    v
+                }
+            };
 
-            v.pop();
             v.push(Segment::from(" "));
             v
         })
@@ -49,7 +78,7 @@ pub fn parasect_result_to_lines(args: &CliArgs, last_bad: &IBig) -> Vec<Line> {
     vec![
         Line::join([
             mkline!(("Successfully parasected", Color::Green), " "),
-            command_line(&args.command, &args.substitution_string),
+            command_line(&args.display_command(), &args.substitution_string),
         ]),
         mkline!(
             "First bad index: ",
@@ -58,14 +87,226 @@ pub fn parasect_result_to_lines(args: &CliArgs, last_bad: &IBig) -> Vec<Line> {
     ]
 }
 
+/// The latest point before `first_bad` that was probed and found good, if any.
+pub fn latest_good<'a>(
+    first_bad: &IBig,
+    results: &'a BTreeMap<IBig, ParasectPayloadResult>,
+) -> Option<&'a IBig> {
+    results
+        .range(..first_bad)
+        .rev()
+        .find(|(_, r)| **r == Continue(Good))
+        .map(|(point, _)| point)
+}
+
+/// Lists the annotations of the latest good and first bad points, for the points that have any.
+pub fn boundary_annotations_to_lines(
+    first_bad: &IBig,
+    results: &BTreeMap<IBig, ParasectPayloadResult>,
+    annotations: &BTreeMap<IBig, Annotations>,
+) -> Vec<Line> {
+    let latest_good = latest_good(first_bad, results).and_then(|p| annotations.get_key_value(p));
+    let first_bad = annotations.get_key_value(first_bad);
+
+    [("Latest good", latest_good), ("First bad", first_bad)]
+        .into_iter()
+        .filter_map(|(label, entry)| {
+            let (point, annotations) = entry?;
+            Some(mkline!(
+                label,
+                " ",
+                (point, Color::Blue, Attributes::Bold),
+                ": ",
+                annotations.to_string()
+            ))
+        })
+        .collect_vec()
+}
+
+/// Lists the log files of the latest good and first bad points, if they were logged.
+pub fn boundary_logs_to_lines(
+    first_bad: &IBig,
+    results: &BTreeMap<IBig, ParasectPayloadResult>,
+    logs: &BTreeMap<IBig, ProbeLogs>,
+) -> Vec<Line> {
+    [
+        ("Logs of latest good ", latest_good(first_bad, results)),
+        ("Logs of first bad ", Some(first_bad)),
+    ]
+    .into_iter()
+    .filter_map(|(label, point)| {
+        let point = point?;
+        let files = logs.get(point)?.written();
+        (!files.is_empty()).then(|| {
+            mkline!(
+                label,
+                (point, Color::Blue, Attributes::Bold),
+                ": ",
+                files
+                    .iter()
+                    .map(|f| f.display().to_string())
+                    .collect_vec()
+                    .join(" ")
+            )
+        })
+    })
+    .collect_vec()
+}
+
+/// Shows the diff of the output of the latest good and first bad points, colored like `diff --color`.
+pub fn boundary_diff_to_lines(
+    latest_good: &IBig,
+    first_bad: &IBig,
+    diff: &Result<String, String>,
+) -> Vec<Line> {
+    let diff = match diff {
+        Ok(diff) => diff,
+        Err(e) => {
+            return vec![mkline!(
+                "Failed to diff the output of latest good and first bad: ",
+                e
+            )]
+        }
+    };
+    if diff.is_empty() {
+        return vec![mkline!(
+            "Latest good ",
+            (latest_good, Color::Blue, Attributes::Bold),
+            " and first bad ",
+            (first_bad, Color::Blue, Attributes::Bold),
+            " printed the same output"
+        )];
+    }
+
+    let header = mkline!(
+        "Output diff from latest good ",
+        (latest_good, Color::Blue, Attributes::Bold),
+        " to first bad ",
+        (first_bad, Color::Blue, Attributes::Bold),
+        ":"
+    );
+    let lines = diff.lines().map(|line| {
+        let color = match line {
+            l if l.starts_with("---") || l.starts_with("+++") => {
+                return mkline!((line, Attributes::Bold))
+            }
+            l if l.starts_with("@@") => Color::Magenta,
+            l if l.starts_with('-') => Color::Red,
+            l if l.starts_with('+') => Color::Green,
+            _ => Color::Default,
+        };
+        mkline!((line, color))
+    });
+
+    [header].into_iter().chain(lines).collect_vec()
+}
+
+pub fn collected_artifacts_to_lines(collected: &BTreeMap<IBig, PathBuf>) -> Vec<Line> {
+    collected
+        .iter()
+        .map(|(point, dir)| {
+            mkline!(
+                "Collected the artifacts of ",
+                (point, Color::Blue, Attributes::Bold),
+                ": ",
+                dir.display().to_string()
+            )
+        })
+        .collect_vec()
+}
+
+pub fn kept_scratch_to_lines(kept: &[(ProbeContext, PathBuf)]) -> Vec<Line> {
+    kept.iter()
+        .map(|(ctx, dir)| {
+            mkline!(
+                "Kept the scratch directory of ",
+                (&ctx.point, Color::Blue, Attributes::Bold),
+                ": ",
+                dir.display().to_string()
+            )
+        })
+        .collect_vec()
+}
+
+/// The command line that resumes an interrupted search: `argv` (parasect's own arguments) with --low and --high narrowed to the points found so far.
+///
+/// The points themselves are probed again, which keeps the resumed search from trusting a result it can't see.
+pub fn resume_command(
+    argv: &[String],
+    latest_good: Option<&IBig>,
+    earliest_bad: Option<&IBig>,
+) -> Vec<String> {
+    let narrowed = [("--low", "-x", latest_good), ("--high", "-y", earliest_bad)]
+        .into_iter()
+        .filter_map(|(long, short, point)| Some((long, short, point?)))
+        .collect_vec();
+
+    let mut ret = argv[..1].to_vec();
+    ret.extend(
+        narrowed
+            .iter()
+            .map(|(long, _, point)| format!("{}={}", long, point)),
+    );
+
+    let mut args = argv[1..].iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            ret.push(arg.clone());
+            ret.extend(args.by_ref().cloned());
+            break;
+        }
+
+        match narrowed.iter().find(|(long, short, _)| {
+            arg == long
+                || arg == short
+                || arg.starts_with(&format!("{}=", long))
+                || (arg.starts_with(short) && !arg.starts_with("--"))
+        }) {
+            // the value is the next argument
+            Some((long, short, _)) if arg == long || arg == short => {
+                args.next();
+            }
+            Some(_) => {}
+            None => ret.push(arg.clone()),
+        }
+    }
+
+    ret
+}
+
+pub fn resume_command_to_lines(
+    argv: &[String],
+    latest_good: Option<&IBig>,
+    earliest_bad: Option<&IBig>,
+) -> Vec<Line> {
+    let command = resume_command(argv, latest_good, earliest_bad)
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect_vec();
+
+    vec![
+        mkline!("To resume the search, run:"),
+        mkline!("  ", (command.join(" "), Color::Blue)),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use crate::cli::cli_args::CliArgs;
-    use crate::cli::utils::{command_line, parasect_result_to_lines};
-    use crate::test_util::test_util::test_util::ib;
+    use crate::cli::utils::{
+        boundary_annotations_to_lines, boundary_diff_to_lines, command_line, kept_scratch_to_lines,
+        parasect_result_to_lines, resume_command, resume_command_to_lines,
+    };
+    use crate::parasect::probe_context::SearchState;
+    use crate::parasect::types::Annotations;
+    use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+    use crate::parasect::types::ParasectPayloadResult::{Continue, Skip};
+    use crate::test_util::test_util::test_util::{ib, r};
     use crate::ui::line::{mkline, Line};
     use crate::ui::segment::{Attributes, Color};
     use clap::Parser;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
 
     #[test]
     fn test_command_line_basic() {
@@ -85,6 +326,35 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_command_line_expressions() {
+        let cmd = vec![
+            "thing".to_string(),
+            "--size=${X*4096}".to_string(),
+            "${HOME}/build-${X:06}".to_string(),
+        ];
+
+        assert_eq!(
+            command_line(&cmd, "$X"),
+            mkline!(
+                "thing --size=",
+                ("${X*4096}", Color::Blue, Attributes::Bold),
+                " ${HOME}/build-",
+                ("${X:06}", Color::Blue, Attributes::Bold)
+            )
+        )
+    }
+
+    #[test]
+    fn test_command_line_invalid_expression() {
+        let cmd = vec!["thing".to_string(), "--size=${X*}/$X".to_string()];
+
+        assert_eq!(
+            command_line(&cmd, "$X"),
+            mkline!("thing --size=${X*}/", ("$X", Color::Blue, Attributes::Bold))
+        )
+    }
+
     #[test]
     fn test_command_line_empty() {
         let cmd = Vec::new();
@@ -141,4 +411,124 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_kept_scratch_to_lines() {
+        let ctx = SearchState::new(r(0, 10)).start_probe(ib(7), 0);
+
+        assert_eq!(
+            kept_scratch_to_lines(&[(ctx, PathBuf::from("/tmp/parasect-1/point-7"))]),
+            vec![mkline!(
+                "Kept the scratch directory of ",
+                (7, Color::Blue, Attributes::Bold),
+                ": /tmp/parasect-1/point-7"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_resume_command() {
+        let argv = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            resume_command(
+                &argv("parasect --low=0 -y 100 -t -- ./test.sh --low=$X"),
+                Some(&ib(13)),
+                Some(&ib(20))
+            ),
+            argv("parasect --low=13 --high=20 -t -- ./test.sh --low=$X")
+        );
+        // only what was found is narrowed
+        assert_eq!(
+            resume_command(&argv("parasect -x5 --high 100 -- cmd"), None, Some(&ib(20))),
+            argv("parasect --high=20 -x5 -- cmd")
+        );
+
+        assert_eq!(
+            resume_command_to_lines(
+                &argv("parasect -x=0 -y=9 -- echo $X it's"),
+                Some(&ib(3)),
+                None
+            ),
+            vec![
+                mkline!("To resume the search, run:"),
+                mkline!(
+                    "  ",
+                    (
+                        "'parasect' '--low=3' '-y=9' '--' 'echo' '$X' 'it'\\''s'",
+                        Color::Blue
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boundary_diff_to_lines() {
+        let diff = Ok("--- 5.out\n+++ 6.out\n@@ -1 +1 @@\n-a\n+b\n".to_string());
+
+        assert_eq!(
+            boundary_diff_to_lines(&ib(5), &ib(6), &diff),
+            vec![
+                mkline!(
+                    "Output diff from latest good ",
+                    (5, Color::Blue, Attributes::Bold),
+                    " to first bad ",
+                    (6, Color::Blue, Attributes::Bold),
+                    ":"
+                ),
+                mkline!(("--- 5.out", Attributes::Bold)),
+                mkline!(("+++ 6.out", Attributes::Bold)),
+                mkline!(("@@ -1 +1 @@", Color::Magenta)),
+                mkline!(("-a", Color::Red)),
+                mkline!(("+b", Color::Green)),
+            ]
+        );
+        assert_eq!(
+            boundary_diff_to_lines(&ib(5), &ib(6), &Ok(String::new())),
+            vec![mkline!(
+                "Latest good ",
+                (5, Color::Blue, Attributes::Bold),
+                " and first bad ",
+                (6, Color::Blue, Attributes::Bold),
+                " printed the same output"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_boundary_annotations_to_lines() {
+        let note = |s: &str| Annotations {
+            note: Some(s.into()),
+            metrics: BTreeMap::new(),
+        };
+        let results = BTreeMap::from([
+            (ib(3), Continue(Good)),
+            (ib(5), Continue(Good)),
+            (ib(6), Skip("flaky".into())),
+            (ib(7), Continue(Bad)),
+        ]);
+        let annotations = BTreeMap::from([
+            (ib(3), note("early")),
+            (ib(6), note("skipped")),
+            (ib(7), note("slow")),
+        ]);
+
+        assert_eq!(
+            boundary_annotations_to_lines(&ib(7), &results, &annotations),
+            vec![mkline!(
+                "First bad ",
+                (7, Color::Blue, Attributes::Bold),
+                ": slow"
+            )]
+        );
+        assert_eq!(
+            boundary_annotations_to_lines(&ib(5), &results, &annotations),
+            vec![mkline!(
+                "Latest good ",
+                (3, Color::Blue, Attributes::Bold),
+                ": early"
+            )]
+        );
+    }
 }
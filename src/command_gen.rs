@@ -1,20 +1,76 @@
 use crate::cli::error_handling::CliResult;
 use crate::cli::utils::command_line;
 use crate::collections::collect_collection::CollectVec;
+use crate::parasect::probe_context::ProbeContext;
+use crate::template::expression::Variables;
+use crate::template::template_string::{value_name, TemplatePiece, TemplateString};
 use crate::ui::line::{mkline, Line};
 use crate::ui::segment::{Attributes, Color};
+use crate::util::string::shell_quote;
 use ibig::IBig;
 
+/// Produces the command to execute for a given number from a templated command.
+///
+/// Each argument is parsed as a `TemplateString` up front, so malformed placeholders are reported before anything runs.
 #[derive(Debug)]
 pub struct CommandGen {
-    args: Vec<String>,
+    raw_args: Vec<String>,
+    args: Vec<TemplateString>,
     substitution_string: String,
+    shell: bool,
+}
+
+/// Returns the arguments that look like the substitution string was already expanded to nothing by the caller's shell, e.g. `--rev=` from an unquoted `--rev=$X`.
+fn empty_looking_args(args: &[String]) -> Vec<&str> {
+    args.iter()
+        .filter(|a| {
+            a.is_empty()
+                || a.split_whitespace()
+                    .any(|w| w.ends_with('=') || w == "\"\"" || w == "''")
+        })
+        .map(|a| a.as_str())
+        .collect_vec()
+}
+
+fn expansion_hint(label: (&str, Color), args: &[&str], substitution_string: &str) -> Line {
+    let quoted = args
+        .iter()
+        .map(|a| format!("\"{}\"", a))
+        .collect_vec()
+        .join(", ");
+
+    mkline!(
+        (label.0, label.1, Attributes::Bold),
+        ": the command has empty-looking arguments (",
+        quoted,
+        "). If they were meant to contain ",
+        (substitution_string, Color::Blue, Attributes::Bold),
+        ", your shell expanded it before parasect saw it. Put ",
+        (substitution_string, Color::Blue, Attributes::Bold),
+        " in single quotes."
+    )
 }
 
 impl CommandGen {
     pub fn new<I: IntoIterator<Item = String>>(
         args: I,
         substitution_string: String,
+    ) -> CliResult<Self> {
+        Self::new_inner(args, substitution_string, true)
+    }
+
+    /// Like `new`, but doesn't require the command to reference the value, for when it's passed some other way (e.g. in a template file).
+    pub fn new_without_value<I: IntoIterator<Item = String>>(
+        args: I,
+        substitution_string: String,
+    ) -> CliResult<Self> {
+        Self::new_inner(args, substitution_string, false)
+    }
+
+    fn new_inner<I: IntoIterator<Item = String>>(
+        args: I,
+        substitution_string: String,
+        require_value: bool,
     ) -> CliResult<Self> {
         if substitution_string.is_empty() {
             return Err(vec![mkline!("The substitution string cannot be empty.")]);
@@ -26,8 +82,31 @@ impl CommandGen {
             return Err(vec![mkline!("The command cannot be empty.")]);
         }
 
-        if !args.iter().any(|a| a.contains(&substitution_string)) {
-            return Err(vec![
+        let templates = args
+            .iter()
+            .map(|a| TemplateString::parse(a, &substitution_string, &ProbeContext::VARIABLE_NAMES))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                vec![
+                    mkline!(
+                        "Invalid substitution ",
+                        (&e.source, Color::Blue, Attributes::Bold),
+                        ": ",
+                        e.message
+                    ),
+                    Line::join([
+                        mkline!("Command: "),
+                        command_line(&args, substitution_string.as_str()),
+                    ]),
+                ]
+            })?;
+
+        if require_value
+            && !templates
+                .iter()
+                .any(|t| t.references_value() || t.references_variable("PARASECT_VALUE"))
+        {
+            let mut lines = vec![
                 mkline!(
                     "The given command does not contain the substitution string ",
                     (&substitution_string, Color::Blue, Attributes::Bold)
@@ -36,28 +115,110 @@ impl CommandGen {
                     mkline!("Command: "),
                     command_line(&args, substitution_string.as_str()),
                 ]),
-            ]);
+            ];
+
+            let empty_looking = empty_looking_args(&args);
+            if !empty_looking.is_empty() {
+                lines.push(expansion_hint(
+                    ("Hint", Color::Yellow),
+                    &empty_looking,
+                    &substitution_string,
+                ));
+            }
+
+            return Err(lines);
         }
 
         Ok(Self {
-            args,
+            raw_args: args,
+            args: templates,
             substitution_string,
+            shell: false,
         })
     }
 
-    pub fn command_for_number(&self, num: &IBig) -> Vec<String> {
-        let num_string = num.to_string();
-        self.args
+    /// Runs the command through `sh -c` instead of executing it directly, so pipes, `&&`, etc. can be used.
+    ///
+    /// The arguments are joined with spaces into a single script. If the substitution string looks like a shell variable (e.g. `$X`), the number is assigned to that variable before the script runs, so the shell does the quoting.
+    pub fn with_shell(mut self, shell: bool) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Lines describing anything suspicious about the command that didn't prevent it from being used.
+    ///
+    /// Empty-looking arguments are only suspicious when the command has no placeholder at all, and never in shell mode, where things like `CFLAGS=` and `""` are common.
+    pub fn warnings(&self) -> Vec<Line> {
+        let has_placeholder = self.args.iter().any(|t| {
+            t.pieces()
+                .iter()
+                .any(|p| matches!(p, TemplatePiece::Substitution(_)))
+        });
+        let empty_looking = if self.shell || has_placeholder {
+            Vec::new()
+        } else {
+            empty_looking_args(&self.raw_args)
+        };
+
+        if empty_looking.is_empty() {
+            Vec::new()
+        } else {
+            vec![expansion_hint(
+                ("Warning", Color::Yellow),
+                &empty_looking,
+                &self.substitution_string,
+            )]
+        }
+    }
+
+    /// Substitutes the given number into the command.
+    ///
+    /// Fails if a placeholder can't be evaluated for this number, e.g. when dividing by zero or when referencing a context variable.
+    pub fn command_for_number(&self, num: &IBig) -> Result<Vec<String>, String> {
+        self.command_for_variables(
+            &Variables::new(num.clone()).with(ProbeContext::VARIABLE_NAMES[0], num.clone()),
+        )
+    }
+
+    /// Substitutes the probed number and the context variables (e.g. `${PARASECT_WORKER_ID}`) into the command.
+    ///
+    /// Fails if a placeholder can't be evaluated, e.g. when dividing by zero.
+    pub fn command_for_context(&self, ctx: &ProbeContext) -> Result<Vec<String>, String> {
+        self.command_for_variables(&ctx.template_variables())
+    }
+
+    fn command_for_variables(&self, variables: &Variables) -> Result<Vec<String>, String> {
+        let num = variables.value();
+
+        if !self.shell {
+            return self.args.iter().map(|x| x.render(variables)).collect();
+        }
+
+        let variable = value_name(&self.substitution_string);
+
+        let script = self
+            .args
             .iter()
-            .map(|x| x.clone().replace(&self.substitution_string, &num_string))
-            .collect_vec()
+            .map(|x| match variable {
+                Some(_) => x.render_expressions(variables),
+                None => x.render(variables),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(" ");
+
+        let assignment = variable
+            .map(|v| format!("{}={}\n", v, shell_quote(&num.to_string())))
+            .unwrap_or_default();
+
+        Ok(vec!["sh".into(), "-c".into(), assignment + &script])
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_util::test_util::test_util::ib;
+    use crate::parasect::probe_context::SearchState;
+    use crate::test_util::test_util::test_util::{ib, r};
 
     #[test]
     fn test_cmdgen_basic() {
@@ -71,7 +232,7 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            cmdgen.command_for_number(&ib(69)),
+            cmdgen.command_for_number(&ib(69)).unwrap(),
             vec!["thing", "--flag=x", "--other-flag=69", "--third-flag=z"]
         )
     }
@@ -88,7 +249,7 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            cmdgen.command_for_number(&ib(69)),
+            cmdgen.command_for_number(&ib(69)).unwrap(),
             vec!["thing", "--flag=69", "--other-flag=69", "--third-flag=z"]
         )
     }
@@ -105,11 +266,122 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            cmdgen.command_for_number(&ib(69)),
+            cmdgen.command_for_number(&ib(69)).unwrap(),
             vec!["thing", "69", "--other-flag=69", "--third-flag=z"]
         )
     }
 
+    #[test]
+    fn test_cmdgen_expressions() {
+        let cmdgen = CommandGen::new(
+            vec!["thing", "--size=${X*4096}", "build-${X:06}", "${X+1:x}"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cmdgen.command_for_number(&ib(123)).unwrap(),
+            vec!["thing", "--size=503808", "build-000123", "7c"]
+        )
+    }
+
+    #[test]
+    fn test_cmdgen_only_expressions_counts_as_match() {
+        assert!(CommandGen::new(
+            vec!["thing", "${X:x}"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_cmdgen_context() {
+        let cmdgen = CommandGen::new(
+            vec![
+                "thing",
+                "--value=${PARASECT_VALUE}",
+                "--out=${PARASECT_WORKER_ID:02}",
+                "${PARASECT_EARLIEST_BAD - X}",
+            ]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        let ctx = SearchState::new(r(0, 100)).start_probe(ib(40), 3);
+
+        assert_eq!(
+            cmdgen.command_for_context(&ctx).unwrap(),
+            vec!["thing", "--value=40", "--out=03", "61"]
+        );
+        assert_eq!(
+            cmdgen.command_for_number(&ib(40)),
+            Err("${PARASECT_WORKER_ID:02}: \"PARASECT_WORKER_ID\" is not available here".into())
+        );
+    }
+
+    #[test]
+    fn test_cmdgen_context_without_value_fails() {
+        assert!(CommandGen::new(
+            vec!["thing", "${PARASECT_WORKER_ID}"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_cmdgen_evaluation_error() {
+        let cmdgen = CommandGen::new(
+            vec!["thing", "${100/X}"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cmdgen.command_for_number(&ib(0)),
+            Err("${100/X}: division by zero".to_string())
+        )
+    }
+
+    #[test]
+    fn test_cmdgen_fails_with_invalid_expression() {
+        assert_eq!(
+            CommandGen::new(
+                vec!["thing", "--size=${X:q}", "$X"]
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect_vec(),
+                "$X".to_string(),
+            )
+            .unwrap_err(),
+            vec![
+                mkline!(
+                    "Invalid substitution ",
+                    ("${X:q}", Color::Blue, Attributes::Bold),
+                    ": unknown format \"q\""
+                ),
+                mkline!(
+                    "Command: thing --size=${X:q} ",
+                    ("$X", Color::Blue, Attributes::Bold)
+                ),
+            ]
+        )
+    }
+
     #[test]
     fn test_cmdgen_fails_with_no_ss_matches() {
         assert_eq!(
@@ -131,6 +403,118 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_cmdgen_without_value() {
+        let cmdgen = CommandGen::new_without_value(
+            vec!["thing", "--third-flag=z"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cmdgen.command_for_number(&ib(5)).unwrap(),
+            vec!["thing", "--third-flag=z"]
+        );
+    }
+
+    #[test]
+    fn test_cmdgen_fails_with_no_ss_matches_hint() {
+        assert_eq!(
+            CommandGen::new(
+                vec!["thing", "--rev=", ""]
+                    .into_iter()
+                    .map(|x| x.to_string())
+                    .collect_vec(),
+                "$X".to_string(),
+            )
+            .unwrap_err()[2],
+            mkline!(
+                ("Hint", Color::Yellow, Attributes::Bold),
+                ": the command has empty-looking arguments (\"--rev=\", \"\"). If they were meant to contain ",
+                ("$X", Color::Blue, Attributes::Bold),
+                ", your shell expanded it before parasect saw it. Put ",
+                ("$X", Color::Blue, Attributes::Bold),
+                " in single quotes."
+            )
+        )
+    }
+
+    #[test]
+    fn test_cmdgen_warnings() {
+        let cmdgen = CommandGen::new_without_value(
+            vec!["thing", "--rev=", ""]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(cmdgen.warnings().len(), 1);
+        assert!(cmdgen.with_shell(true).warnings().is_empty());
+
+        let cmdgen = CommandGen::new(
+            vec!["thing", "--rev=", "$X"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert!(cmdgen.warnings().is_empty());
+
+        let cmdgen = CommandGen::new(
+            vec!["thing", "--rev=$X"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap();
+
+        assert!(cmdgen.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_cmdgen_shell() {
+        let cmdgen = CommandGen::new(
+            vec!["make -j4 && ./test \"$X\" | grep ${X*2}"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "$X".to_string(),
+        )
+        .unwrap()
+        .with_shell(true);
+
+        assert_eq!(
+            cmdgen.command_for_number(&ib(-5)).unwrap(),
+            vec!["sh", "-c", "X='-5'\nmake -j4 && ./test \"$X\" | grep -10"]
+        )
+    }
+
+    #[test]
+    fn test_cmdgen_shell_joins_args() {
+        let cmdgen = CommandGen::new(
+            vec!["./build", "&&", "./test", "@@"]
+                .into_iter()
+                .map(|x| x.to_string())
+                .collect_vec(),
+            "@@".to_string(),
+        )
+        .unwrap()
+        .with_shell(true);
+
+        assert_eq!(
+            cmdgen.command_for_number(&ib(7)).unwrap(),
+            vec!["sh", "-c", "./build && ./test 7"]
+        )
+    }
+
     #[test]
     fn test_cmdgen_fails_with_blank_ss() {
         assert_eq!(
@@ -2,81 +2,378 @@
 
 extern crate core;
 
-use crate::cli::cli_args::CliArgs;
+use crate::cli::cli_args::{Cli, CliArgs, CliCommand, OutputFormat};
 use crate::cli::error_handling::{parasect_error_to_cli_error, CliResult};
-use crate::cli::utils::{command_line, parasect_result_to_lines};
-use crate::collections::collect_collection::CollectVec;
+use crate::cli::interrupt::catch_interrupts;
+use crate::cli::json_output::run_to_json;
+use crate::cli::junit_output::outcome_to_junit;
+use crate::cli::outcome::SearchOutcome;
+use crate::cli::replay_args::ReplayArgs;
+use crate::cli::report::outcome_to_report;
+use crate::cli::trace::outcome_to_trace;
+use crate::cli::utils::{
+    boundary_annotations_to_lines, boundary_diff_to_lines, boundary_logs_to_lines,
+    collected_artifacts_to_lines, command_line, kept_scratch_to_lines, latest_good,
+    parasect_result_to_lines, resume_command_to_lines,
+};
 use crate::parasect::parasect::{parasect, ParasectSettings};
-use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
-use crate::parasect::types::ParasectPayloadResult::{Continue, Stop};
-use crate::task::cancellable_subprocess::CancellableSubprocess;
-use crate::task::cancellable_task::CancellableTask;
-use crate::task::result_cancellable_task::ResultCancellableTask;
-use crate::ui::line::print_lines;
+use crate::parasect::probe_context::SearchState;
+use crate::parasect::types::ParasectError;
+use crate::probe::artifacts::Artifacts;
+use crate::probe::build_pool::BuildPool;
+use crate::probe::output_diff::OutputDiff;
+use crate::probe::probe::Probe;
+use crate::probe::scratch::ScratchDirs;
+use crate::range::numeric_range::NumericRange;
+use crate::threading::async_value::AsyncValue;
+use crate::ui::event_log::{search_started, EventLog};
+use crate::ui::event_recorder::EventRecorder;
+use crate::ui::line::{mkline, print_lines, Line};
+use crate::ui::replay::replay as replay_recording;
+use crate::ui::segment::{Attributes, Color};
 use crate::ui::ui::Ui;
 use clap::Parser;
 use crossbeam_channel::unbounded;
 use do_notation::m;
-use ibig::IBig;
+use std::collections::BTreeMap;
 use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+use std::{env, fs};
 
 mod cli;
 mod collections;
 mod command_gen;
 mod messaging;
 mod parasect;
+mod probe;
 mod range;
 mod task;
+mod template;
 #[cfg(test)]
 mod test_util;
 mod threading;
 mod ui;
 mod util;
 
-fn run_parasect(args: &CliArgs) -> CliResult<IBig> {
+/// Measures the metric at the low end of the range, if the metric is relative to it.
+fn measure_baseline(probe: &mut Probe, range: &NumericRange) -> CliResult<()> {
+    let low = range.first().unwrap();
+    let ctx = SearchState::new(range.clone()).start_probe(low.clone(), 0);
+
+    probe.measure_baseline(ctx).map_err(|e| {
+        vec![mkline!(
+            "Failed to measure the baseline at ",
+            (low, Color::Blue, Attributes::Bold),
+            ": ",
+            e
+        )]
+    })
+}
+
+/// Describes why the search failed, and how to resume it if it was interrupted.
+fn search_error_to_lines(e: ParasectError) -> Vec<Line> {
+    let resume = match &e {
+        ParasectError::Interrupted {
+            latest_good,
+            earliest_bad,
+        } => resume_command_to_lines(
+            &env::args().collect::<Vec<_>>(),
+            latest_good.as_ref(),
+            earliest_bad.as_ref(),
+        ),
+        _ => Vec::new(),
+    };
+
+    [parasect_error_to_cli_error(e), resume].concat()
+}
+
+/// Runs the search, returning what it found about every point that was probed, along with the first bad point or why the search failed.
+///
+/// The search stops early, cancelling the running probes, once `interrupt` is sent.
+fn run_parasect(
+    args: &CliArgs,
+    scratch: Arc<ScratchDirs>,
+    interrupt: AsyncValue<()>,
+) -> CliResult<SearchOutcome> {
     let (event_sender, event_receiver) = unbounded();
-    let title = command_line(&args.command, &args.substitution_string);
+    let title = command_line(&args.display_command(), &args.substitution_string);
+    let machine_output = args.machine_output();
 
     m! {
-        cgen <- args.command_gen();
+        checks <- args.checks();
+        bad_if <- args.bad_if(&checks);
+        output_rules <- args.output_rules();
+        metric <- args.metric();
+        limits <- args.limits();
+        pty <- args.pty();
+        cancellation <- args.cancellation();
+        log_dir <- args.log_dir();
+        output_limit <- args.output_limit();
+        output_diff <- args.output_diff();
+        artifacts <- args.artifacts();
         range <- args.range();
+        alignment <- args.alignment();
         max_parallelism <- args.max_parallelism();
+        template_files <- args.template_files();
+        cwd <- args.cwd();
+        setup <- args.setup_cmd();
+        teardown <- args.teardown_cmd();
+        build <- args.build_cmd();
+        build_parallelism <- args.build_parallelism();
+        events <- args.events();
+        event_writer <- events
+            .map(|sink| sink.open())
+            .transpose()
+            .map_err(|e| vec![mkline!("Can't write the events: ", e)]);
+        report <- args.report();
+
+        let (event_log, ui_events) = match event_writer {
+            Some(writer) => {
+                let header = search_started(&range, &alignment, &args.display_command(), &args.substitution_string);
+                let (forward, ui_events) = unbounded();
+                (Some(EventLog::start(writer, header, event_receiver, forward)), ui_events)
+            }
+            None => (None, event_receiver),
+        };
+        let (event_recorder, ui_events) = if report.is_some() || args.trace.is_some() {
+            let (forward, recorded_events) = unbounded();
+            (Some(EventRecorder::start(ui_events, forward)), recorded_events)
+        } else {
+            (None, ui_events)
+        };
 
-        let _ui = Ui::start(range.clone(), title, event_receiver, args.no_tty);
+        // with machine output, the events are never read, but the receiver stays alive until the search is done so that sending them still works
+        let _ui = match machine_output {
+            true => None,
+            false => Some(Ui::start(range.clone(), alignment.clone(), title, ui_events, args.no_tty)),
+        };
 
-        let settings = ParasectSettings::new(range, move |num| {
-            let cmd = cgen.command_for_number(&num);
+        let artifacts = artifacts.map(Arc::new);
+        let build_pool = build.map(|b| {
+            Arc::new(BuildPool::new(b, scratch.clone(), build_parallelism).with_stderr_to_stderr(machine_output))
+        });
 
-            let cmd_ref = cmd.iter().map(|x| x.as_str()).collect_vec();
+        let mut probe = Probe::with_checks(checks, bad_if, scratch)
+            .with_output_rules(output_rules)
+            .with_limits(limits)
+            .with_metric(metric)
+            .with_event_sender(event_sender.clone())
+            .with_build(build_pool.clone(), args.on_build_failure)
+            .with_setup(setup, args.on_setup_failure)
+            .with_teardown(teardown)
+            .with_template_files(template_files)
+            .with_cwd(cwd)
+            .with_pty(pty)
+            .with_cancellation(cancellation)
+            .with_log_dir(log_dir)
+            .with_output_limit(output_limit)
+            .with_boundary_output(output_diff.is_some())
+            .with_probe_output(args.junit.is_some())
+            .with_artifacts(artifacts.clone())
+            .with_stderr_to_stderr(machine_output);
+        _ <- measure_baseline(&mut probe, &range);
 
-            ResultCancellableTask::new(CancellableSubprocess::new(cmd_ref.as_slice())).map(move |r| {
-                match r {
-                    Ok(Ok(v)) => Continue(if v.status.success() { Good } else { Bad }),
-                    Ok(Err(e)) => Stop(format!("Failed to execute {:?}: {}", cmd, e)),
-                    Err(e) => Stop(format!("Failed to execute {:?}: {}", cmd, e)),
-                }
-            })
-        }).with_max_parallelism(max_parallelism).with_event_sender(event_sender);
+        let probe = Arc::new(probe);
+        let payload_probe = probe.clone();
+
+        let settings = ParasectSettings::new(range.clone(), move |ctx| payload_probe.run(ctx)).with_max_parallelism(max_parallelism).with_alignment(alignment).with_event_sender(event_sender).with_interrupt(interrupt);
+        let settings = match build_pool {
+            Some(pool) => settings.with_prefetcher(pool, build_parallelism),
+            None => settings,
+        };
+
+        let started = Instant::now();
+        let started_at = SystemTime::now();
+        let search = parasect(settings);
+        let elapsed = started.elapsed();
+        let event_log_error = event_log.and_then(|log| log.finish().err());
+        let events = event_recorder.map(|r| r.finish()).unwrap_or_default();
+
+        let outcome = SearchOutcome {
+            range,
+            search,
+            results: probe.results(),
+            annotations: probe.annotations(),
+            probes: probe.history(),
+            logs: probe.logs(),
+            diff: None,
+            artifacts: BTreeMap::new(),
+            started,
+            started_at,
+            elapsed,
+            event_log_error,
+            events,
+        };
+
+        Ok(inspect_boundary(outcome, &probe, output_diff, artifacts))
+    }
+}
+
+/// Once the search found the first bad point, diffs its output to that of the latest good one and drops the artifacts of the other points, if asked to.
+fn inspect_boundary(
+    mut outcome: SearchOutcome,
+    probe: &Probe,
+    output_diff: Option<OutputDiff>,
+    artifacts: Option<Arc<Artifacts>>,
+) -> SearchOutcome {
+    if let Ok(first_bad) = &outcome.search {
+        let (good, _) = outcome.bracket();
+        let boundary = [latest_good(first_bad, &outcome.results), Some(first_bad)];
+        if let Some(a) = &artifacts {
+            a.keep_only(&boundary.into_iter().flatten().collect::<Vec<_>>());
+        }
+        outcome.diff = output_diff.zip(good).map(|(diff, good)| {
+            let d = probe.diff_boundary(&diff, &good, first_bad, &outcome.range);
+            (good, d)
+        });
+    }
+    outcome.artifacts = artifacts.map(|a| a.collected()).unwrap_or_default();
+    outcome
+}
 
-        parasect(settings).map_err(parasect_error_to_cli_error)
+/// What a search printed for a person: the first bad point and what's known around it, or why the search failed.
+fn outcome_to_lines(args: &CliArgs, outcome: SearchOutcome) -> CliResult<Vec<Line>> {
+    let first_bad = outcome.search.map_err(search_error_to_lines)?;
+    let mut lines = parasect_result_to_lines(args, &first_bad);
+    lines.extend(boundary_annotations_to_lines(
+        &first_bad,
+        &outcome.results,
+        &outcome.annotations,
+    ));
+    lines.extend(boundary_logs_to_lines(
+        &first_bad,
+        &outcome.results,
+        &outcome.logs,
+    ));
+    if let Some((good, diff)) = &outcome.diff {
+        lines.extend(boundary_diff_to_lines(good, &first_bad, diff));
     }
+    lines.extend(collected_artifacts_to_lines(&outcome.artifacts));
+    Ok(lines)
 }
 
-fn execute(args: &CliArgs) -> ExitCode {
-    let result = run_parasect(&args);
+fn execute(args: &CliArgs, interrupt: AsyncValue<()>) -> ExitCode {
+    let scratch = args.scratch_dirs().map(Arc::new);
+    let result = scratch
+        .clone()
+        .and_then(|scratch| run_parasect(args, scratch, interrupt));
+
+    let mut warnings = args
+        .command_gen()
+        .map(|cgen| cgen.warnings())
+        .unwrap_or_default();
+    if let Ok(SearchOutcome {
+        event_log_error: Some(e),
+        ..
+    }) = &result
+    {
+        warnings.push(mkline!((e, Color::Yellow)));
+    }
+    if let (Ok(Some((path, format))), Ok(outcome)) = (args.report(), &result) {
+        if let Err(e) = fs::write(&path, outcome_to_report(args, outcome, format)) {
+            warnings.push(mkline!((
+                format!("Failed to write the report to {}: {}", path, e),
+                Color::Yellow
+            )));
+        }
+    }
+    if let (Some(path), Ok(outcome)) = (&args.junit, &result) {
+        if let Err(e) = fs::write(path, outcome_to_junit(args, outcome)) {
+            warnings.push(mkline!((
+                format!("Failed to write the JUnit report to {}: {}", path, e),
+                Color::Yellow
+            )));
+        }
+    }
+    if let (Some(path), Ok(outcome)) = (&args.trace, &result) {
+        if let Err(e) = fs::write(path, outcome_to_trace(args, outcome).to_string()) {
+            warnings.push(mkline!((
+                format!("Failed to write the trace to {}: {}", path, e),
+                Color::Yellow
+            )));
+        }
+    }
+    let kept_scratch = scratch.map(|s| s.kept()).unwrap_or_default();
+    let ret = match &result {
+        Ok(SearchOutcome { search: Ok(_), .. }) => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    };
+
+    if args.output == OutputFormat::Json {
+        let json = run_to_json(args, &result, &kept_scratch, &warnings);
+        println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        return ret;
+    }
 
-    let (lines, ret) = match result {
-        Ok(idx) => (parasect_result_to_lines(&args, &idx), ExitCode::SUCCESS),
-        Err(e) => (e, ExitCode::FAILURE),
+    // with --porcelain, only the first bad point goes to stdout, and everything else is for the person who runs the script
+    let mut lines = match (args.porcelain, result) {
+        (
+            true,
+            Ok(SearchOutcome {
+                search: Ok(first_bad),
+                ..
+            }),
+        ) => {
+            println!("{}", first_bad);
+            Vec::new()
+        }
+        (true, result) => result
+            .and_then(|outcome| outcome.search.map_err(search_error_to_lines))
+            .err()
+            .unwrap_or_default(),
+        (false, result) => result
+            .and_then(|outcome| outcome_to_lines(args, outcome))
+            .unwrap_or_else(|e| e),
     };
+    lines.extend(warnings);
+    lines.extend(kept_scratch_to_lines(&kept_scratch));
 
-    print_lines(lines.iter());
-    println!();
+    // stdout is taken by --porcelain or by the events
+    if args.machine_output() {
+        for line in lines {
+            eprintln!("{}", line.plaintext());
+        }
+    } else {
+        print_lines(lines.iter());
+        println!();
+    }
 
     ret
 }
 
+/// Plays back a recorded search.
+fn replay(args: &ReplayArgs) -> ExitCode {
+    let result = m! {
+        recording <- args.recording();
+        speed <- args.speed();
+        let title = command_line(&recording.command, &recording.substitution_string);
+        replay_recording(&recording, title, speed, args.step_by_step).map_err(|e| vec![mkline!(e)])
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(lines) => {
+            print_lines(lines.iter());
+            println!();
+            ExitCode::FAILURE
+        }
+    }
+}
+
 fn main() -> ExitCode {
-    let args = CliArgs::parse();
-    execute(&args)
+    match Cli::parse() {
+        Cli {
+            subcommand: Some(CliCommand::Replay(args)),
+            ..
+        } => replay(&args),
+        Cli {
+            search: Some(args), ..
+        } => {
+            let interrupt = catch_interrupts();
+            execute(&args, interrupt)
+        }
+        // clap requires --low and --high unless there's a subcommand
+        Cli { .. } => unreachable!(),
+    }
 }
@@ -1,10 +1,24 @@
 use crate::parasect::types::ParasectPayloadAnswer;
 use crate::parasect::worker::WorkerMessage;
 use crate::range::numeric_range::NumericRange;
+use ibig::IBig;
+
+/// A detail about a probe that's still running or just finished, reported by the payload itself, e.g. the result of one of its sub-commands.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct ProbeNote {
+    pub worker_id: usize,
+    pub point: IBig,
+    /// What the note is about, e.g. "check lint".
+    pub source: String,
+    /// The answer `source` gave, if it gave one.
+    pub answer: Option<ParasectPayloadAnswer>,
+    pub detail: String,
+}
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
 pub enum Event {
     WorkerMessageSent(WorkerMessage),
     ParasectCancelled(String),
     RangeInvalidated(NumericRange, ParasectPayloadAnswer),
+    ProbeNoted(ProbeNote),
 }
@@ -1,4 +1,6 @@
 pub mod event;
 pub mod parasect;
+pub mod prefetcher;
+pub mod probe_context;
 pub mod types;
 pub mod worker;
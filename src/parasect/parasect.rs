@@ -4,12 +4,19 @@ use crate::messaging::listener::{Listener, ListenerBehavior};
 use crate::messaging::mailbox::Mailbox;
 use crate::parasect::event::Event;
 use crate::parasect::event::Event::{ParasectCancelled, RangeInvalidated, WorkerMessageSent};
-use crate::parasect::types::ParasectError::{InconsistencyError, PayloadError};
+use crate::parasect::prefetcher::Prefetcher;
+use crate::parasect::probe_context::{ProbeContext, SearchState};
+use crate::parasect::types::ParasectError::{
+    AmbiguityError, InconsistencyError, Interrupted, PayloadError,
+};
 use crate::parasect::types::ParasectPayloadAnswer::*;
 use crate::parasect::types::ParasectPayloadResult::*;
-use crate::parasect::types::{ParasectError, ParasectPayloadAnswer, ParasectPayloadResult};
-use crate::parasect::worker::PointCompletionMessageType::Completed;
+use crate::parasect::types::{
+    ParasectError, ParasectPayloadAnswer, ParasectPayloadOutput, ParasectPayloadResult,
+};
+use crate::parasect::worker::PointCompletionMessageType::{Completed, Started};
 use crate::parasect::worker::{Worker, WorkerMessage};
+use crate::range::alignment::Alignment;
 use crate::range::bisecting_range_queue::BisectingRangeQueue;
 use crate::range::numeric_range::NumericRange;
 use crate::task::cancellable_task::CancellableTask;
@@ -17,27 +24,29 @@ use crate::threading::async_value::AsyncValue;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use dashmap::DashMap;
 use ibig::IBig;
-use std::cmp::{max, min};
-use std::ops::Deref;
-use std::sync::{Arc, RwLock};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use std::thread;
 
 #[derive(Clone, Debug)]
 pub struct ParasectSettings<TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
     range: NumericRange,
     payload: FPayload,
     event_sender: Option<Sender<Event>>,
     max_parallelism: usize,
+    alignment: Alignment,
+    prefetcher: Option<(Arc<dyn Prefetcher>, usize)>,
+    interrupt: Option<AsyncValue<()>>,
 }
 
 impl<TTask, FPayload> ParasectSettings<TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
     pub fn new(range: NumericRange, payload: FPayload) -> Self {
         return ParasectSettings {
@@ -45,6 +54,9 @@ where
             payload,
             event_sender: None,
             max_parallelism: num_cpus::get(),
+            alignment: Alignment::default(),
+            prefetcher: None,
+            interrupt: None,
         };
     }
 }
@@ -52,8 +64,8 @@ where
 #[allow(unused)]
 impl<TTask, FPayload> ParasectSettings<TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
     pub fn with_event_sender(mut self, sender: Sender<Event>) -> Self {
         self.event_sender = Some(sender);
@@ -64,35 +76,94 @@ where
         self.max_parallelism = parallelism;
         self
     }
+
+    /// Only probes numbers matching the given `Alignment`. The returned first bad index will also be aligned.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Tells `prefetcher` about the next `lookahead` points whenever a probe starts, and about every invalidated range.
+    pub fn with_prefetcher(mut self, prefetcher: Arc<dyn Prefetcher>, lookahead: usize) -> Self {
+        self.prefetcher = Some((prefetcher, lookahead));
+        self
+    }
+
+    /// Stops the search as soon as `interrupt` is sent a value, cancelling the points being probed.
+    ///
+    /// The search then fails with `Interrupted`, which gives what was found so far.
+    pub fn with_interrupt(mut self, interrupt: AsyncValue<()>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+}
+
+/// Why the search stopped before running out of points.
+#[derive(Clone, Debug)]
+enum Halt {
+    Failed(String),
+    Interrupted,
+}
+
+impl Display for Halt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Halt::Failed(reason) => f.write_str(reason),
+            Halt::Interrupted => f.write_str("Interrupted; press Ctrl-C again to exit immediately"),
+        }
+    }
+}
+
+/// Halts the search when the interrupt it's registered with is sent.
+struct InterruptMailbox(AsyncValue<Option<Halt>>);
+
+impl Mailbox<'static> for InterruptMailbox {
+    type Message = ();
+
+    fn send_msg(&self, _: ()) -> bool {
+        self.0.send(Some(Halt::Interrupted))
+    }
 }
 
 struct ParasectController<'a, TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
     settings: &'a ParasectSettings<TTask, FPayload>,
     message_receiver: Receiver<WorkerMessage>,
     queue: Arc<BisectingRangeQueue>,
     workers: Vec<Worker<TTask, &'a FPayload>>,
-    latest_good: RwLock<IBig>,
-    earliest_bad: RwLock<IBig>,
+    state: Arc<SearchState>,
     results: DashMap<IBig, ParasectPayloadResult>,
-    failure_message: AsyncValue<Option<String>>,
+    halt: AsyncValue<Option<Halt>>,
 }
 
 impl<'a, TTask, FPayload> ParasectController<'a, TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
     fn new(settings: &'a ParasectSettings<TTask, FPayload>) -> Self {
         let (message_sender, message_receiver) = unbounded();
 
-        let queue = Arc::new(BisectingRangeQueue::new(settings.range.clone()));
+        let queue = Arc::new(BisectingRangeQueue::new_aligned(
+            settings.range.clone(),
+            settings.alignment.clone(),
+        ));
+
+        let state = Arc::new(SearchState::new(settings.range.clone()));
 
         let workers = (0..settings.max_parallelism)
-            .map(|i| Worker::new(i, queue.clone(), message_sender.clone(), &settings.payload))
+            .map(|i| {
+                Worker::new(
+                    i,
+                    queue.clone(),
+                    state.clone(),
+                    message_sender.clone(),
+                    &settings.payload,
+                )
+            })
             .collect_vec();
 
         Self {
@@ -100,10 +171,9 @@ where
             message_receiver,
             queue,
             workers,
-            latest_good: RwLock::new(IBig::from(settings.range.first().unwrap() - 1)),
-            earliest_bad: RwLock::new(IBig::from(settings.range.last().unwrap() + 1)),
+            state,
             results: DashMap::new(),
-            failure_message: AsyncValue::new(),
+            halt: AsyncValue::new(),
         }
     }
 
@@ -114,6 +184,10 @@ where
 
         self.queue.invalidate(&range);
 
+        if let Some((prefetcher, _)) = &self.settings.prefetcher {
+            prefetcher.invalidate(range);
+        }
+
         for worker in self.workers.iter() {
             worker.skip_if_in_range(&range);
         }
@@ -126,28 +200,20 @@ where
     }
 
     fn check_good_does_not_exceed_bad(&self) {
-        let good_read = self.latest_good.read().unwrap();
-        let bad_read = self.earliest_bad.read().unwrap();
-        if good_read.deref() > bad_read.deref() {
-            self.failure_message.send_msg(Some(format!("A good {} was detected after a bad {}. Parasect requires 1 or more good followed by remaining bad.", good_read.deref(), bad_read.deref())));
+        let good = self.state.latest_good();
+        let bad = self.state.earliest_bad();
+        if good > bad {
+            self.halt.send_msg(Some(Halt::Failed(format!("A good {} was detected after a bad {}. Parasect requires 1 or more good followed by remaining bad.", good, bad))));
         }
     }
 
     fn adjust_latest_good(&self, point: &IBig) {
-        if self.latest_good.read().unwrap().deref() < &point {
-            let mut guard = self.latest_good.write().unwrap();
-            *guard = max(guard.deref().clone(), point.clone());
-        }
-
+        self.state.adjust_latest_good(point);
         self.check_good_does_not_exceed_bad();
     }
 
     fn adjust_earliest_bad(&self, point: &IBig) {
-        if self.earliest_bad.read().unwrap().deref() > point {
-            let mut guard = self.earliest_bad.write().unwrap();
-            *guard = min(guard.deref().clone(), point.clone());
-        }
-
+        self.state.adjust_earliest_bad(point);
         self.check_good_does_not_exceed_bad();
     }
 
@@ -159,7 +225,7 @@ where
         }
 
         match message.msg_type {
-            Completed(result) => {
+            Completed(result, _) => {
                 match &result {
                     Continue(Good) => {
                         self.adjust_latest_good(&message.point);
@@ -169,23 +235,33 @@ where
                         self.adjust_earliest_bad(&message.point);
                         self.invalidate_range(&message.right.map_first(|x| x - 1), Bad);
                     }
+                    Skip(_) => {}
                     Stop(reason) => {
-                        self.failure_message.send(Some(reason.clone()));
+                        self.halt.send(Some(Halt::Failed(reason.clone())));
                         self.results.insert(message.point, result);
                         return StopProcessing;
                     }
                 }
                 self.results.insert(message.point, result);
             }
+            Started => {
+                if let Some((prefetcher, lookahead)) = &self.settings.prefetcher {
+                    prefetcher.prefetch(&self.queue.peek(*lookahead));
+                }
+            }
             _ => {}
         };
 
         ContinueProcessing
     }
 
-    fn run(self) -> DashMap<IBig, ParasectPayloadResult> {
+    fn run(self) -> Result<DashMap<IBig, ParasectPayloadResult>, ParasectError> {
         let self_ref = &self;
 
+        if let Some(interrupt) = &self.settings.interrupt {
+            interrupt.notify(InterruptMailbox(self.halt.clone()));
+        }
+
         thread::scope(|scope| {
             let message_loop =
                 Listener::spawn_scoped(scope, self.message_receiver.clone(), |msg| {
@@ -193,15 +269,23 @@ where
                 });
 
             scope.spawn(move || {
-                let result = self_ref.failure_message.wait();
+                let result = self_ref.halt.wait();
 
                 message_loop.stop();
 
-                result.inspect(|reason| {
+                result.inspect(|halt| {
                     self_ref.queue.invalidate(&self_ref.settings.range.clone());
+                    if let Some((prefetcher, _)) = &self_ref.settings.prefetcher {
+                        prefetcher.invalidate(&self_ref.settings.range);
+                    }
+                    if let Halt::Interrupted = halt {
+                        for worker in self_ref.workers.iter() {
+                            worker.skip_if_in_range(&self_ref.settings.range);
+                        }
+                    }
                     if let Some(sender) = &self_ref.settings.event_sender {
                         sender
-                            .send(ParasectCancelled((*reason).clone()))
+                            .send(ParasectCancelled(halt.to_string()))
                             .expect("event_sender should not be closed");
                     }
                 });
@@ -217,14 +301,22 @@ where
                 t.join().unwrap();
             }
 
-            self_ref.failure_message.send(None);
+            self_ref.halt.send(None);
         });
 
         while let Ok(msg) = self.message_receiver.try_recv() {
             self.handle_message(msg);
         }
 
-        self.results
+        if let Some(Halt::Interrupted) = self.halt.wait() {
+            let range = &self.settings.range;
+            return Err(Interrupted {
+                latest_good: Some(self.state.latest_good()).filter(|g| range.contains(g.clone())),
+                earliest_bad: Some(self.state.earliest_bad()).filter(|b| range.contains(b.clone())),
+            });
+        }
+
+        Ok(self.results)
     }
 }
 
@@ -233,24 +325,42 @@ fn process_result_map(
 ) -> Result<IBig, ParasectError> {
     let mut good = Vec::new();
     let mut bad = Vec::new();
+    let mut skipped = Vec::new();
 
     for (k, v) in results.into_iter() {
         match v {
             Continue(Good) => good.push(k),
             Continue(Bad) => bad.push(k),
+            Skip(_) => skipped.push(k),
             Stop(err) => return Err(PayloadError(err)),
         }
     }
 
     good.sort();
     bad.sort();
+    skipped.sort();
 
     if good.is_empty() {
         Err(InconsistencyError("All points were bad.".into()))
     } else if bad.is_empty() {
         Err(InconsistencyError("All points were good.".into()))
     } else if good.last().unwrap() < bad.first().unwrap() {
-        Ok(bad.first().unwrap().clone())
+        let (latest_good, earliest_bad) = (good.last().unwrap(), bad.first().unwrap());
+        let ambiguous = skipped
+            .iter()
+            .filter(|s| latest_good < *s && *s < earliest_bad)
+            .map(|s| s.to_string())
+            .collect_vec();
+
+        if ambiguous.is_empty() {
+            Ok(earliest_bad.clone())
+        } else {
+            Err(AmbiguityError(format!(
+                "The first bad index is {} or one of the skipped points before it: {}.",
+                earliest_bad,
+                ambiguous.join(", ")
+            )))
+        }
     } else {
         Err(InconsistencyError(format!(
             "Found good point {} after bad point {}.",
@@ -265,37 +375,47 @@ pub fn parasect<TTask, FPayload>(
     settings: ParasectSettings<TTask, FPayload>,
 ) -> Result<IBig, ParasectError>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: (Fn(IBig) -> TTask) + Send + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: (Fn(ProbeContext) -> TTask) + Send + Sync,
 {
-    if settings.range.is_empty() {
+    if settings.range.align(&settings.alignment).is_empty() {
         return Err(InconsistencyError("Cannot parasect an empty range.".into()));
     }
 
     let controller = ParasectController::new(&settings);
-    let results = controller.run();
+    let results = controller.run()?;
     process_result_map(results)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parasect::types::Annotations;
     use crate::task::free_cancellable_task::FreeCancellableTask;
     use crate::task::function_cancellable_task::FunctionCancellableTask;
     use crate::test_util::test_util::test_util::{ib, r};
     use proptest::prelude::*;
     use rand::random;
+    use std::sync::Mutex;
     use std::time::Duration;
 
+    /// A payload task that's already done, with `result` and no annotations.
+    fn done(result: ParasectPayloadResult) -> FreeCancellableTask<ParasectPayloadOutput> {
+        FreeCancellableTask::new(result.into())
+    }
+
     #[test]
     fn test_parasect() {
-        let result = parasect(ParasectSettings::new(r(1, 500), |x| {
-            FreeCancellableTask::new(if x < ib(320) {
-                Continue(Good)
-            } else {
-                Continue(Bad)
-            })
-        }));
+        let result = parasect(ParasectSettings::new(
+            r(1, 500),
+            |ProbeContext { point: x, .. }| {
+                done(if x < ib(320) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            },
+        ));
 
         match result {
             Ok(v) => assert_eq!(v, ib(320)),
@@ -305,13 +425,16 @@ mod tests {
 
     #[test]
     fn test_parasect_stop() {
-        let result = parasect(ParasectSettings::new(r(1, 500), |x| {
-            FreeCancellableTask::new(if x < ib(15) {
-                Stop("error".into())
-            } else {
-                Continue(Bad)
-            })
-        }));
+        let result = parasect(ParasectSettings::new(
+            r(1, 500),
+            |ProbeContext { point: x, .. }| {
+                done(if x < ib(15) {
+                    Stop("error".into())
+                } else {
+                    Continue(Bad)
+                })
+            },
+        ));
 
         match result {
             Err(PayloadError(s)) => assert_eq!(s, "error"),
@@ -319,11 +442,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parasect_skip() {
+        let result = parasect(ParasectSettings::new(
+            r(1, 500),
+            |ProbeContext { point: x, .. }| {
+                done(if x == ib(200) || x == ib(250) {
+                    Skip("setup failed".into())
+                } else if x < ib(320) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            },
+        ));
+
+        assert_eq!(result, Ok(ib(320)));
+    }
+
+    #[test]
+    fn test_parasect_skip_ambiguous() {
+        let result = parasect(ParasectSettings::new(
+            r(1, 500),
+            |ProbeContext { point: x, .. }| {
+                done(if x == ib(318) || x == ib(319) {
+                    Skip("setup failed".into())
+                } else if x < ib(320) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            },
+        ));
+
+        assert_eq!(
+            result,
+            Err(AmbiguityError(
+                "The first bad index is 320 or one of the skipped points before it: 318, 319."
+                    .into()
+            ))
+        );
+    }
+
     #[test]
     fn test_parasect_all_good() {
-        let result = parasect(ParasectSettings::new(r(1, 500), |_| {
-            FreeCancellableTask::new(Continue(Good))
-        }));
+        let result = parasect(ParasectSettings::new(r(1, 500), |_| done(Continue(Good))));
 
         assert_eq!(
             result,
@@ -333,9 +496,7 @@ mod tests {
 
     #[test]
     fn test_parasect_all_bad() {
-        let result = parasect(ParasectSettings::new(r(1, 500), |_| {
-            FreeCancellableTask::new(Continue(Bad))
-        }));
+        let result = parasect(ParasectSettings::new(r(1, 500), |_| done(Continue(Bad))));
 
         assert_eq!(
             result,
@@ -343,6 +504,156 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parasect_context() {
+        let result = parasect(
+            ParasectSettings::new(r(1, 500), |ctx| {
+                assert!(ctx.worker_id < 4);
+                assert_eq!(ctx.attempt, 1);
+                assert_eq!(ctx.range, r(1, 500));
+                assert!(ctx.latest_good < ctx.point && ctx.point < ctx.earliest_bad);
+                done(if ctx.point < ib(320) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            })
+            .with_max_parallelism(4),
+        );
+
+        assert_eq!(result, Ok(ib(320)));
+    }
+
+    #[test]
+    fn test_parasect_annotated() {
+        let result = parasect(
+            ParasectSettings::new(r(1, 100), |ctx| {
+                let annotations = Annotations {
+                    note: Some(format!("point {}", ctx.point)),
+                    metrics: Default::default(),
+                };
+                FreeCancellableTask::new(ParasectPayloadOutput::new(
+                    if ctx.point < ib(42) {
+                        Continue(Good)
+                    } else {
+                        Continue(Bad)
+                    },
+                    annotations,
+                ))
+            })
+            .with_max_parallelism(3),
+        );
+
+        assert_eq!(result, Ok(ib(42)));
+    }
+
+    #[test]
+    fn test_parasect_aligned() {
+        let result = parasect(
+            ParasectSettings::new(r(0, 500), |ProbeContext { point: x, .. }| {
+                assert_eq!(&x % ib(64), ib(1));
+                done(if x < ib(320) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            })
+            .with_alignment(Alignment::new(64u8, 1)),
+        );
+
+        assert_eq!(result, Ok(ib(321)));
+    }
+
+    #[test]
+    fn test_parasect_interrupted() {
+        let interrupt = AsyncValue::new();
+        let interrupt_clone = interrupt.clone();
+
+        let result = thread::scope(|scope| {
+            let search = scope.spawn(|| {
+                parasect(
+                    ParasectSettings::new(r(0, 100), |ctx| {
+                        // bad points never finish unless they're cancelled
+                        if ctx.point < ib(42) {
+                            AsyncValue::from(Some(Continue(Good).into()))
+                        } else {
+                            AsyncValue::new()
+                        }
+                    })
+                    .with_max_parallelism(2)
+                    .with_interrupt(interrupt_clone),
+                )
+            });
+
+            thread::sleep(Duration::from_millis(100));
+            interrupt.send(());
+            search.join().unwrap()
+        });
+
+        match result {
+            Err(Interrupted {
+                latest_good: Some(good),
+                earliest_bad: None,
+            }) => assert!(good < ib(42)),
+            x => panic!("expected an interruption after a good point, got {:?}", x),
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingPrefetcher {
+        prefetched: Mutex<Vec<IBig>>,
+        invalidated: Mutex<Vec<NumericRange>>,
+    }
+
+    impl Prefetcher for RecordingPrefetcher {
+        fn prefetch(&self, points: &[IBig]) {
+            self.prefetched.lock().unwrap().extend_from_slice(points);
+        }
+
+        fn invalidate(&self, range: &NumericRange) {
+            self.invalidated.lock().unwrap().push(range.clone());
+        }
+    }
+
+    #[test]
+    fn test_parasect_prefetcher() {
+        let prefetcher = Arc::new(RecordingPrefetcher::default());
+
+        let result = parasect(
+            ParasectSettings::new(r(0, 14), |ProbeContext { point: x, .. }| {
+                done(if x < ib(5) {
+                    Continue(Good)
+                } else {
+                    Continue(Bad)
+                })
+            })
+            .with_max_parallelism(1)
+            .with_prefetcher(prefetcher.clone(), 2),
+        );
+
+        assert_eq!(result, Ok(ib(5)));
+
+        let prefetched = prefetcher.prefetched.lock().unwrap();
+        assert!(!prefetched.is_empty());
+        assert!(prefetched.iter().all(|p| r(0, 14).contains(p.clone())));
+
+        let invalidated = prefetcher.invalidated.lock().unwrap();
+        assert!(invalidated.contains(&r(7, 14)));
+    }
+
+    #[test]
+    fn test_parasect_aligned_empty() {
+        let result = parasect(
+            ParasectSettings::new(r(2, 60), |_| done(Continue(Good)))
+                .with_alignment(Alignment::new(64u8, 1)),
+        );
+
+        assert_eq!(
+            result,
+            Err(InconsistencyError("Cannot parasect an empty range.".into()))
+        );
+    }
+
     proptest! {
         #[test]
         fn prop_parasect_fuzz(a in 1..1000, b in 1..1000, c in 1..1000) {
@@ -354,8 +665,8 @@ mod tests {
 
             let result =
                 parasect(
-                    ParasectSettings::new(r(lo, hi), |x|
-                        FreeCancellableTask::new(if x < IBig::from(lt) { Continue(Good) } else { Continue(Bad) })).with_max_parallelism(3));
+                    ParasectSettings::new(r(lo, hi), |ProbeContext { point: x, .. }|
+                        done(if x < IBig::from(lt) { Continue(Good) } else { Continue(Bad) })).with_max_parallelism(3));
 
             prop_assert_eq!(result, Ok(IBig::from(lt)));
         }
@@ -370,10 +681,10 @@ mod tests {
 
             let result =
                 parasect(
-                    ParasectSettings::new(r(lo, hi), |x|
+                    ParasectSettings::new(r(lo, hi), |ProbeContext { point: x, .. }|
                         FunctionCancellableTask::new(move || {
                         thread::sleep(Duration::from_millis(random::<u64>() % 7));
-                        if x < IBig::from(lt) { Continue(Good) } else { Continue(Bad) }
+                        ParasectPayloadOutput::from(if x < IBig::from(lt) { Continue(Good) } else { Continue(Bad) })
                     })).with_max_parallelism(3));
 
             prop_assert_eq!(result, Ok(IBig::from(lt)));
@@ -0,0 +1,12 @@
+use crate::range::numeric_range::NumericRange;
+use ibig::IBig;
+use std::fmt::Debug;
+
+/// Gets told ahead of time which points are likely to be probed soon, so expensive work for them (like builds) can start early.
+pub trait Prefetcher: Send + Sync + Debug {
+    /// Called whenever a probe starts, with the points the queue will hand out next, most likely first.
+    fn prefetch(&self, points: &[IBig]);
+
+    /// Called when no point in `range` will be probed anymore, so work prepared for those points can be abandoned.
+    fn invalidate(&self, range: &NumericRange);
+}
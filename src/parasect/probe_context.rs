@@ -0,0 +1,165 @@
+use crate::range::numeric_range::NumericRange;
+use crate::template::expression::Variables;
+use dashmap::DashMap;
+use ibig::IBig;
+use std::cmp::{max, min};
+use std::ops::Deref;
+use std::sync::RwLock;
+
+/// Everything a payload knows about the probe it's running.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
+pub struct ProbeContext {
+    /// The number being probed.
+    pub point: IBig,
+    pub worker_id: usize,
+    /// The whole range being searched.
+    pub range: NumericRange,
+    /// How many times `point` has been probed so far, including this time. Starts at 1.
+    pub attempt: usize,
+    /// The latest point known to be good when the probe started, or one before the range if none is known.
+    pub latest_good: IBig,
+    /// The earliest point known to be bad when the probe started, or one after the range if none is known.
+    pub earliest_bad: IBig,
+}
+
+impl ProbeContext {
+    /// The names `variables()` uses, in the same order.
+    pub const VARIABLE_NAMES: [&'static str; 7] = [
+        "PARASECT_VALUE",
+        "PARASECT_WORKER_ID",
+        "PARASECT_LOW",
+        "PARASECT_HIGH",
+        "PARASECT_ATTEMPT",
+        "PARASECT_LATEST_GOOD",
+        "PARASECT_EARLIEST_BAD",
+    ];
+
+    /// The context as named numbers, e.g. for the environment of a subprocess.
+    pub fn variables(&self) -> Vec<(&'static str, IBig)> {
+        let values = [
+            self.point.clone(),
+            IBig::from(self.worker_id),
+            self.range.first().unwrap(),
+            self.range.last().unwrap(),
+            IBig::from(self.attempt),
+            self.latest_good.clone(),
+            self.earliest_bad.clone(),
+        ];
+
+        Self::VARIABLE_NAMES.into_iter().zip(values).collect()
+    }
+
+    /// The context as variables for rendering a `TemplateString`.
+    pub fn template_variables(&self) -> Variables {
+        self.variables()
+            .into_iter()
+            .fold(Variables::new(self.point.clone()), |v, (name, value)| {
+                v.with(name, value)
+            })
+    }
+}
+
+/// State of the search that's shared between the controller and its workers.
+#[derive(Debug)]
+pub struct SearchState {
+    range: NumericRange,
+    latest_good: RwLock<IBig>,
+    earliest_bad: RwLock<IBig>,
+    attempts: DashMap<IBig, usize>,
+}
+
+impl SearchState {
+    /// Panics if `range` is empty.
+    pub fn new(range: NumericRange) -> Self {
+        Self {
+            latest_good: RwLock::new(range.first().unwrap() - 1),
+            earliest_bad: RwLock::new(range.last().unwrap() + 1),
+            range,
+            attempts: DashMap::new(),
+        }
+    }
+
+    pub fn latest_good(&self) -> IBig {
+        self.latest_good.read().unwrap().clone()
+    }
+
+    pub fn earliest_bad(&self) -> IBig {
+        self.earliest_bad.read().unwrap().clone()
+    }
+
+    pub fn adjust_latest_good(&self, point: &IBig) {
+        if self.latest_good.read().unwrap().deref() < point {
+            let mut guard = self.latest_good.write().unwrap();
+            *guard = max(guard.deref().clone(), point.clone());
+        }
+    }
+
+    pub fn adjust_earliest_bad(&self, point: &IBig) {
+        if self.earliest_bad.read().unwrap().deref() > point {
+            let mut guard = self.earliest_bad.write().unwrap();
+            *guard = min(guard.deref().clone(), point.clone());
+        }
+    }
+
+    /// Records a new attempt at probing `point`, and returns the context to probe it with.
+    pub fn start_probe(&self, point: IBig, worker_id: usize) -> ProbeContext {
+        let attempt = {
+            let mut entry = self.attempts.entry(point.clone()).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+
+        ProbeContext {
+            point,
+            worker_id,
+            range: self.range.clone(),
+            attempt,
+            latest_good: self.latest_good(),
+            earliest_bad: self.earliest_bad(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::{ib, r};
+
+    #[test]
+    fn test_start_probe() {
+        let state = SearchState::new(r(0, 100));
+
+        let ctx = state.start_probe(ib(50), 2);
+        assert_eq!(ctx.attempt, 1);
+        assert_eq!(ctx.latest_good, ib(-1));
+        assert_eq!(ctx.earliest_bad, ib(101));
+
+        state.adjust_latest_good(&ib(20));
+        state.adjust_latest_good(&ib(10));
+        state.adjust_earliest_bad(&ib(60));
+
+        let ctx = state.start_probe(ib(50), 3);
+        assert_eq!(ctx.attempt, 2);
+        assert_eq!(ctx.worker_id, 3);
+        assert_eq!(ctx.latest_good, ib(20));
+        assert_eq!(ctx.earliest_bad, ib(60));
+    }
+
+    #[test]
+    fn test_variables() {
+        let ctx = SearchState::new(r(5, 10)).start_probe(ib(7), 1);
+
+        assert_eq!(
+            ctx.variables(),
+            vec![
+                ("PARASECT_VALUE", ib(7)),
+                ("PARASECT_WORKER_ID", ib(1)),
+                ("PARASECT_LOW", ib(5)),
+                ("PARASECT_HIGH", ib(10)),
+                ("PARASECT_ATTEMPT", ib(1)),
+                ("PARASECT_LATEST_GOOD", ib(4)),
+                ("PARASECT_EARLIEST_BAD", ib(11)),
+            ]
+        );
+    }
+}
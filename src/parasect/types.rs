@@ -1,5 +1,7 @@
 use crate::parasect::types::ParasectPayloadAnswer::*;
 use crate::parasect::types::ParasectPayloadResult::*;
+use ibig::IBig;
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
 
 #[derive(PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone, Debug)]
@@ -18,9 +20,51 @@ impl Display for ParasectPayloadAnswer {
     }
 }
 
+/// Extra information a payload reports about a point, which doesn't affect the search.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, Default)]
+pub struct Annotations {
+    pub note: Option<String>,
+    /// Named measurements, kept as the text the payload reported them as.
+    pub metrics: BTreeMap<String, String>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.note.is_none() && self.metrics.is_empty()
+    }
+
+    /// Adds `other` to these annotations. Its note and metrics replace those with the same name.
+    pub fn merge(mut self, other: Annotations) -> Self {
+        if other.note.is_some() {
+            self.note = other.note;
+        }
+        self.metrics.extend(other.metrics);
+        self
+    }
+}
+
+impl Display for Annotations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let metrics = self
+            .metrics
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match (&self.note, metrics.is_empty()) {
+            (Some(note), true) => f.write_str(note),
+            (Some(note), false) => write!(f, "{}; {}", note, metrics),
+            (None, _) => f.write_str(&metrics),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub enum ParasectPayloadResult {
     Continue(ParasectPayloadAnswer),
+    /// The point couldn't be tested (e.g. its setup failed). The search continues around it.
+    Skip(String),
     Stop(String),
 }
 
@@ -28,14 +72,74 @@ impl Display for ParasectPayloadResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Continue(ans) => f.write_str(&format!("{}", ans)),
+            Skip(s) => f.write_str(&format!("Skipped ({})", s)),
             Stop(s) => f.write_str(&format!("Aborting ({})", s)),
         }?;
         Ok(())
     }
 }
 
+/// What a payload found at a point: the result the search acts on, and the annotations that came with it.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub struct ParasectPayloadOutput {
+    pub result: ParasectPayloadResult,
+    pub annotations: Annotations,
+}
+
+impl ParasectPayloadOutput {
+    pub fn new(result: ParasectPayloadResult, annotations: Annotations) -> Self {
+        Self {
+            result,
+            annotations,
+        }
+    }
+}
+
+impl From<ParasectPayloadResult> for ParasectPayloadOutput {
+    fn from(result: ParasectPayloadResult) -> Self {
+        Self::new(result, Annotations::default())
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub enum ParasectError {
     PayloadError(String),
     InconsistencyError(String),
+    /// Skipped points make it impossible to tell which point is the first bad one.
+    AmbiguityError(String),
+    /// The search was stopped before it finished, with the latest good and earliest bad points found so far (if any).
+    Interrupted {
+        latest_good: Option<IBig>,
+        earliest_bad: Option<IBig>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotations(note: Option<&str>, metrics: &[(&str, &str)]) -> Annotations {
+        Annotations {
+            note: note.map(|s| s.to_string()),
+            metrics: metrics
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_annotations() {
+        let a = annotations(Some("flaky infra"), &[("ms", "123")]);
+        assert_eq!(a.to_string(), "flaky infra; ms=123");
+        assert_eq!(annotations(None, &[("ms", "7")]).to_string(), "ms=7");
+        assert!(Annotations::default().is_empty());
+
+        let merged = a.merge(annotations(None, &[("ms", "7"), ("rss", "1K")]));
+        assert_eq!(merged.to_string(), "flaky infra; ms=7, rss=1K");
+        assert_eq!(
+            merged.merge(annotations(Some("slow"), &[])).note,
+            Some("slow".into())
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::messaging::listener::Listener;
 use crate::messaging::listener::ListenerBehavior::{ContinueProcessing, StopProcessing};
-use crate::parasect::types::ParasectPayloadResult;
+use crate::parasect::probe_context::{ProbeContext, SearchState};
+use crate::parasect::types::{Annotations, ParasectPayloadOutput, ParasectPayloadResult};
 use crate::parasect::worker::PointCompletionMessageType::*;
 use crate::range::bisecting_range_queue::BisectingRangeQueue;
 use crate::range::numeric_range::NumericRange;
@@ -13,7 +14,8 @@ use std::thread;
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Clone, Hash)]
 pub enum PointCompletionMessageType {
     Started,
-    Completed(ParasectPayloadResult),
+    /// The point's result, and the annotations the payload reported along with it.
+    Completed(ParasectPayloadResult, Annotations),
     Cancelled,
 }
 
@@ -28,11 +30,12 @@ pub struct WorkerMessage {
 
 pub struct Worker<TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: Fn(IBig) -> TTask + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: Fn(ProbeContext) -> TTask + Sync,
 {
     id: usize,
     queue: Arc<BisectingRangeQueue>,
+    state: Arc<SearchState>,
     cancel_sender: Sender<NumericRange>,
     cancel_receiver: Receiver<NumericRange>,
     worker_message_sender: Sender<WorkerMessage>,
@@ -41,12 +44,13 @@ where
 
 impl<TTask, FPayload> Worker<TTask, FPayload>
 where
-    TTask: CancellableTask<ParasectPayloadResult> + Send,
-    FPayload: Fn(IBig) -> TTask + Sync,
+    TTask: CancellableTask<ParasectPayloadOutput> + Send,
+    FPayload: Fn(ProbeContext) -> TTask + Sync,
 {
     pub fn new(
         id: usize,
         queue: Arc<BisectingRangeQueue>,
+        state: Arc<SearchState>,
         worker_message_sender: Sender<WorkerMessage>,
         payload: FPayload,
     ) -> Self {
@@ -55,6 +59,7 @@ where
         Self {
             id,
             queue,
+            state,
             cancel_sender,
             cancel_receiver,
             worker_message_sender,
@@ -67,11 +72,11 @@ where
         midpoint: IBig,
         left: NumericRange,
         right: NumericRange,
-        result: Option<ParasectPayloadResult>,
+        output: Option<ParasectPayloadOutput>,
     ) -> WorkerMessage {
-        let msg_type = match result {
+        let msg_type = match output {
             None => Cancelled,
-            Some(a) => Completed(a),
+            Some(o) => Completed(o.result, o.annotations),
         };
 
         WorkerMessage {
@@ -95,7 +100,7 @@ where
                 })
                 .expect("worker_message_sender closed unexpectedly.");
 
-            let task = (self.payload)(midpoint.clone());
+            let task = (self.payload)(self.state.start_probe(midpoint.clone(), self.id));
 
             let v = thread::scope(|scope| {
                 let cancel_receiver_loop =
@@ -139,7 +144,8 @@ mod tests {
         let range_queue = Arc::new(BisectingRangeQueue::new(r(0, 10)));
         let rq_clone = range_queue.clone();
 
-        let worker = Worker::new(0, range_queue, send, |_range| AsyncValue::new());
+        let state = Arc::new(SearchState::new(r(0, 10)));
+        let worker = Worker::new(0, range_queue, state, send, |_ctx| AsyncValue::new());
 
         thread::scope(|scope| {
             rq_clone.invalidate(&r(0, 4));
@@ -0,0 +1,193 @@
+use crate::parasect::probe_context::ProbeContext;
+use clap::ValueEnum;
+use glob::Pattern;
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Which probes' artifacts are kept.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum CollectFor {
+    /// Every probe that was bad or failed to run.
+    #[default]
+    Bad,
+    /// Only the latest good and first bad points, once the search is done.
+    Boundary,
+    /// Every probe.
+    All,
+}
+
+/// Copies the files matching some globs out of each probe's working directory into `<dir>/<point>/`, keeping their relative paths, e.g. to keep core dumps or test reports.
+///
+/// Retries of a point go to `<dir>/<point>.attemptN/`.
+#[derive(Debug)]
+pub struct Artifacts {
+    dir: PathBuf,
+    patterns: Vec<String>,
+    policy: CollectFor,
+    collected: Mutex<BTreeMap<IBig, PathBuf>>,
+}
+
+impl Artifacts {
+    /// Fails if one of the patterns isn't a valid glob.
+    pub fn new(
+        dir: impl AsRef<Path>,
+        patterns: Vec<String>,
+        policy: CollectFor,
+    ) -> Result<Self, String> {
+        for p in &patterns {
+            Pattern::new(p).map_err(|e| format!("Invalid glob \"{}\": {}", p, e.msg))?;
+        }
+
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            patterns,
+            policy,
+            collected: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Whether the artifacts of a probe that ended this way should be collected.
+    pub fn wants(&self, failed: bool) -> bool {
+        failed || self.policy != CollectFor::Bad
+    }
+
+    fn point_dir(&self, ctx: &ProbeContext) -> PathBuf {
+        if ctx.attempt == 1 {
+            self.dir.join(ctx.point.to_string())
+        } else {
+            self.dir
+                .join(format!("{}.attempt{}", ctx.point, ctx.attempt))
+        }
+    }
+
+    /// Copies the files of the probe in `ctx` that match the patterns out of `cwd`. Returns how many were copied.
+    ///
+    /// Files already in the collect directory never match, in case it's inside `cwd`. A later attempt at the same point replaces the earlier one in `collected`, but its directory is left alone.
+    pub fn collect(&self, ctx: &ProbeContext, cwd: &Path) -> Result<usize, String> {
+        let dest = self.point_dir(ctx);
+        let _ = fs::remove_dir_all(&dest);
+        let collected_before =
+            |path: &Path| match (fs::canonicalize(&self.dir), fs::canonicalize(path)) {
+                (Ok(dir), Ok(path)) => path.starts_with(dir),
+                _ => false,
+            };
+
+        let mut copied = 0;
+        for pattern in &self.patterns {
+            let full = format!("{}/{}", Pattern::escape(&cwd.to_string_lossy()), pattern);
+            let paths = glob::glob(&full).map_err(|e| e.msg.to_string())?;
+
+            for path in paths
+                .filter_map(Result::ok)
+                .filter(|p| p.is_file() && !collected_before(p))
+            {
+                let to = dest.join(path.strip_prefix(cwd).unwrap_or(&path));
+                fs::create_dir_all(to.parent().unwrap())
+                    .and_then(|_| fs::copy(&path, &to))
+                    .map_err(|e| format!("Failed to copy \"{}\": {}", path.display(), e))?;
+                copied += 1;
+            }
+        }
+
+        if copied > 0 {
+            self.collected
+                .lock()
+                .unwrap()
+                .insert(ctx.point.clone(), dest);
+        }
+        Ok(copied)
+    }
+
+    /// With `CollectFor::Boundary`, deletes the artifacts of every point but the given ones. Does nothing otherwise.
+    pub fn keep_only(&self, points: &[&IBig]) {
+        if self.policy != CollectFor::Boundary {
+            return;
+        }
+
+        self.collected.lock().unwrap().retain(|point, dir| {
+            let keep = points.contains(&point);
+            if !keep {
+                let _ = fs::remove_dir_all(dir);
+            }
+            keep
+        });
+    }
+
+    /// Where the artifacts of each point that had any went.
+    pub fn collected(&self) -> BTreeMap<IBig, PathBuf> {
+        self.collected.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::parasect::probe_context::SearchState;
+    use crate::test_util::test_util::test_util::{ib, r, TestDir};
+
+    #[test]
+    fn test_collect() {
+        let dir = TestDir::new("artifacts");
+        let cwd = dir.join("cwd");
+        fs::create_dir_all(cwd.join("test-results/nested")).unwrap();
+        fs::write(cwd.join("a.core"), "core").unwrap();
+        fs::write(cwd.join("test-results/nested/b.core"), "core").unwrap();
+        fs::write(cwd.join("test-results/c.xml"), "xml").unwrap();
+        fs::write(cwd.join("d.txt"), "txt").unwrap();
+
+        let artifacts = Artifacts::new(
+            dir.join("results"),
+            vec!["**/*.core".into(), "test-results/*.xml".into()],
+            CollectFor::Boundary,
+        )
+        .unwrap();
+        let state = SearchState::new(r(0, 10));
+
+        assert_eq!(artifacts.collect(&state.start_probe(ib(4), 0), &cwd), Ok(3));
+        assert_eq!(
+            fs::read_to_string(dir.join("results/4/test-results/nested/b.core")).unwrap(),
+            "core"
+        );
+        assert!(dir.join("results/4/test-results/c.xml").exists());
+        assert!(!dir.join("results/4/d.txt").exists());
+
+        // the collect directory is never collected from, even when it's inside the working directory
+        let nested = Artifacts::new(
+            cwd.join("results"),
+            vec!["**/*.core".into()],
+            CollectFor::All,
+        )
+        .unwrap();
+        for point in [1, 2, 3] {
+            assert_eq!(
+                nested.collect(&state.start_probe(ib(point), 0), &cwd),
+                Ok(2)
+            );
+        }
+        assert!(!cwd.join("results/3/results").exists());
+
+        let mut ctx = state.start_probe(ib(5), 0);
+        ctx.attempt = 2;
+        artifacts.collect(&ctx, &cwd).unwrap();
+        assert_eq!(
+            artifacts.collected(),
+            BTreeMap::from([
+                (ib(4), dir.join("results/4")),
+                (ib(5), dir.join("results/5.attempt2"))
+            ])
+        );
+
+        artifacts.keep_only(&[&ib(5)]);
+        assert!(!dir.join("results/4").exists());
+        assert_eq!(artifacts.collected().len(), 1);
+
+        assert_eq!(
+            Artifacts::new(&dir, vec!["***".into()], CollectFor::Bad).unwrap_err(),
+            "Invalid glob \"***\": wildcards are either regular `*` or recursive `**`"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::collections::collect_collection::CollectVec;
+use crate::command_gen::CommandGen;
+use crate::parasect::prefetcher::Prefetcher;
+use crate::probe::probe_task::{ProbeSteps, ProbeTask};
+use crate::probe::scratch::ScratchDirs;
+use crate::range::numeric_range::NumericRange;
+use crate::task::cancellable_subprocess::SubprocessOptions;
+use crate::task::cancellable_task::CancellableTask;
+use crate::util::macros::unwrap_or;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Build = ProbeTask<Result<PathBuf, String>>;
+
+/// Runs the build command for the points being probed, with its own limit on how many builds run at once.
+///
+/// Each point is built at most once, and its artifact directory is shared by every probe of that point. As a `Prefetcher`, it also starts builds for the points the search will likely need next, but only while build slots are free, and cancels builds for points that won't be probed anymore.
+pub struct BuildPool {
+    command_gen: CommandGen,
+    scratch: Arc<ScratchDirs>,
+    // holds one message per free build slot
+    free_slots: (Sender<()>, Receiver<()>),
+    builds: Mutex<BTreeMap<IBig, Arc<Build>>>,
+    stderr_to_stderr: bool,
+}
+
+impl BuildPool {
+    pub fn new(command_gen: CommandGen, scratch: Arc<ScratchDirs>, parallelism: usize) -> Self {
+        let free_slots = bounded(parallelism);
+        for _ in 0..parallelism {
+            free_slots.0.send(()).unwrap();
+        }
+
+        Self {
+            command_gen,
+            scratch,
+            free_slots,
+            builds: Mutex::new(BTreeMap::new()),
+            stderr_to_stderr: false,
+        }
+    }
+
+    /// Passes the builds' stderr through to parasect's stderr instead of its stdout.
+    pub fn with_stderr_to_stderr(mut self, stderr_to_stderr: bool) -> Self {
+        self.stderr_to_stderr = stderr_to_stderr;
+        self
+    }
+
+    fn run_build(
+        steps: &ProbeSteps,
+        point: &IBig,
+        command: Result<Vec<String>, String>,
+        artifact_dir: Result<PathBuf, String>,
+        options: SubprocessOptions,
+    ) -> Option<Result<PathBuf, String>> {
+        let (command, artifact_dir) = match (command, artifact_dir) {
+            (Ok(c), Ok(d)) => (c, d),
+            (Err(e), _) | (_, Err(e)) => return Some(Err(e)),
+        };
+
+        let options = options
+            .with_env("PARASECT_VALUE", point.to_string())
+            .with_env("PARASECT_ARTIFACT_DIR", artifact_dir.display().to_string());
+
+        Some(match steps.run(&command, &options)? {
+            Ok(output) if output.status.success() => Ok(artifact_dir),
+            Ok(output) => Err(format!("The build command failed ({})", output.status)),
+            Err(e) => Err(format!(
+                "Failed to execute the build command {:?}: {}",
+                command, e
+            )),
+        })
+    }
+
+    /// Starts building the given point. If `has_slot` is false, the build first waits for a free slot.
+    fn start(&self, point: &IBig, has_slot: bool) -> Arc<Build> {
+        let command = self.command_gen.command_for_number(point);
+        let artifact_dir = self.scratch.artifact_dir(point).map_err(|e| {
+            format!(
+                "Failed to create the artifact directory for {}: {}",
+                point, e
+            )
+        });
+        let (release, acquire) = self.free_slots.clone();
+        let point = point.clone();
+        let options = SubprocessOptions::new().with_stderr_to_stderr(self.stderr_to_stderr);
+
+        Arc::new(ProbeTask::spawn(move |steps| {
+            if !has_slot {
+                while acquire.recv_timeout(Duration::from_millis(50)).is_err() {
+                    if steps.cancelled() {
+                        return None;
+                    }
+                }
+            }
+
+            let result = Self::run_build(steps, &point, command, artifact_dir, options);
+            release.send(()).unwrap();
+            result
+        }))
+    }
+
+    /// Waits for the given point's build, starting it if it hasn't been started yet.
+    ///
+    /// Returns the point's artifact directory, or why the build failed. Returns None if `steps` is cancelled while waiting.
+    pub fn wait_for_build(
+        &self,
+        point: &IBig,
+        steps: &ProbeSteps,
+    ) -> Option<Result<PathBuf, String>> {
+        let build = self
+            .builds
+            .lock()
+            .unwrap()
+            .entry(point.clone())
+            .or_insert_with(|| self.start(point, false))
+            .clone();
+
+        steps.wait_for(build.as_ref())
+    }
+}
+
+impl Prefetcher for BuildPool {
+    fn prefetch(&self, points: &[IBig]) {
+        let mut builds = self.builds.lock().unwrap();
+
+        for point in points {
+            if builds.contains_key(point) {
+                continue;
+            }
+            if self.free_slots.1.try_recv().is_err() {
+                return;
+            }
+            builds.insert(point.clone(), self.start(point, true));
+        }
+    }
+
+    fn invalidate(&self, range: &NumericRange) {
+        let (low, high) = unwrap_or!(range.as_tuple(), return);
+        let mut builds = self.builds.lock().unwrap();
+
+        let invalidated = builds
+            .range(low..=high)
+            .map(|(k, _)| k.clone())
+            .collect_vec();
+        for point in invalidated {
+            if let Some(build) = builds.remove(&point) {
+                build.request_cancellation();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BuildPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildPool")
+            .field("command_gen", &self.command_gen)
+            .field("builds", &self.builds.lock().unwrap().keys().collect_vec())
+            .finish()
+    }
+}
+
+impl Drop for BuildPool {
+    fn drop(&mut self) {
+        for build in self.builds.lock().unwrap().values() {
+            build.request_cancellation();
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::probe::scratch::ScratchMode;
+    use crate::test_util::test_util::test_util::{ib, r, TestDir};
+    use std::path::Path;
+    use std::time::Instant;
+    use std::{fs, thread};
+
+    fn pool(dir: &Path, script: &str, parallelism: usize) -> Arc<BuildPool> {
+        Arc::new(BuildPool::new(
+            CommandGen::new_without_value([script.to_string()], "$X".into())
+                .unwrap()
+                .with_shell(true),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+            parallelism,
+        ))
+    }
+
+    fn wait_for_build(pool: &Arc<BuildPool>, point: IBig) -> Build {
+        let pool = pool.clone();
+        ProbeTask::spawn(move |steps| pool.wait_for_build(&point, steps))
+    }
+
+    #[test]
+    fn test_builds_are_shared() {
+        let dir = TestDir::new("build-pool-shared");
+        let log = dir.join("log");
+        let pool = pool(
+            &dir,
+            &format!(
+                "echo $X >> '{}'; echo $X > \"$PARASECT_ARTIFACT_DIR/out\"",
+                log.display()
+            ),
+            2,
+        );
+
+        let a = wait_for_build(&pool, ib(4));
+        let b = wait_for_build(&pool, ib(4));
+
+        let artifact_dir = a.wait().unwrap().unwrap();
+        assert_eq!(b.wait(), Some(Ok(artifact_dir.clone())));
+        assert_eq!(fs::read_to_string(artifact_dir.join("out")).unwrap(), "4\n");
+        assert_eq!(fs::read_to_string(&log).unwrap(), "4\n");
+    }
+
+    #[test]
+    fn test_build_failure() {
+        let dir = TestDir::new("build-pool-failure");
+        let pool = pool(&dir, "test $X -lt 5", 1);
+
+        assert!(matches!(wait_for_build(&pool, ib(3)).wait(), Some(Ok(_))));
+        assert!(matches!(wait_for_build(&pool, ib(7)).wait(), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_prefetch_only_uses_free_slots() {
+        let dir = TestDir::new("build-pool-prefetch");
+        let pool = pool(&dir, "sleep 5", 1);
+
+        pool.prefetch(&[ib(3), ib(11)]);
+        assert_eq!(
+            pool.builds.lock().unwrap().keys().cloned().collect_vec(),
+            vec![ib(3)]
+        );
+
+        let start = Instant::now();
+        let build = pool.builds.lock().unwrap()[&ib(3)].clone();
+        thread::sleep(Duration::from_millis(50));
+
+        pool.invalidate(&r(0, 6));
+        assert!(pool.builds.lock().unwrap().is_empty());
+        assert_eq!(build.wait(), None);
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        pool.prefetch(&[ib(11)]);
+        assert!(pool.builds.lock().unwrap().contains_key(&ib(11)));
+    }
+}
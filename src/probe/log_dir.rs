@@ -0,0 +1,140 @@
+use crate::parasect::probe_context::ProbeContext;
+use crate::task::cancellable_subprocess::OutputLog;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// A directory that the output of every probe is written to, as `<point>.out` and `<point>.err`.
+///
+/// Retries of a point go to `<point>.attemptN.out` and so on. When there are several checks, each one gets its own files, e.g. `<point>.lint.out`, and setup and teardown always do, e.g. `<point>.setup.out`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LogDir {
+    dir: PathBuf,
+}
+
+/// Where the output of each command of one probe goes.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ProbeLogs {
+    /// One per check, in the same order as the checks.
+    pub commands: Vec<OutputLog>,
+    pub setup: OutputLog,
+    pub teardown: OutputLog,
+}
+
+/// Keeps a check's name from escaping the directory or making odd file names.
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl LogDir {
+    /// Uses `dir` for the logs, creating it if needed.
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// The log files of the probe in `ctx`, given the names of its checks.
+    pub fn logs_for(&self, ctx: &ProbeContext, checks: &[&str]) -> ProbeLogs {
+        let base = if ctx.attempt == 1 {
+            ctx.point.to_string()
+        } else {
+            format!("{}.attempt{}", ctx.point, ctx.attempt)
+        };
+        let log = |step: Option<&str>| {
+            let name = match step {
+                Some(step) => format!("{}.{}", base, file_name_part(step)),
+                None => base.clone(),
+            };
+            OutputLog {
+                stdout: self.dir.join(format!("{}.out", name)),
+                stderr: self.dir.join(format!("{}.err", name)),
+            }
+        };
+
+        ProbeLogs {
+            commands: match checks {
+                [_] => vec![log(None)],
+                checks => checks.iter().map(|c| log(Some(c))).collect(),
+            },
+            setup: log(Some("setup")),
+            teardown: log(Some("teardown")),
+        }
+    }
+}
+
+impl ProbeLogs {
+    fn all(&self) -> impl Iterator<Item = &OutputLog> {
+        self.commands.iter().chain([&self.setup, &self.teardown])
+    }
+
+    /// Removes the files left by an earlier run, since the logs are appended to (e.g. once per sample of a metric).
+    pub fn clear(&self) -> Result<(), String> {
+        for path in self.all().flat_map(|l| [&l.stdout, &l.stderr]) {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(format!(
+                        "Failed to remove the old log file \"{}\": {}",
+                        path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The files that were written to, in the order the commands run.
+    pub fn written(&self) -> Vec<&Path> {
+        [&self.setup]
+            .into_iter()
+            .chain(&self.commands)
+            .chain([&self.teardown])
+            .flat_map(|l| [l.stdout.as_path(), l.stderr.as_path()])
+            .filter(|p| p.exists())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::parasect::probe_context::SearchState;
+    use crate::test_util::test_util::test_util::{ib, r, TestDir};
+
+    #[test]
+    fn test_logs_for() {
+        let dir = TestDir::new("log-dir");
+        let log_dir = LogDir::new(&dir).unwrap();
+        let mut ctx = SearchState::new(r(0, 10)).start_probe(ib(4), 0);
+
+        let logs = log_dir.logs_for(&ctx, &["command"]);
+        assert_eq!(logs.commands[0].stdout, dir.join("4.out"));
+        assert_eq!(logs.setup.stderr, dir.join("4.setup.err"));
+
+        ctx.attempt = 2;
+        let logs = log_dir.logs_for(&ctx, &["lint", "a/b"]);
+        assert_eq!(
+            logs.commands.iter().map(|l| &l.stdout).collect::<Vec<_>>(),
+            vec![
+                &dir.join("4.attempt2.lint.out"),
+                &dir.join("4.attempt2.a_b.out")
+            ]
+        );
+
+        fs::write(&logs.commands[1].stderr, "err").unwrap();
+        assert_eq!(logs.written(), vec![logs.commands[1].stderr.as_path()]);
+        logs.clear().unwrap();
+        assert_eq!(logs.written(), Vec::<&Path>::new());
+    }
+}
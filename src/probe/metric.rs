@@ -0,0 +1,349 @@
+use crate::parasect::types::Annotations;
+use crate::parasect::types::ParasectPayloadAnswer;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use clap::ValueEnum;
+use regex::Regex;
+use std::fmt::{Display, Formatter};
+
+/// Which side of the threshold is bad.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+/// The value a metric must cross for a point to be bad, e.g. `>250` or `>+10%`.
+///
+/// A relative threshold is a change from the baseline, the value measured at the low end of the range.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Threshold {
+    pub comparison: Comparison,
+    pub value: f64,
+    pub relative: bool,
+}
+
+impl Threshold {
+    /// Parses `>N` or `<N` for a fixed threshold, and `>N%` or `<N%` (e.g. `>+10%`, `<-5%`) for one relative to the baseline.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (comparison, rest) = if let Some(rest) = s.strip_prefix('>') {
+            (Comparison::Above, rest)
+        } else if let Some(rest) = s.strip_prefix('<') {
+            (Comparison::Below, rest)
+        } else {
+            return Err("Expected it to start with \">\" or \"<\"".into());
+        };
+
+        let (number, relative) = match rest.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (rest, false),
+        };
+        let value = number
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("\"{}\" isn't a number", number))?;
+
+        Ok(Self {
+            comparison,
+            value: if relative { value / 100.0 } else { value },
+            relative,
+        })
+    }
+
+    /// The value a metric is compared against, given the mean of the baseline (if any).
+    pub fn limit(&self, baseline: Option<f64>) -> f64 {
+        match (self.relative, baseline) {
+            (true, Some(b)) => b * (1.0 + self.value),
+            _ => self.value,
+        }
+    }
+
+    fn crossed(&self, value: f64, limit: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => value > limit,
+            Comparison::Below => value < limit,
+        }
+    }
+}
+
+impl Display for Threshold {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let op = match self.comparison {
+            Comparison::Above => ">",
+            Comparison::Below => "<",
+        };
+        if self.relative {
+            let sign = if self.value < 0.0 { "" } else { "+" };
+            write!(f, "{}{}{}%", op, sign, format_number(self.value * 100.0))
+        } else {
+            write!(f, "{}{}", op, self.value)
+        }
+    }
+}
+
+/// How the samples of a point are compared against the threshold.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum MetricTest {
+    /// Bad if the mean of the samples crosses the threshold.
+    #[default]
+    Mean,
+    /// Bad if the whole 95% confidence interval of the mean crosses the threshold. Needs at least 2 samples.
+    Ci,
+    /// Bad if a one-sided Mann-Whitney U test says the samples crossed the baseline's samples shifted by the threshold, at 95% confidence. Needs a relative threshold and at least 3 samples, since fewer can never reach that confidence.
+    MannWhitney,
+}
+
+/// A number measured from each probe's output, which decides whether the point is good or bad.
+#[derive(Clone, Debug)]
+pub struct Metric {
+    pattern: Option<Regex>,
+    threshold: Threshold,
+    samples: usize,
+    test: MetricTest,
+    baseline: Option<Vec<f64>>,
+}
+
+/// Formats a measurement without the noise of floating point, e.g. `12.5` rather than `12.500000000000002`.
+fn format_number(x: f64) -> String {
+    let s = format!("{:.3}", x);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    s.to_string()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    let m = mean(values);
+    let var = values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    var.sqrt()
+}
+
+/// The one-sided 95% quantile of Student's t-distribution with the given degrees of freedom.
+fn t_quantile(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        6.314, 2.920, 2.353, 2.132, 2.015, 1.943, 1.895, 1.860, 1.833, 1.812, 1.796, 1.782, 1.771,
+        1.761, 1.753, 1.746, 1.740, 1.734, 1.729, 1.725, 1.721, 1.717, 1.714, 1.711, 1.708, 1.706,
+        1.703, 1.701, 1.699, 1.697,
+    ];
+    TABLE.get(df.max(1) - 1).copied().unwrap_or(1.645)
+}
+
+/// The z-score of the Mann-Whitney U statistic of `xs` against `ys`, using the normal approximation.
+///
+/// Positive if `xs` tends to be greater than `ys`.
+fn mann_whitney_z(xs: &[f64], ys: &[f64]) -> f64 {
+    let u: f64 = xs
+        .iter()
+        .flat_map(|x| ys.iter().map(move |y| (x, y)))
+        .map(|(x, y)| match x.partial_cmp(y) {
+            Some(std::cmp::Ordering::Greater) => 1.0,
+            Some(std::cmp::Ordering::Equal) => 0.5,
+            _ => 0.0,
+        })
+        .sum();
+    let (n1, n2) = (xs.len() as f64, ys.len() as f64);
+    let sd = (n1 * n2 * (n1 + n2 + 1.0) / 12.0).sqrt();
+
+    (u - n1 * n2 / 2.0) / sd
+}
+
+impl Metric {
+    pub fn new(threshold: Threshold) -> Self {
+        Self {
+            pattern: None,
+            threshold,
+            samples: 1,
+            test: MetricTest::default(),
+            baseline: None,
+        }
+    }
+
+    /// Reads the metric from the first match of `pattern` (its first capture group, if it has one) instead of the whole output.
+    pub fn with_pattern(mut self, pattern: Option<Regex>) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Runs the command this many times per point.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_test(mut self, test: MetricTest) -> Self {
+        self.test = test;
+        self
+    }
+
+    /// Sets the samples measured at the low end of the range, which relative thresholds are relative to.
+    pub fn with_baseline(mut self, baseline: Vec<f64>) -> Self {
+        self.baseline = Some(baseline);
+        self
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// `true` if the baseline must be measured before any point can be decided.
+    pub fn needs_baseline(&self) -> bool {
+        self.threshold.relative && self.baseline.is_none()
+    }
+
+    /// Reads the metric from a command's output.
+    pub fn extract(&self, output: &str) -> Result<f64, String> {
+        let text = match &self.pattern {
+            Some(pattern) => {
+                let captures = pattern.captures(output).ok_or_else(|| {
+                    format!("The output didn't match the metric regex \"{}\"", pattern)
+                })?;
+                captures.get(1).or(captures.get(0)).unwrap().as_str()
+            }
+            None => output,
+        };
+
+        text.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Couldn't read the metric from \"{}\"", text.trim()))
+    }
+
+    /// Decides whether the point is good or bad from its samples.
+    pub fn decide(&self, values: &[f64]) -> ParasectPayloadAnswer {
+        let baseline = self.baseline.as_deref();
+        let limit = self.threshold.limit(baseline.map(mean));
+
+        let bad = match self.test {
+            MetricTest::Mean => self.threshold.crossed(mean(values), limit),
+            MetricTest::Ci => {
+                let margin =
+                    t_quantile(values.len() - 1) * std_dev(values) / (values.len() as f64).sqrt();
+                match self.threshold.comparison {
+                    Comparison::Above => mean(values) - margin > limit,
+                    Comparison::Below => mean(values) + margin < limit,
+                }
+            }
+            MetricTest::MannWhitney => {
+                let shifted = baseline
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|b| b * (1.0 + self.threshold.value))
+                    .collect::<Vec<_>>();
+                let z = mann_whitney_z(values, &shifted);
+                match self.threshold.comparison {
+                    Comparison::Above => z > 1.645,
+                    Comparison::Below => z < -1.645,
+                }
+            }
+        };
+
+        if bad {
+            Bad
+        } else {
+            Good
+        }
+    }
+
+    /// What to show about the samples in the logs: their mean, and the change from the baseline if there is one.
+    pub fn annotations(&self, values: &[f64]) -> Annotations {
+        let mut ret = Annotations::default();
+        let m = mean(values);
+
+        ret.metrics.insert("metric".into(), format_number(m));
+        if values.len() > 1 {
+            ret.metrics
+                .insert("samples".into(), values.len().to_string());
+        }
+        if let Some(b) = self.baseline.as_deref().map(mean).filter(|b| *b != 0.0) {
+            ret.metrics
+                .insert("change".into(), format!("{:+.1}%", (m / b - 1.0) * 100.0));
+        }
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_parse() {
+        assert_eq!(
+            Threshold::parse(">250"),
+            Ok(Threshold {
+                comparison: Comparison::Above,
+                value: 250.0,
+                relative: false
+            })
+        );
+        assert_eq!(
+            Threshold::parse("<-5%"),
+            Ok(Threshold {
+                comparison: Comparison::Below,
+                value: -0.05,
+                relative: true
+            })
+        );
+        assert!((Threshold::parse(">+10%").unwrap().limit(Some(200.0)) - 220.0).abs() < 1e-9);
+        assert_eq!(Threshold::parse(">+10%").unwrap().to_string(), ">+10%");
+        assert!(Threshold::parse("250").is_err());
+        assert_eq!(
+            Threshold::parse(">fast"),
+            Err("\"fast\" isn't a number".into())
+        );
+    }
+
+    #[test]
+    fn test_extract() {
+        let metric = Metric::new(Threshold::parse(">1").unwrap());
+        assert_eq!(metric.extract(" 12.5\n"), Ok(12.5));
+        assert!(metric.extract("took 12.5ms").is_err());
+
+        let metric = metric.with_pattern(Some(Regex::new(r"took ([\d.]+)ms").unwrap()));
+        assert_eq!(metric.extract("start\ntook 12.5ms\n"), Ok(12.5));
+        assert_eq!(
+            metric.extract("crashed"),
+            Err("The output didn't match the metric regex \"took ([\\d.]+)ms\"".into())
+        );
+
+        let metric = metric.with_pattern(Some(Regex::new(r"\d+").unwrap()));
+        assert_eq!(metric.extract("rss 2048 kB"), Ok(2048.0));
+    }
+
+    #[test]
+    fn test_decide_mean() {
+        let metric = Metric::new(Threshold::parse(">+10%").unwrap()).with_baseline(vec![100.0]);
+
+        assert_eq!(metric.decide(&[105.0, 112.0]), Good);
+        assert_eq!(metric.decide(&[111.0]), Bad);
+
+        let annotations = metric.annotations(&[105.0, 112.0]);
+        assert_eq!(
+            annotations.to_string(),
+            "change=+8.5%, metric=108.5, samples=2"
+        );
+    }
+
+    #[test]
+    fn test_decide_ci() {
+        let metric = Metric::new(Threshold::parse(">100").unwrap()).with_test(MetricTest::Ci);
+
+        // the mean is over the threshold, but too noisy to be sure
+        assert_eq!(metric.decide(&[90.0, 130.0, 110.0]), Good);
+        assert_eq!(metric.decide(&[109.0, 111.0, 110.0]), Bad);
+    }
+
+    #[test]
+    fn test_decide_mann_whitney() {
+        let metric = Metric::new(Threshold::parse("<-10%").unwrap())
+            .with_test(MetricTest::MannWhitney)
+            .with_baseline(vec![100.0, 101.0, 99.0, 100.0]);
+
+        assert_eq!(metric.decide(&[80.0, 81.0, 79.0, 80.0]), Bad);
+        assert_eq!(metric.decide(&[95.0, 96.0, 94.0, 95.0]), Good);
+        assert_eq!(metric.decide(&[80.0, 100.0, 79.0, 101.0]), Good);
+    }
+}
@@ -0,0 +1,13 @@
+pub mod artifacts;
+pub mod build_pool;
+pub mod log_dir;
+pub mod metric;
+pub mod output_diff;
+pub mod output_rules;
+pub mod predicate;
+pub mod probe;
+pub mod probe_task;
+pub mod resource_limits;
+pub mod result_file;
+pub mod scratch;
+pub mod template_file;
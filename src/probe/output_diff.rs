@@ -0,0 +1,187 @@
+use crate::parasect::types::ParasectPayloadAnswer;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::task::cancellable_subprocess::SubprocessOutput;
+use ibig::IBig;
+use regex::Regex;
+use similar::TextDiff;
+
+/// What one command of a probe printed.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn new(output: &SubprocessOutput) -> Self {
+        Self {
+            stdout: output.output.clone().unwrap_or_default(),
+            stderr: output.stderr.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// The output of each check of a probe, in the same order as the checks. None for a check that was stopped before it finished.
+pub type ProbeOutput = Vec<Option<CommandOutput>>;
+
+/// The output of the latest good and earliest bad points probed so far.
+///
+/// Every good point is before every bad one, so these are the only ones that can end up around the first bad point, and the rest can be dropped as soon as they're probed.
+#[derive(Clone, Debug, Default)]
+pub struct BoundaryOutputs {
+    good: Option<(IBig, ProbeOutput)>,
+    bad: Option<(IBig, ProbeOutput)>,
+}
+
+impl BoundaryOutputs {
+    /// Keeps `output` if `point` is the latest good or earliest bad point so far, depending on `answer`.
+    pub fn offer(&mut self, point: &IBig, answer: ParasectPayloadAnswer, output: ProbeOutput) {
+        let (kept, closer): (_, fn(&IBig, &IBig) -> bool) = match answer {
+            Good => (&mut self.good, |p: &IBig, kept: &IBig| p >= kept),
+            Bad => (&mut self.bad, |p: &IBig, kept: &IBig| p <= kept),
+        };
+        if kept.as_ref().is_none_or(|(k, _)| closer(point, k)) {
+            *kept = Some((point.clone(), output));
+        }
+    }
+
+    /// The output of `point`, if it's still kept as the given answer.
+    pub fn get(&self, point: &IBig, answer: ParasectPayloadAnswer) -> Option<&ProbeOutput> {
+        let kept = match answer {
+            Good => &self.good,
+            Bad => &self.bad,
+        };
+        kept.as_ref()
+            .filter(|(p, _)| p == point)
+            .map(|(_, output)| output)
+    }
+}
+
+/// Compares the output of two points line by line, leaving out lines that match any of `ignore`, e.g. timestamps and PIDs.
+#[derive(Clone, Debug, Default)]
+pub struct OutputDiff {
+    ignore: Vec<Regex>,
+}
+
+impl OutputDiff {
+    pub fn new(ignore: Vec<Regex>) -> Self {
+        Self { ignore }
+    }
+
+    fn filter(&self, text: &str) -> String {
+        text.split_inclusive('\n')
+            .filter(|line| {
+                let line = line.trim_end_matches('\n');
+                !self.ignore.iter().any(|r| r.is_match(line))
+            })
+            .collect()
+    }
+
+    /// A unified diff from `old` to `new`, with the given names in its header. Empty if they only differ in ignored lines.
+    pub fn diff(&self, old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+        let (old, new) = (self.filter(old), self.filter(new));
+        TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .header(old_name, new_name)
+            .to_string()
+    }
+
+    /// Diffs stdout and stderr of every check between the `good` and `bad` points, given the names of the checks.
+    ///
+    /// The names in the headers are those of the log files, e.g. `12.out` or `12.lint.err`. Checks that didn't finish at either point are left out.
+    pub fn compare(
+        &self,
+        good: (&IBig, &ProbeOutput),
+        bad: (&IBig, &ProbeOutput),
+        checks: &[&str],
+    ) -> String {
+        let name = |point: &IBig, check: &str, stream: &str| match checks {
+            [_] => format!("{}.{}", point, stream),
+            _ => format!("{}.{}.{}", point, check, stream),
+        };
+
+        checks
+            .iter()
+            .zip(good.1.iter().zip(bad.1.iter()))
+            .filter_map(|(check, outputs)| match outputs {
+                (Some(g), Some(b)) => Some((check, g, b)),
+                _ => None,
+            })
+            .flat_map(|(check, g, b)| {
+                [
+                    self.diff(
+                        &g.stdout,
+                        &b.stdout,
+                        &name(good.0, check, "out"),
+                        &name(bad.0, check, "out"),
+                    ),
+                    self.diff(
+                        &g.stderr,
+                        &b.stderr,
+                        &name(good.0, check, "err"),
+                        &name(bad.0, check, "err"),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::ib;
+
+    fn output(stdout: &str, stderr: &str) -> Option<CommandOutput> {
+        Some(CommandOutput {
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+        })
+    }
+
+    #[test]
+    fn test_offer() {
+        let mut outputs = BoundaryOutputs::default();
+        outputs.offer(&ib(3), Good, vec![output("3", "")]);
+        outputs.offer(&ib(5), Good, vec![output("5", "")]);
+        outputs.offer(&ib(4), Good, vec![output("4", "")]);
+        outputs.offer(&ib(9), Bad, vec![output("9", "")]);
+        outputs.offer(&ib(7), Bad, vec![output("7", "")]);
+
+        assert_eq!(outputs.get(&ib(5), Good), Some(&vec![output("5", "")]));
+        assert_eq!(outputs.get(&ib(4), Good), None);
+        assert_eq!(outputs.get(&ib(7), Bad), Some(&vec![output("7", "")]));
+        assert_eq!(outputs.get(&ib(7), Good), None);
+    }
+
+    #[test]
+    fn test_diff() {
+        let diff = OutputDiff::new(vec![Regex::new("^pid ").unwrap()]);
+
+        assert_eq!(
+            diff.diff("a\npid 1\nb\n", "a\npid 2\nc\n", "5.out", "6.out"),
+            "--- 5.out\n+++ 6.out\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n"
+        );
+        assert_eq!(diff.diff("pid 1\n", "pid 2\n", "5.out", "6.out"), "");
+    }
+
+    #[test]
+    fn test_compare() {
+        let diff = OutputDiff::default();
+        let good = vec![output("a\n", "x\n"), output("b\n", "")];
+        let bad = vec![output("a\n", "y\n"), None];
+
+        assert_eq!(
+            diff.compare((&ib(5), &good), (&ib(6), &bad), &["unit", "lint"]),
+            "--- 5.unit.err\n+++ 6.unit.err\n@@ -1 +1 @@\n-x\n+y\n"
+        );
+        assert_eq!(
+            diff.compare(
+                (&ib(5), &good[..1].to_vec()),
+                (&ib(6), &bad[..1].to_vec()),
+                &["command"]
+            ),
+            "--- 5.err\n+++ 6.err\n@@ -1 +1 @@\n-x\n+y\n"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::parasect::types::ParasectPayloadAnswer;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::task::cancellable_subprocess::SubprocessOutput;
+use clap::ValueEnum;
+use regex::Regex;
+
+/// Which of a command's output streams the output rules look at.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+    #[default]
+    Both,
+}
+
+/// What a matching output rule says about the point.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum OutputVerdict {
+    Answer(ParasectPayloadAnswer),
+    Skip,
+}
+
+/// The line that matched an output rule, along with what the rule says about the point.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct OutputMatch {
+    pub verdict: OutputVerdict,
+    pub pattern: String,
+    pub line: String,
+}
+
+/// Decides a point from a command's output instead of (or on top of) its exit code.
+///
+/// Skip rules win over bad rules, which win over good rules. If no rule matches, the exit code decides.
+#[derive(Clone, Debug, Default)]
+pub struct OutputRules {
+    skip: Vec<Regex>,
+    bad: Vec<Regex>,
+    good: Vec<Regex>,
+    stream: OutputStream,
+}
+
+impl OutputRules {
+    pub fn new(stream: OutputStream) -> Self {
+        Self {
+            stream,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_skip(mut self, pattern: Regex) -> Self {
+        self.skip.push(pattern);
+        self
+    }
+
+    pub fn with_bad(mut self, pattern: Regex) -> Self {
+        self.bad.push(pattern);
+        self
+    }
+
+    pub fn with_good(mut self, pattern: Regex) -> Self {
+        self.good.push(pattern);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skip.is_empty() && self.bad.is_empty() && self.good.is_empty()
+    }
+
+    /// `true` if stderr needs to be captured to evaluate the rules.
+    pub fn needs_stderr(&self) -> bool {
+        !self.is_empty() && self.stream != OutputStream::Stdout
+    }
+
+    fn lines<'a>(&self, output: &'a SubprocessOutput) -> impl Iterator<Item = &'a str> {
+        let stdout = match self.stream {
+            OutputStream::Stdout | OutputStream::Both => output.output.as_deref(),
+            OutputStream::Stderr => None,
+        };
+        let stderr = match self.stream {
+            OutputStream::Stderr | OutputStream::Both => output.stderr.as_deref(),
+            OutputStream::Stdout => None,
+        };
+
+        stdout.into_iter().chain(stderr).flat_map(|s| s.lines())
+    }
+
+    /// Returns the first match of the highest-priority rule that matches any line of the output, or None if no rule matches.
+    pub fn evaluate(&self, output: &SubprocessOutput) -> Option<OutputMatch> {
+        let groups = [
+            (&self.skip, OutputVerdict::Skip),
+            (&self.bad, OutputVerdict::Answer(Bad)),
+            (&self.good, OutputVerdict::Answer(Good)),
+        ];
+
+        groups.into_iter().find_map(|(patterns, verdict)| {
+            self.lines(output).find_map(|line| {
+                patterns
+                    .iter()
+                    .find(|p| p.is_match(line))
+                    .map(|p| OutputMatch {
+                        verdict: verdict.clone(),
+                        pattern: p.to_string(),
+                        line: line.to_string(),
+                    })
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn output(stdout: &str, stderr: &str) -> SubprocessOutput {
+        SubprocessOutput {
+            status: ExitStatus::from_raw(0),
+            output: Some(stdout.into()),
+            stderr: Some(stderr.into()),
+            usage: Default::default(),
+            timed_out: false,
+        }
+    }
+
+    fn re(s: &str) -> Regex {
+        Regex::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_evaluate_precedence() {
+        let rules = OutputRules::new(OutputStream::Both)
+            .with_good(re("^PASS"))
+            .with_bad(re("FAIL"))
+            .with_skip(re("SKIP"));
+
+        assert_eq!(
+            rules.evaluate(&output("PASS\ntest 3 FAIL\n", "")),
+            Some(OutputMatch {
+                verdict: OutputVerdict::Answer(Bad),
+                pattern: "FAIL".into(),
+                line: "test 3 FAIL".into()
+            })
+        );
+        assert_eq!(
+            rules
+                .evaluate(&output("PASS\n", "SKIP: no network\n"))
+                .map(|m| (m.verdict, m.line)),
+            Some((OutputVerdict::Skip, "SKIP: no network".into()))
+        );
+        assert_eq!(
+            rules.evaluate(&output("PASS\n", "")).map(|m| m.verdict),
+            Some(OutputVerdict::Answer(Good))
+        );
+        assert_eq!(rules.evaluate(&output("all done\n", "")), None);
+    }
+
+    #[test]
+    fn test_evaluate_stream() {
+        let rules = OutputRules::new(OutputStream::Stdout).with_bad(re("FAIL"));
+
+        assert!(rules.evaluate(&output("", "FAIL")).is_none());
+        assert!(rules.evaluate(&output("FAIL", "")).is_some());
+        assert!(!rules.needs_stderr());
+
+        let rules = OutputRules::new(OutputStream::Stderr).with_bad(re("FAIL"));
+
+        assert!(rules.evaluate(&output("", "FAIL")).is_some());
+        assert!(rules.evaluate(&output("FAIL", "")).is_none());
+        assert!(rules.needs_stderr());
+        assert!(!OutputRules::new(OutputStream::Both).needs_stderr());
+    }
+}
@@ -0,0 +1,260 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// A boolean expression over the outcomes of a probe's checks, e.g. `build && !flaky`.
+///
+/// Each name stands for "that check failed". Supports `!`, `&&`, `||` and parentheses, with the usual precedence.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Predicate {
+    /// True if the check with this index failed.
+    Failed(usize),
+    Not(Box<Predicate>),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum Token {
+    Name(String),
+    Not,
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+/// `true` if `s` can be used as a name in a predicate.
+pub fn is_valid_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars: Peekable<CharIndices> = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '!' => Token::Not,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' | '|' => {
+                if chars.next_if(|(_, next)| *next == c).is_none() {
+                    return Err(format!("Expected \"{}{}\" at position {}", c, c, i));
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut end = i + c.len_utf8();
+                while let Some((j, next)) =
+                    chars.next_if(|(_, n)| n.is_ascii_alphanumeric() || *n == '_' || *n == '-')
+                {
+                    end = j + next.len_utf8();
+                }
+                Token::Name(s[i..end].to_string())
+            }
+            c => return Err(format!("Unexpected \"{}\" at position {}", c, i)),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    names: &'a [&'a str],
+}
+
+impl<'a> Parser<'a> {
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Predicate, String> {
+        let mut ret = self.and()?;
+        while self.next_if(&Token::Or) {
+            ret = Predicate::Or(Box::new(ret), Box::new(self.and()?));
+        }
+        Ok(ret)
+    }
+
+    fn and(&mut self) -> Result<Predicate, String> {
+        let mut ret = self.unary()?;
+        while self.next_if(&Token::And) {
+            ret = Predicate::And(Box::new(ret), Box::new(self.unary()?));
+        }
+        Ok(ret)
+    }
+
+    fn unary(&mut self) -> Result<Predicate, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+
+        match token {
+            Some(Token::Not) => Ok(Predicate::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                if !self.next_if(&Token::Close) {
+                    return Err("Unclosed parenthesis".into());
+                }
+                Ok(inner)
+            }
+            Some(Token::Name(name)) => self
+                .names
+                .iter()
+                .position(|n| *n == name)
+                .map(Predicate::Failed)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown check \"{}\" (the checks are {})",
+                        name,
+                        self.names.join(", ")
+                    )
+                }),
+            Some(t) => Err(format!("Unexpected {:?}", t)),
+            None => Err("Unexpected end of expression".into()),
+        }
+    }
+}
+
+impl Predicate {
+    /// Parses a predicate, where the names are the checks in `names`, in order.
+    pub fn parse(s: &str, names: &[&str]) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            names,
+        };
+
+        let ret = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(ret),
+            Some(t) => Err(format!("Unexpected {:?}", t)),
+        }
+    }
+
+    /// True if any of the `count` checks failed.
+    pub fn any_failed(count: usize) -> Self {
+        (1..count).fold(Predicate::Failed(0), |acc, i| {
+            Predicate::Or(Box::new(acc), Box::new(Predicate::Failed(i)))
+        })
+    }
+
+    /// Evaluates the predicate given which checks failed so far, where None means the check hasn't finished yet.
+    ///
+    /// Returns None if the result still depends on unfinished checks, so a result can be known before every check finishes.
+    pub fn evaluate(&self, failed: &[Option<bool>]) -> Option<bool> {
+        match self {
+            Predicate::Failed(i) => failed[*i],
+            Predicate::Not(p) => p.evaluate(failed).map(|x| !x),
+            Predicate::And(a, b) => match (a.evaluate(failed), b.evaluate(failed)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Predicate::Or(a, b) => match (a.evaluate(failed), b.evaluate(failed)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Predicate::*;
+    use super::*;
+
+    const NAMES: [&str; 3] = ["a", "b", "test-c"];
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Predicate::parse("a && !b || test-c", &NAMES),
+            Ok(Or(
+                Box::new(And(Box::new(Failed(0)), Box::new(Not(Box::new(Failed(1)))))),
+                Box::new(Failed(2))
+            ))
+        );
+
+        assert_eq!(
+            Predicate::parse("a && (b || test-c)", &NAMES),
+            Ok(And(
+                Box::new(Failed(0)),
+                Box::new(Or(Box::new(Failed(1)), Box::new(Failed(2))))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Predicate::parse("a && d", &NAMES),
+            Err("Unknown check \"d\" (the checks are a, b, test-c)".into())
+        );
+        assert_eq!(
+            Predicate::parse("a & b", &NAMES),
+            Err("Expected \"&&\" at position 2".into())
+        );
+        assert_eq!(
+            Predicate::parse("(a || b", &NAMES),
+            Err("Unclosed parenthesis".into())
+        );
+        assert_eq!(
+            Predicate::parse("a b", &NAMES),
+            Err("Unexpected Name(\"b\")".into())
+        );
+        assert_eq!(
+            Predicate::parse("a &&", &NAMES),
+            Err("Unexpected end of expression".into())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_short_circuits() {
+        let p = Predicate::parse("a && !b", &NAMES).unwrap();
+
+        assert_eq!(p.evaluate(&[None, None, None]), None);
+        assert_eq!(p.evaluate(&[Some(false), None, None]), Some(false));
+        assert_eq!(p.evaluate(&[None, Some(true), None]), Some(false));
+        assert_eq!(p.evaluate(&[Some(true), None, None]), None);
+        assert_eq!(p.evaluate(&[Some(true), Some(false), None]), Some(true));
+    }
+
+    #[test]
+    fn test_any_failed() {
+        let p = Predicate::any_failed(3);
+
+        assert_eq!(p.evaluate(&[Some(false), None, None]), None);
+        assert_eq!(p.evaluate(&[Some(false), Some(true), None]), Some(true));
+        assert_eq!(
+            p.evaluate(&[Some(false), Some(false), Some(false)]),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("lint"));
+        assert!(is_valid_name("test_a-2"));
+        assert!(!is_valid_name("2fast"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("a b"));
+    }
+}
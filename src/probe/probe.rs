@@ -0,0 +1,1705 @@
+use crate::collections::collect_collection::CollectVec;
+use crate::command_gen::CommandGen;
+use crate::parasect::event::Event::ProbeNoted;
+use crate::parasect::event::{Event, ProbeNote};
+use crate::parasect::probe_context::{ProbeContext, SearchState};
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadResult::{Continue, Skip, Stop};
+use crate::parasect::types::{
+    Annotations, ParasectPayloadAnswer, ParasectPayloadOutput, ParasectPayloadResult,
+};
+use crate::probe::artifacts::Artifacts;
+use crate::probe::build_pool::BuildPool;
+use crate::probe::log_dir::{LogDir, ProbeLogs};
+use crate::probe::metric::Metric;
+use crate::probe::output_diff::{BoundaryOutputs, CommandOutput, OutputDiff, ProbeOutput};
+use crate::probe::output_rules::{OutputMatch, OutputRules, OutputVerdict};
+use crate::probe::predicate::Predicate;
+use crate::probe::probe_task::{ProbeSteps, ProbeTask};
+use crate::probe::resource_limits::ResourceLimits;
+use crate::probe::result_file::ResultFile;
+use crate::probe::scratch::ScratchDirs;
+use crate::probe::template_file::TemplateFile;
+use crate::range::numeric_range::NumericRange;
+use crate::task::cancellable_subprocess::{
+    Cancellation, OutputLog, SubprocessOptions, SubprocessOutput, DEFAULT_OUTPUT_LIMIT,
+};
+use crate::task::cancellable_task::CancellableTask;
+use crate::task::pty::PtySize;
+use crate::task::resource_usage::{format_duration, ResourceUsage};
+use crate::template::template_string::TemplateString;
+use clap::ValueEnum;
+use crossbeam_channel::Sender;
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What to do with a point whose setup or build command fails.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum FailureAction {
+    /// Skip the point and keep searching around it.
+    Skip,
+    /// Stop the whole search.
+    #[default]
+    Abort,
+}
+
+impl FailureAction {
+    fn result(self, reason: String) -> ParasectPayloadResult {
+        match self {
+            FailureAction::Skip => Skip(reason),
+            FailureAction::Abort => Stop(reason),
+        }
+    }
+}
+
+/// One of the commands that together decide whether a point is good or bad.
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub command_gen: CommandGen,
+}
+
+impl Check {
+    pub fn new(name: impl Into<String>, command_gen: CommandGen) -> Self {
+        Self {
+            name: name.into(),
+            command_gen,
+        }
+    }
+}
+
+/// One run of a probe, kept for machine-readable output.
+#[derive(Clone, Debug)]
+pub struct ProbeRecord {
+    pub point: IBig,
+    pub attempt: usize,
+    pub worker_id: usize,
+    pub started: Instant,
+    pub duration: Duration,
+    /// None if the probe was cancelled.
+    pub result: Option<ParasectPayloadResult>,
+    /// What the probe reported along with its result, e.g. its resource usage.
+    pub annotations: Annotations,
+    /// The command with the point substituted, unless it couldn't be prepared.
+    pub command: Option<Vec<String>>,
+    /// What each check that finished printed, named after the check, if output is kept.
+    pub output: Vec<(String, CommandOutput)>,
+}
+
+/// Everything needed to launch the user's command for a given `ProbeContext`.
+#[derive(Debug)]
+pub struct Probe {
+    checks: Vec<Check>,
+    bad_if: Predicate,
+    output_rules: OutputRules,
+    limits: ResourceLimits,
+    metric: Option<Metric>,
+    event_sender: Option<Sender<Event>>,
+    setup: Option<CommandGen>,
+    teardown: Option<CommandGen>,
+    on_setup_failure: FailureAction,
+    build: Option<(Arc<BuildPool>, FailureAction)>,
+    template_files: Vec<TemplateFile>,
+    cwd: Option<TemplateString>,
+    pty: Option<PtySize>,
+    cancellation: Cancellation,
+    log_dir: Option<LogDir>,
+    output_limit: usize,
+    keep_boundary_output: bool,
+    keep_output: bool,
+    artifacts: Option<Arc<Artifacts>>,
+    stderr_to_stderr: bool,
+    scratch: Arc<ScratchDirs>,
+    results: Arc<Mutex<BTreeMap<IBig, ParasectPayloadResult>>>,
+    annotations: Arc<Mutex<BTreeMap<IBig, Annotations>>>,
+    logs: Arc<Mutex<BTreeMap<IBig, ProbeLogs>>>,
+    boundary_outputs: Arc<Mutex<BoundaryOutputs>>,
+    history: Arc<Mutex<Vec<ProbeRecord>>>,
+}
+
+/// The commands of a probe with every placeholder substituted, ready to run.
+#[derive(Clone, Debug)]
+pub struct PreparedProbe {
+    /// One command per check, in the same order as the checks.
+    pub commands: Vec<Vec<String>>,
+    pub setup: Option<Vec<String>>,
+    pub teardown: Option<Vec<String>>,
+    pub options: SubprocessOptions,
+    /// Where each command's output goes, if it's logged.
+    pub logs: Option<ProbeLogs>,
+    /// What each command printed, if it's kept.
+    pub outputs: Option<Arc<Mutex<ProbeOutput>>>,
+    pub output_rules: OutputRules,
+    pub limits: ResourceLimits,
+    pub metric: Option<Metric>,
+    /// Where the command can write its verdict and annotations, given to it as `PARASECT_RESULT_FILE`.
+    pub result_file: PathBuf,
+    pub scratch_dir: PathBuf,
+}
+
+/// Adds the resource usage of the probe's commands to the output's annotations.
+fn with_usage(
+    mut output: ParasectPayloadOutput,
+    usage: Option<ResourceUsage>,
+) -> ParasectPayloadOutput {
+    if let Some(usage) = usage {
+        output.annotations.metrics.extend(usage.to_metrics());
+    }
+    output
+}
+
+impl PreparedProbe {
+    /// The options to run one of the commands with, writing its output to the log picked by `log`, if there are logs.
+    fn options_for(&self, log: impl FnOnce(&ProbeLogs) -> &OutputLog) -> SubprocessOptions {
+        self.options
+            .clone()
+            .with_log(self.logs.as_ref().map(|l| log(l).clone()))
+    }
+
+    /// The options to run the `i`th command with, which is killed once it goes over the wall time limit.
+    fn command_options(&self, i: usize) -> SubprocessOptions {
+        self.options_for(|l| &l.commands[i])
+            .with_deadline(self.limits.wall)
+    }
+
+    /// Keeps what the `i`th command printed, if output is kept.
+    fn keep_output(&self, i: usize, output: &SubprocessOutput) {
+        if let Some(outputs) = &self.outputs {
+            outputs.lock().unwrap()[i] = Some(CommandOutput::new(output));
+        }
+    }
+
+    fn run_setup(
+        &self,
+        steps: &ProbeSteps,
+        on_failure: FailureAction,
+    ) -> Option<Result<(), ParasectPayloadResult>> {
+        let setup = match &self.setup {
+            Some(s) => s,
+            None => return Some(Ok(())),
+        };
+
+        let reason = match steps.run(setup, &self.options_for(|l| &l.setup))? {
+            Ok(output) if output.status.success() => return Some(Ok(())),
+            Ok(output) => format!("The setup command failed ({})", output.status),
+            Err(e) => format!("Failed to execute the setup command {:?}: {}", setup, e),
+        };
+
+        Some(Err(on_failure.result(reason)))
+    }
+
+    /// What a finished command says about the point, along with why unless it was decided by the exit code.
+    ///
+    /// Skip rules win over resource limits, which win over the other output rules, which win over the exit code.
+    fn judge(&self, output: &SubprocessOutput) -> (OutputVerdict, Option<String>) {
+        let describe = |m: &OutputMatch| format!("output matched \"{}\": {}", m.pattern, m.line);
+
+        let exceeded = match (output.timed_out, self.limits.wall) {
+            (true, Some(limit)) => Some(format!(
+                "wall time went over the limit of {}, so the command was killed",
+                format_duration(limit)
+            )),
+            _ => self.limits.exceeded(&output.usage),
+        };
+
+        match (self.output_rules.evaluate(output), exceeded) {
+            (Some(m), _) if m.verdict == OutputVerdict::Skip => {
+                (m.verdict.clone(), Some(describe(&m)))
+            }
+            (_, Some(reason)) => (OutputVerdict::Answer(Bad), Some(reason)),
+            (Some(m), None) => (m.verdict.clone(), Some(describe(&m))),
+            (None, None) => (
+                OutputVerdict::Answer(if output.status.success() { Good } else { Bad }),
+                None,
+            ),
+        }
+    }
+
+    fn run_command(
+        &self,
+        steps: &ProbeSteps,
+        mut on_check: impl FnMut(usize, Option<ParasectPayloadAnswer>, String),
+    ) -> Option<ParasectPayloadOutput> {
+        let command = &self.commands[0];
+        let output = match steps.run(command, &self.command_options(0))? {
+            Ok(output) => output,
+            Err(e) => return Some(Stop(format!("Failed to execute {:?}: {}", command, e)).into()),
+        };
+
+        self.keep_output(0, &output);
+        let result = match self.judge(&output) {
+            (OutputVerdict::Answer(a), None) => Continue(a),
+            (OutputVerdict::Answer(a), Some(reason)) => {
+                on_check(0, Some(a), reason);
+                Continue(a)
+            }
+            (OutputVerdict::Skip, reason) => {
+                let reason = reason.unwrap_or_default();
+                on_check(0, None, reason.clone());
+                Skip(reason)
+            }
+        };
+        Some(with_usage(result.into(), Some(output.usage)))
+    }
+
+    /// Runs the command once per sample of `metric` and reads the metric from each run's output.
+    ///
+    /// Returns the result to give the point instead if a run can't be measured, e.g. because an output rule matched, the command failed or its metric couldn't be read.
+    fn sample_metric(
+        &self,
+        steps: &ProbeSteps,
+        metric: &Metric,
+        mut on_check: impl FnMut(usize, Option<ParasectPayloadAnswer>, String),
+    ) -> Option<Result<(Vec<f64>, ResourceUsage), ParasectPayloadResult>> {
+        let command = &self.commands[0];
+        let mut values = Vec::new();
+        let mut usage = ResourceUsage::default();
+
+        for _ in 0..metric.samples() {
+            let output = match steps.run(command, &self.command_options(0))? {
+                Ok(output) => output,
+                Err(e) => {
+                    return Some(Err(Stop(format!("Failed to execute {:?}: {}", command, e))))
+                }
+            };
+
+            usage = usage.max(output.usage);
+            self.keep_output(0, &output);
+            let result = match self.judge(&output) {
+                (OutputVerdict::Answer(Good), None) => {
+                    match metric.extract(output.output.as_deref().unwrap_or_default()) {
+                        Ok(v) => {
+                            values.push(v);
+                            continue;
+                        }
+                        Err(e) => Skip(e),
+                    }
+                }
+                (OutputVerdict::Answer(_), None) => Skip(format!(
+                    "The command failed ({}), so its metric can't be trusted",
+                    output.status
+                )),
+                (OutputVerdict::Answer(a), Some(reason)) => {
+                    on_check(0, Some(a), reason);
+                    Continue(a)
+                }
+                (OutputVerdict::Skip, reason) => {
+                    let reason = reason.unwrap_or_default();
+                    on_check(0, None, reason.clone());
+                    Skip(reason)
+                }
+            };
+            return Some(Err(result));
+        }
+
+        Some(Ok((values, usage)))
+    }
+
+    fn run_metric(
+        &self,
+        steps: &ProbeSteps,
+        metric: &Metric,
+        on_check: impl FnMut(usize, Option<ParasectPayloadAnswer>, String),
+    ) -> Option<ParasectPayloadOutput> {
+        Some(match self.sample_metric(steps, metric, on_check)? {
+            Ok((values, usage)) => with_usage(
+                ParasectPayloadOutput::new(
+                    Continue(metric.decide(&values)),
+                    metric.annotations(&values),
+                ),
+                Some(usage),
+            ),
+            Err(result) => result.into(),
+        })
+    }
+
+    /// Runs setup, the command once per sample, and teardown, returning the measured samples.
+    pub fn measure(&self, steps: &ProbeSteps, metric: &Metric) -> Option<Result<Vec<f64>, String>> {
+        let result = match self.run_setup(steps, FailureAction::Abort)? {
+            Ok(()) => self.sample_metric(steps, metric, |_, _, _| {}).map(|r| {
+                r.map(|(values, _)| values)
+                    .map_err(|result| result.to_string())
+            }),
+            Err(result) => Some(Err(result.to_string())),
+        };
+
+        match self.run_teardown(steps) {
+            Err(e) if !steps.cancelled() => Some(Err(e)),
+            _ => result,
+        }
+    }
+
+    /// Runs every check at once, and stops the ones still running as soon as `bad_if` is decided, or a check says to skip the point.
+    ///
+    /// `on_check` is told about each check that finished, along with its answer, or None if it was stopped early or said to skip.
+    fn run_checks(
+        &self,
+        steps: &ProbeSteps,
+        bad_if: &Predicate,
+        mut on_check: impl FnMut(usize, Option<ParasectPayloadAnswer>, String),
+    ) -> Option<ParasectPayloadOutput> {
+        let mut failed = vec![None; self.commands.len()];
+        let mut reported = vec![false; self.commands.len()];
+        let mut result = None;
+        let mut usage: Option<ResourceUsage> = None;
+
+        let commands = self
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.clone(), self.command_options(i)))
+            .collect_vec();
+        steps.run_concurrently(&commands, |i, output| {
+            let output = match output {
+                Ok(output) => output,
+                Err(e) => {
+                    result = Some(Stop(format!(
+                        "Failed to execute {:?}: {}",
+                        self.commands[i], e
+                    )));
+                    return false;
+                }
+            };
+
+            self.keep_output(i, &output);
+            let (verdict, reason) = self.judge(&output);
+            let detail = reason.unwrap_or_else(|| output.status.to_string());
+            reported[i] = true;
+            usage = Some(usage.map_or(output.usage, |u| u.max(output.usage)));
+
+            match verdict {
+                OutputVerdict::Answer(a) => {
+                    on_check(i, Some(a), detail);
+                    failed[i] = Some(a == Bad);
+                    result = bad_if
+                        .evaluate(&failed)
+                        .map(|bad| Continue(if bad { Bad } else { Good }));
+                }
+                OutputVerdict::Skip => {
+                    on_check(i, None, detail.clone());
+                    result = Some(Skip(detail));
+                }
+            }
+            result.is_none()
+        })?;
+
+        for (i, r) in reported.iter().enumerate() {
+            if !r {
+                on_check(i, None, "stopped early".into());
+            }
+        }
+
+        result.map(|r| with_usage(r.into(), usage))
+    }
+
+    /// Applies whatever the command wrote to the result file, if anything.
+    fn apply_result_file(&self, output: ParasectPayloadOutput) -> ParasectPayloadOutput {
+        match ResultFile::read(&self.result_file) {
+            Ok(Some(file)) => file.apply(output),
+            Ok(None) => output,
+            Err(e) => Stop(format!(
+                "Invalid result file \"{}\": {}",
+                self.result_file.display(),
+                e
+            ))
+            .into(),
+        }
+    }
+
+    fn run_teardown(&self, steps: &ProbeSteps) -> Result<(), String> {
+        let teardown = match &self.teardown {
+            Some(t) => t,
+            None => return Ok(()),
+        };
+
+        match steps.run_uncancellable(teardown, &self.options_for(|l| &l.teardown)) {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(format!("The teardown command failed ({})", output.status)),
+            Err(e) => Err(format!(
+                "Failed to execute the teardown command {:?}: {}",
+                teardown, e
+            )),
+        }
+    }
+
+    /// Runs setup, the command (or checks), and teardown, in that order.
+    ///
+    /// The command only runs if setup succeeds, and what it writes to the result file overrides its result. Teardown always runs, even if setup fails or the probe is cancelled. If teardown fails, the probe is stopped, because later probes can't be trusted.
+    pub fn execute(
+        &self,
+        steps: &ProbeSteps,
+        on_setup_failure: FailureAction,
+        bad_if: &Predicate,
+        on_check: impl FnMut(usize, Option<ParasectPayloadAnswer>, String),
+    ) -> Option<ParasectPayloadOutput> {
+        let result = match self.run_setup(steps, on_setup_failure) {
+            Some(Ok(())) if self.metric.is_some() => self
+                .run_metric(steps, self.metric.as_ref().unwrap(), on_check)
+                .map(|r| self.apply_result_file(r)),
+            Some(Ok(())) if *bad_if == Predicate::Failed(0) => self
+                .run_command(steps, on_check)
+                .map(|r| self.apply_result_file(r)),
+            Some(Ok(())) => self
+                .run_checks(steps, bad_if, on_check)
+                .map(|r| self.apply_result_file(r)),
+            Some(Err(r)) => Some(r.into()),
+            None => None,
+        };
+
+        match self.run_teardown(steps) {
+            Err(e) if !steps.cancelled() => Some(Stop(e).into()),
+            _ => result,
+        }
+    }
+}
+
+impl Probe {
+    /// Probes each point by running all of `checks` at once.
+    ///
+    /// The point is bad if `bad_if` is true, where each name in it means that check failed. By default, the point is bad if any check fails. As soon as the answer is known, the checks still running are stopped.
+    pub fn with_checks(
+        checks: Vec<Check>,
+        bad_if: Option<Predicate>,
+        scratch: Arc<ScratchDirs>,
+    ) -> Self {
+        Self {
+            bad_if: bad_if.unwrap_or_else(|| Predicate::any_failed(checks.len())),
+            checks,
+            output_rules: OutputRules::default(),
+            limits: ResourceLimits::default(),
+            metric: None,
+            event_sender: None,
+            setup: None,
+            teardown: None,
+            on_setup_failure: FailureAction::default(),
+            build: None,
+            template_files: Vec::new(),
+            cwd: None,
+            pty: None,
+            cancellation: Cancellation::default(),
+            log_dir: None,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            keep_boundary_output: false,
+            keep_output: false,
+            artifacts: None,
+            stderr_to_stderr: false,
+            scratch,
+            results: Arc::new(Mutex::new(BTreeMap::new())),
+            annotations: Arc::new(Mutex::new(BTreeMap::new())),
+            logs: Arc::new(Mutex::new(BTreeMap::new())),
+            boundary_outputs: Arc::new(Mutex::new(BoundaryOutputs::default())),
+            history: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Runs the given command before each probe, with the same environment and working directory as the probe.
+    ///
+    /// If it fails, the point is skipped or the search is aborted, depending on `on_failure`.
+    pub fn with_setup(mut self, setup: Option<CommandGen>, on_failure: FailureAction) -> Self {
+        self.setup = setup;
+        self.on_setup_failure = on_failure;
+        self
+    }
+
+    /// Runs the given command after each probe, even if the probe was cancelled or its setup failed.
+    pub fn with_teardown(mut self, teardown: Option<CommandGen>) -> Self {
+        self.teardown = teardown;
+        self
+    }
+
+    /// Decides points by matching the output of the command (or of each check) against `rules`, before looking at the exit code.
+    pub fn with_output_rules(mut self, rules: OutputRules) -> Self {
+        self.output_rules = rules;
+        self
+    }
+
+    /// Marks a point bad if its command (or any of its checks) goes over `limits`, whatever its exit code.
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Decides points by measuring `metric` from the command's output instead of by its exit code.
+    ///
+    /// If the metric's threshold is relative, `measure_baseline` must be called before any probe.
+    pub fn with_metric(mut self, metric: Option<Metric>) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Measures the metric at the point of `ctx` (normally the low end of the range), which relative thresholds are relative to.
+    ///
+    /// Does nothing if the metric doesn't need a baseline.
+    pub fn measure_baseline(&mut self, ctx: ProbeContext) -> Result<(), String> {
+        let metric = match &self.metric {
+            Some(m) if m.needs_baseline() => m.clone(),
+            _ => return Ok(()),
+        };
+
+        let prepared = self.prepare(&ctx)?;
+        let task_metric = metric.clone();
+        let values = ProbeTask::spawn(move |steps| prepared.measure(steps, &task_metric))
+            .wait()
+            .unwrap_or_else(|| Err("cancelled".into()));
+        self.scratch.finish(&ctx, values.is_err());
+        let values = values?;
+
+        if let Some(sender) = &self.event_sender {
+            let _ = sender.send(ProbeNoted(ProbeNote {
+                worker_id: ctx.worker_id,
+                point: ctx.point.clone(),
+                source: "baseline".into(),
+                answer: None,
+                detail: metric.annotations(&values).to_string(),
+            }));
+        }
+
+        self.metric = Some(metric.with_baseline(values));
+        Ok(())
+    }
+
+    /// Reports the answer of each check to `sender` as it finishes, and any output rule that matched.
+    pub fn with_event_sender(mut self, sender: Sender<Event>) -> Self {
+        self.event_sender = Some(sender);
+        self
+    }
+
+    /// Runs every command of the probe in a pseudo-terminal of the given size instead of with pipes.
+    ///
+    /// Their stdout and stderr are then captured together, with escape sequences stripped.
+    pub fn with_pty(mut self, pty: Option<PtySize>) -> Self {
+        self.pty = pty;
+        self
+    }
+
+    /// How the probe's commands are stopped when it's cancelled, e.g. because its point is no longer needed.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Streams the output of every command of the probe to files in `log_dir`, instead of passing stderr through to parasect's stdout.
+    pub fn with_log_dir(mut self, log_dir: Option<LogDir>) -> Self {
+        self.log_dir = log_dir;
+        self
+    }
+
+    /// Limits how many bytes of each command's stdout and stderr are kept for output rules and metrics, and written to the logs.
+    pub fn with_output_limit(mut self, limit: usize) -> Self {
+        self.output_limit = limit;
+        self
+    }
+
+    /// Keeps the output of the latest good and earliest bad points probed, so `diff_boundary` can compare them. Stderr is then captured instead of passed through.
+    pub fn with_boundary_output(mut self, keep: bool) -> Self {
+        self.keep_boundary_output = keep;
+        self
+    }
+
+    /// Keeps the output of every probe in its `ProbeRecord`, e.g. for JUnit reports. Stderr is then captured instead of passed through.
+    pub fn with_probe_output(mut self, keep: bool) -> Self {
+        self.keep_output = keep;
+        self
+    }
+
+    /// Copies files out of the working directory of each probe that `artifacts` wants, once the probe is done.
+    ///
+    /// If copying fails, it's noted in the log, but the probe's result stands.
+    pub fn with_artifacts(mut self, artifacts: Option<Arc<Artifacts>>) -> Self {
+        self.artifacts = artifacts;
+        self
+    }
+
+    /// Passes the stderr of the probe's commands through to parasect's stderr instead of its stdout, when it isn't captured or logged.
+    pub fn with_stderr_to_stderr(mut self, stderr_to_stderr: bool) -> Self {
+        self.stderr_to_stderr = stderr_to_stderr;
+        self
+    }
+
+    /// Waits for the point's build from `pool` before each probe, and gives its artifact directory to the probe as `PARASECT_ARTIFACT_DIR`.
+    ///
+    /// If the build fails, the point is skipped or the search is aborted, depending on `on_failure`. Neither setup nor teardown run in that case.
+    pub fn with_build(mut self, pool: Option<Arc<BuildPool>>, on_failure: FailureAction) -> Self {
+        self.build = pool.map(|p| (p, on_failure));
+        self
+    }
+
+    /// Renders the given files into the probe's scratch directory before each probe.
+    ///
+    /// The rendered paths are given to the command as `PARASECT_TEMPLATE_FILE_0`, `PARASECT_TEMPLATE_FILE_1`, etc. in the order given.
+    pub fn with_template_files(mut self, template_files: Vec<TemplateFile>) -> Self {
+        self.template_files = template_files;
+        self
+    }
+
+    /// Runs the command in the directory given by rendering `cwd`, creating it if needed.
+    ///
+    /// By default, the command runs in parasect's working directory.
+    pub fn with_cwd(mut self, cwd: Option<TemplateString>) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    /// The result of every probe that finished so far.
+    pub fn results(&self) -> BTreeMap<IBig, ParasectPayloadResult> {
+        self.results.lock().unwrap().clone()
+    }
+
+    /// The annotations of the latest probe of every point that finished so far with any.
+    pub fn annotations(&self) -> BTreeMap<IBig, Annotations> {
+        self.annotations.lock().unwrap().clone()
+    }
+
+    /// Every probe run so far, including cancelled ones and retries, in the order they finished.
+    pub fn history(&self) -> Vec<ProbeRecord> {
+        self.history.lock().unwrap().clone()
+    }
+
+    /// The log files of the latest probe of every point that finished so far, if there's a log directory.
+    pub fn logs(&self) -> BTreeMap<IBig, ProbeLogs> {
+        self.logs.lock().unwrap().clone()
+    }
+
+    /// The output of `point`, which was found to be `answer`. If it isn't kept, the point is probed again in `range`.
+    fn boundary_output(
+        &self,
+        point: &IBig,
+        answer: ParasectPayloadAnswer,
+        range: &NumericRange,
+    ) -> Result<ProbeOutput, String> {
+        if let Some(output) = self.boundary_outputs.lock().unwrap().get(point, answer) {
+            return Ok(output.clone());
+        }
+
+        let ctx = SearchState::new(range.clone()).start_probe(point.clone(), 0);
+        match self.spawn(ctx, false).wait().map(|o| o.result) {
+            Some(Continue(a)) if a == answer => self
+                .boundary_outputs
+                .lock()
+                .unwrap()
+                .get(point, answer)
+                .cloned()
+                .ok_or_else(|| format!("The output of {} wasn't kept", point)),
+            Some(r) => Err(format!(
+                "Probing {} again gave a different result ({}), so its output can't be compared",
+                point, r
+            )),
+            None => Err(format!("Probing {} again was cancelled", point)),
+        }
+    }
+
+    /// A unified diff of the output of the points `good` and `bad`, probing them again if their output wasn't kept (e.g. because `good` was the low end of the range and was never probed).
+    ///
+    /// Requires `with_boundary_output`. Empty if the outputs are the same, apart from the lines `diff` ignores.
+    pub fn diff_boundary(
+        &self,
+        diff: &OutputDiff,
+        good: &IBig,
+        bad: &IBig,
+        range: &NumericRange,
+    ) -> Result<String, String> {
+        let good_output = self.boundary_output(good, Good, range)?;
+        let bad_output = self.boundary_output(bad, Bad, range)?;
+        let names = self.checks.iter().map(|c| c.name.as_str()).collect_vec();
+
+        Ok(diff.compare((good, &good_output), (bad, &bad_output), &names))
+    }
+
+    /// Prepares the probe's scratch directory and working directory, and substitutes the context into the commands.
+    ///
+    /// On failure, returns a message describing what went wrong.
+    pub fn prepare(&self, ctx: &ProbeContext) -> Result<PreparedProbe, String> {
+        let commands = self
+            .checks
+            .iter()
+            .map(|c| c.command_gen.command_for_context(ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let setup = self
+            .setup
+            .as_ref()
+            .map(|s| s.command_for_context(ctx))
+            .transpose()?;
+        let teardown = self
+            .teardown
+            .as_ref()
+            .map(|t| t.command_for_context(ctx))
+            .transpose()?;
+
+        let scratch_dir = self.scratch.dir_for(ctx).map_err(|e| {
+            format!(
+                "Failed to create the scratch directory for {}: {}",
+                ctx.point, e
+            )
+        })?;
+
+        // scratch directories can be reused, so a file left over from an earlier probe must not count
+        let result_file = scratch_dir.join("parasect-result.json");
+        if let Err(e) = fs::remove_file(&result_file) {
+            if e.kind() != ErrorKind::NotFound {
+                return Err(format!(
+                    "Failed to remove the old result file \"{}\": {}",
+                    result_file.display(),
+                    e
+                ));
+            }
+        }
+
+        let logs = match &self.log_dir {
+            Some(dir) => {
+                let names = self.checks.iter().map(|c| c.name.as_str()).collect_vec();
+                let logs = dir.logs_for(ctx, &names);
+                logs.clear()?;
+                Some(logs)
+            }
+            None => None,
+        };
+
+        let variables = ctx.template_variables();
+        let mut options = SubprocessOptions::new()
+            .with_envs(ctx.variables().into_iter().map(|(k, v)| (k, v.to_string())))
+            .with_env("PARASECT_SCRATCH_DIR", scratch_dir.display().to_string())
+            .with_env("PARASECT_RESULT_FILE", result_file.display().to_string())
+            .with_env("TMPDIR", scratch_dir.display().to_string());
+
+        for (i, file) in self.template_files.iter().enumerate() {
+            let path = file.render_into(&scratch_dir, &variables)?;
+            options = options.with_env(
+                format!("PARASECT_TEMPLATE_FILE_{}", i),
+                path.display().to_string(),
+            );
+        }
+
+        if let Some(cwd) = &self.cwd {
+            let cwd = cwd
+                .render(&variables)
+                .map_err(|e| format!("Failed to render --cwd: {}", e))?;
+            fs::create_dir_all(&cwd).map_err(|e| {
+                format!("Failed to create the working directory \"{}\": {}", cwd, e)
+            })?;
+            options = options.with_cwd(cwd);
+        }
+
+        let keep_output = self.keep_boundary_output || self.keep_output;
+        if self.output_rules.needs_stderr() || keep_output {
+            options = options.with_capture_stderr(true);
+        }
+        options = options
+            .with_pty(self.pty)
+            .with_cancellation(self.cancellation)
+            .with_output_limit(self.output_limit)
+            .with_stderr_to_stderr(self.stderr_to_stderr);
+
+        Ok(PreparedProbe {
+            output_rules: self.output_rules.clone(),
+            limits: self.limits.clone(),
+            metric: self.metric.clone(),
+            result_file,
+            scratch_dir,
+            commands,
+            setup,
+            teardown,
+            options,
+            logs,
+            outputs: keep_output.then(|| Arc::new(Mutex::new(vec![None; self.checks.len()]))),
+        })
+    }
+
+    /// Probes the point in the given context, returning Good if the command exits with 0 (or the checks say so) and Bad otherwise.
+    ///
+    /// The scratch directory is cleaned up (or kept, if it failed and that's enabled) once the probe finishes.
+    pub fn run(&self, ctx: ProbeContext) -> impl CancellableTask<ParasectPayloadOutput> {
+        self.spawn(ctx, true)
+    }
+
+    /// Runs a probe like `run`, and unless `record` is set, leaves it out of the results, annotations, history, logs, events and artifacts, keeping only its output for `boundary_output`.
+    fn spawn(
+        &self,
+        ctx: ProbeContext,
+        record: bool,
+    ) -> impl CancellableTask<ParasectPayloadOutput> {
+        let mut prepared = self.prepare(&ctx);
+        if let (Ok(p), false) = (&mut prepared, record) {
+            p.logs = None;
+        }
+        let on_setup_failure = self.on_setup_failure;
+        let build = self.build.clone();
+        let scratch = self.scratch.clone();
+        let bad_if = self.bad_if.clone();
+        let names = self.checks.iter().map(|c| c.name.clone()).collect_vec();
+        let check_names = names.clone();
+        let event_sender = self.event_sender.clone().filter(|_| record);
+        let collect_sender = event_sender.clone();
+        let artifacts = self.artifacts.clone().filter(|_| record);
+        // parasect's own directory is shared by every probe, so without --cwd the files come from the scratch directory
+        let cwd = prepared.as_ref().ok().map(|p| {
+            p.options
+                .cwd()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| p.scratch_dir.clone())
+        });
+        let results = self.results.clone();
+        let all_annotations = self.annotations.clone();
+        let logs = prepared.as_ref().ok().and_then(|p| p.logs.clone());
+        let all_logs = self.logs.clone();
+        let command = prepared.as_ref().ok().map(|p| p.commands[0].clone());
+        let outputs = prepared.as_ref().ok().and_then(|p| p.outputs.clone());
+        let boundary_outputs = self.boundary_outputs.clone();
+        let history = self.history.clone();
+        let keep_output = self.keep_output;
+
+        let ctx_clone = ctx.clone();
+        let on_check = move |i: usize, answer, detail| {
+            if let Some(sender) = &event_sender {
+                let _ = sender.send(ProbeNoted(ProbeNote {
+                    worker_id: ctx_clone.worker_id,
+                    point: ctx_clone.point.clone(),
+                    source: match names[i].as_str() {
+                        "command" => "command".into(),
+                        name => format!("check {}", name),
+                    },
+                    answer,
+                    detail,
+                }));
+            }
+        };
+
+        ProbeTask::spawn(move |steps| {
+            let started = Instant::now();
+            let result = match (prepared, &build) {
+                (Ok(p), None) => p.execute(steps, on_setup_failure, &bad_if, on_check),
+                (Ok(mut p), Some((pool, on_build_failure))) => {
+                    match pool.wait_for_build(&ctx.point, steps) {
+                        Some(Ok(dir)) => {
+                            p.options = p
+                                .options
+                                .with_env("PARASECT_ARTIFACT_DIR", dir.display().to_string());
+                            p.execute(steps, on_setup_failure, &bad_if, on_check)
+                        }
+                        Some(Err(reason)) => Some(on_build_failure.result(reason).into()),
+                        None => None,
+                    }
+                }
+                (Err(e), _) => Some(Stop(e).into()),
+            };
+
+            let failed = result.as_ref().is_some_and(|o| o.result != Continue(Good));
+            let collect = match (&result, &artifacts, &cwd) {
+                (Some(_), Some(artifacts), Some(cwd)) if artifacts.wants(failed) => {
+                    artifacts.collect(&ctx, cwd)
+                }
+                _ => Ok(0),
+            };
+            if let Err(e) = collect {
+                if let Some(sender) = &collect_sender {
+                    let _ = sender.send(ProbeNoted(ProbeNote {
+                        worker_id: ctx.worker_id,
+                        point: ctx.point.clone(),
+                        source: "collect".into(),
+                        answer: None,
+                        detail: e,
+                    }));
+                }
+            }
+            scratch.finish(&ctx, failed);
+            if let (Some(o), true) = (&result, record) {
+                results
+                    .lock()
+                    .unwrap()
+                    .insert(ctx.point.clone(), o.result.clone());
+                let mut all_annotations = all_annotations.lock().unwrap();
+                if o.annotations.is_empty() {
+                    all_annotations.remove(&ctx.point);
+                } else {
+                    all_annotations.insert(ctx.point.clone(), o.annotations.clone());
+                }
+                if let Some(logs) = logs {
+                    all_logs.lock().unwrap().insert(ctx.point.clone(), logs);
+                }
+            }
+            if let (Some(Continue(answer)), Some(outputs)) =
+                (result.as_ref().map(|o| &o.result), &outputs)
+            {
+                let output = outputs.lock().unwrap().clone();
+                boundary_outputs
+                    .lock()
+                    .unwrap()
+                    .offer(&ctx.point, *answer, output);
+            }
+            if !record {
+                return result;
+            }
+            history.lock().unwrap().push(ProbeRecord {
+                point: ctx.point.clone(),
+                attempt: ctx.attempt,
+                worker_id: ctx.worker_id,
+                started,
+                duration: started.elapsed(),
+                result: result.as_ref().map(|o| o.result.clone()),
+                annotations: result
+                    .as_ref()
+                    .map(|o| o.annotations.clone())
+                    .unwrap_or_default(),
+                command,
+                output: match (&outputs, keep_output) {
+                    (Some(outputs), true) => check_names
+                        .into_iter()
+                        .zip(outputs.lock().unwrap().iter().cloned())
+                        .filter_map(|(name, output)| Some((name, output?)))
+                        .collect(),
+                    _ => Vec::new(),
+                },
+            });
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::parasect::probe_context::SearchState;
+    use crate::probe::artifacts::CollectFor;
+    use crate::probe::metric::Threshold;
+    use crate::probe::output_rules::OutputStream;
+    use crate::probe::scratch::ScratchMode;
+    use crate::test_util::test_util::test_util::{ib, r, TestDir};
+    use crossbeam_channel::unbounded;
+    use regex::Regex;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn unannotated(output: ParasectPayloadOutput) -> ParasectPayloadResult {
+        output.result
+    }
+
+    /// The result and its annotations as text, without the resource usage, which changes from run to run.
+    fn describe(output: Option<ParasectPayloadOutput>) -> Option<String> {
+        output.map(|mut o| {
+            for (name, _) in ResourceUsage::default().to_metrics() {
+                o.annotations.metrics.remove(&name);
+            }
+            if o.annotations.is_empty() {
+                o.result.to_string()
+            } else {
+                format!("{} [{}]", o.result, o.annotations)
+            }
+        })
+    }
+
+    fn command_gen(script: &str) -> CommandGen {
+        CommandGen::new(["sh", "-c", script, "$X"].map(String::from), "$X".into()).unwrap()
+    }
+
+    /// Probes each point with a single command, which is good if it exits with 0.
+    fn command_probe(command_gen: CommandGen, scratch: Arc<ScratchDirs>) -> Probe {
+        Probe::with_checks(vec![Check::new("command", command_gen)], None, scratch)
+    }
+
+    #[test]
+    fn test_run_with_template_file() {
+        let dir = TestDir::new("probe");
+
+        let source = dir.join("config.tmpl");
+        fs::write(&source, "value=${X+1}").unwrap();
+
+        let probe = command_probe(
+            command_gen(
+                "test \"$(cat \"$PARASECT_TEMPLATE_FILE_0\") $0 $PARASECT_WORKER_ID\" = 'value=5 4 2'",
+            ),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_template_files(vec![TemplateFile::load(
+            &format!("{}:config", source.display()),
+            "$X",
+            &ProbeContext::VARIABLE_NAMES,
+        )
+        .unwrap()]);
+
+        let ctx = SearchState::new(r(0, 10)).start_probe(ib(4), 2);
+        assert_eq!(probe.run(ctx).wait().map(unannotated), Some(Continue(Good)));
+    }
+
+    fn hook(script: &str) -> Option<CommandGen> {
+        Some(
+            CommandGen::new_without_value([script.to_string()], "$X".into())
+                .unwrap()
+                .with_shell(true),
+        )
+    }
+
+    #[test]
+    fn test_setup_and_teardown() {
+        let dir = TestDir::new("probe-hooks");
+        let log = dir.join("log");
+
+        let probe = command_probe(
+            command_gen(&format!("echo run $0 >> '{}'", log.display())),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_setup(
+            hook(&format!("echo setup $X >> '{}'", log.display())),
+            FailureAction::Abort,
+        )
+        .with_teardown(hook(&format!("echo teardown $X >> '{}'", log.display())));
+
+        let ctx = SearchState::new(r(0, 10)).start_probe(ib(4), 0);
+        assert_eq!(probe.run(ctx).wait().map(unannotated), Some(Continue(Good)));
+        assert_eq!(
+            fs::read_to_string(&log).unwrap(),
+            "setup 4\nrun 4\nteardown 4\n"
+        );
+    }
+
+    #[test]
+    fn test_setup_failure() {
+        let dir = TestDir::new("probe-setup-failure");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let state = SearchState::new(r(0, 10));
+
+        let probe = command_probe(command_gen("true"), scratch.clone())
+            .with_setup(hook("exit 3"), FailureAction::Skip);
+
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(4), 0))
+                .wait()
+                .map(unannotated),
+            Some(Skip(_))
+        ));
+
+        let probe = command_probe(command_gen("true"), scratch.clone())
+            .with_setup(hook("exit 3"), FailureAction::Abort);
+
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(4), 0))
+                .wait()
+                .map(unannotated),
+            Some(Stop(_))
+        ));
+
+        let probe =
+            command_probe(command_gen("true"), scratch.clone()).with_teardown(hook("exit 1"));
+
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(4), 0))
+                .wait()
+                .map(unannotated),
+            Some(Stop(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_with_build() {
+        let dir = TestDir::new("probe-build");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let build = |on_failure| {
+            Some(Arc::new(BuildPool::new(
+                hook("test $X -ne 6 && echo $X > \"$PARASECT_ARTIFACT_DIR/bin\"").unwrap(),
+                scratch.clone(),
+                1,
+            )))
+            .map(|pool| (pool, on_failure))
+        };
+
+        let (pool, on_failure) = build(FailureAction::Skip).unwrap();
+        let probe = command_probe(
+            command_gen("test \"$(cat \"$PARASECT_ARTIFACT_DIR/bin\")\" -lt 5"),
+            scratch.clone(),
+        )
+        .with_build(Some(pool), on_failure);
+        let state = SearchState::new(r(0, 10));
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(3), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(6), 0))
+                .wait()
+                .map(unannotated),
+            Some(Skip(_))
+        ));
+
+        let (pool, on_failure) = build(FailureAction::Abort).unwrap();
+        let probe =
+            command_probe(command_gen("true"), scratch.clone()).with_build(Some(pool), on_failure);
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(6), 0))
+                .wait()
+                .map(unannotated),
+            Some(Stop(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_checks() {
+        let dir = TestDir::new("probe-checks");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let checks = || {
+            vec![
+                Check::new("a", hook("test $X -ge 5").unwrap()),
+                Check::new("b", hook("test $X -ge 3").unwrap()),
+                Check::new("slow", hook("sleep 5").unwrap()),
+            ]
+        };
+        let names = ["a", "b", "slow"];
+        let state = SearchState::new(r(0, 10));
+        let start = Instant::now();
+
+        // bad if a fails and b passes, i.e. only when 3 <= x < 5
+        let (send, recv) = unbounded();
+        let probe = Probe::with_checks(
+            checks(),
+            Some(Predicate::parse("a && !b", &names).unwrap()),
+            scratch.clone(),
+        )
+        .with_event_sender(send);
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(4), 1))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 1))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let notes = recv
+            .try_iter()
+            .map(|e| match e {
+                ProbeNoted(n) => (n.point.to_string(), n.source, n.answer),
+                e => panic!("unexpected event {:?}", e),
+            })
+            .collect_vec();
+        assert!(notes.contains(&("4".into(), "check a".into(), Some(Bad))));
+        assert!(notes.contains(&("4".into(), "check b".into(), Some(Good))));
+        assert!(notes.contains(&("4".into(), "check slow".into(), None)));
+        assert!(notes.contains(&("7".into(), "check a".into(), Some(Good))));
+
+        // by default, bad if any check fails
+        let probe = Probe::with_checks(
+            checks().into_iter().take(2).collect_vec(),
+            None,
+            scratch.clone(),
+        );
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(4), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+    }
+
+    #[test]
+    fn test_run_with_output_rules() {
+        let dir = TestDir::new("probe-output");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let (send, recv) = unbounded();
+
+        let probe = command_probe(
+            command_gen(
+                "if [ $0 -eq 6 ]; then echo 'SKIP: flaky' >&2; elif [ $0 -ge 5 ]; then echo 'test 3 FAIL'; fi",
+            ),
+            scratch.clone(),
+        )
+        .with_output_rules(
+            OutputRules::new(OutputStream::Both)
+                .with_bad(Regex::new("FAIL").unwrap())
+                .with_skip(Regex::new("^SKIP").unwrap()),
+        )
+        .with_event_sender(send);
+        let state = SearchState::new(r(0, 10));
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(3), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(6), 0))
+                .wait()
+                .map(unannotated),
+            Some(Skip("output matched \"^SKIP\": SKIP: flaky".into()))
+        );
+
+        let notes = recv
+            .try_iter()
+            .map(|e| match e {
+                ProbeNoted(n) => (n.point.to_string(), n.source, n.answer, n.detail),
+                e => panic!("unexpected event {:?}", e),
+            })
+            .collect_vec();
+        assert_eq!(
+            notes,
+            vec![
+                (
+                    "7".into(),
+                    "command".into(),
+                    Some(Bad),
+                    "output matched \"FAIL\": test 3 FAIL".into()
+                ),
+                (
+                    "6".into(),
+                    "command".into(),
+                    None,
+                    "output matched \"^SKIP\": SKIP: flaky".into()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_with_result_file() {
+        let dir = TestDir::new("probe-result-file");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+
+        let probe = command_probe(
+            command_gen(
+                r#"case $0 in
+                    3) echo '{"verdict":"skip","note":"flaky infra","metrics":{"ms":123}}' > "$PARASECT_RESULT_FILE";;
+                    4) echo '{"metrics":{"ms":5}}' > "$PARASECT_RESULT_FILE"; exit 1;;
+                    5) echo 'nope' > "$PARASECT_RESULT_FILE";;
+                esac"#,
+            ),
+            scratch.clone(),
+        );
+        let state = SearchState::new(r(0, 10));
+
+        let skipped = probe.run(state.start_probe(ib(3), 0)).wait().unwrap();
+        assert_eq!(skipped.result, Skip("flaky infra".into()));
+        assert_eq!(
+            describe(Some(skipped)),
+            Some("Skipped (flaky infra) [flaky infra; ms=123]".into())
+        );
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(4), 0))
+                .wait()
+                .and_then(|r| describe(Some(r))),
+            Some("Bad [ms=5]".into())
+        );
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(5), 0))
+                .wait()
+                .map(unannotated),
+            Some(Stop(_))
+        ));
+        // the file from the earlier probe in the same scratch directory is ignored
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(6), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+    }
+
+    #[test]
+    fn test_run_with_metric() {
+        let dir = TestDir::new("probe-metric");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+
+        let mut probe = command_probe(
+            command_gen(
+                "if [ $0 -eq 6 ]; then exit 1; fi; if [ $0 -eq 8 ]; then echo crashed; exit 0; fi; echo \"took $((100 + $0 * 3))ms\"",
+            ),
+            scratch.clone(),
+        )
+        .with_metric(Some(
+            Metric::new(Threshold::parse(">+20%").unwrap())
+                .with_pattern(Some(Regex::new("took ([0-9]+)ms").unwrap()))
+                .with_samples(2),
+        ));
+        let state = SearchState::new(r(0, 10));
+
+        probe.measure_baseline(state.start_probe(ib(0), 0)).unwrap();
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(5), 0))
+                .wait()
+                .and_then(|r| describe(Some(r))),
+            Some("Good [change=+15.0%, metric=115, samples=2]".into())
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 0))
+                .wait()
+                .and_then(|r| describe(Some(r))),
+            Some("Bad [change=+21.0%, metric=121, samples=2]".into())
+        );
+        assert!(matches!(
+            probe
+                .run(state.start_probe(ib(6), 0))
+                .wait()
+                .map(unannotated),
+            Some(Skip(_))
+        ));
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(8), 0))
+                .wait()
+                .map(unannotated),
+            Some(Skip(
+                "The output didn't match the metric regex \"took ([0-9]+)ms\"".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_run_with_limits() {
+        let dir = TestDir::new("probe-limits");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let (send, recv) = unbounded();
+
+        let probe = command_probe(
+            command_gen("if [ $0 -ge 5 ]; then sleep 5; fi"),
+            scratch.clone(),
+        )
+        .with_limits(ResourceLimits {
+            max_rss: None,
+            wall: Some(Duration::from_millis(200)),
+        })
+        .with_event_sender(send);
+        let state = SearchState::new(r(0, 10));
+
+        let good = probe.run(state.start_probe(ib(3), 0)).wait().unwrap();
+        assert_eq!(good.result, Continue(Good));
+        assert!(good.annotations.metrics.contains_key("max_rss"));
+
+        let start = Instant::now();
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+        // killed at the limit rather than judged once it exited
+        assert!(start.elapsed() < Duration::from_secs(2));
+        match recv.try_recv() {
+            Ok(ProbeNoted(n)) => assert_eq!(
+                n.detail,
+                "wall time went over the limit of 200ms, so the command was killed"
+            ),
+            e => panic!("unexpected event {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_run_with_log_dir() {
+        let dir = TestDir::new("probe-log-dir");
+        let log_dir = LogDir::new(dir.join("logs")).unwrap();
+        let probe = command_probe(
+            command_gen("echo out $0; echo err >&2; exit $(($0 >= 5))"),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_setup(hook("echo setup"), FailureAction::Abort)
+        .with_log_dir(Some(log_dir.clone()));
+        let state = SearchState::new(r(0, 10));
+        let read = |name: &str| fs::read_to_string(dir.join("logs").join(name)).unwrap();
+
+        let mut ctx = state.start_probe(ib(7), 0);
+        ctx.attempt = 2;
+        assert_eq!(probe.run(ctx).wait().map(unannotated), Some(Continue(Bad)));
+        assert_eq!(read("7.attempt2.out"), "out 7\n");
+        assert_eq!(read("7.attempt2.err"), "err\n");
+        assert_eq!(read("7.attempt2.setup.out"), "setup\n");
+
+        // running the point again replaces its logs
+        let ctx = state.start_probe(ib(3), 0);
+        probe.run(ctx.clone()).wait().unwrap();
+        probe.run(ctx.clone()).wait().unwrap();
+        assert_eq!(read("3.out"), "out 3\n");
+        assert_eq!(
+            probe.logs().get(&ib(3)),
+            Some(&log_dir.logs_for(&ctx, &["command"]))
+        );
+    }
+
+    #[test]
+    fn test_diff_boundary() {
+        let dir = TestDir::new("probe-diff");
+        let probe = command_probe(
+            command_gen("echo at $0; echo $$; [ $0 -lt 5 ] || echo broken >&2; [ $0 -lt 5 ]"),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_boundary_output(true);
+        let diff = OutputDiff::new(vec![Regex::new("^[0-9]+$").unwrap()]);
+        let state = SearchState::new(r(0, 10));
+
+        probe.run(state.start_probe(ib(4), 0)).wait().unwrap();
+        assert_eq!(
+            probe.diff_boundary(&diff, &ib(4), &ib(5), &r(0, 10)),
+            Ok("--- 4.out\n+++ 5.out\n@@ -1 +1 @@\n-at 4\n+at 5\n--- 4.err\n+++ 5.err\n@@ -0,0 +1 @@\n+broken\n".into())
+        );
+        // 5 was probed again above, so its output is kept now, but the search never asked for it
+        assert_eq!(probe.results().len(), 1);
+        assert_eq!(probe.history().len(), 1);
+        assert_eq!(
+            probe.diff_boundary(&diff, &ib(6), &ib(5), &r(0, 10)),
+            Err(
+                "Probing 6 again gave a different result (Bad), so its output can't be compared"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_run_with_probe_output() {
+        let dir = TestDir::new("probe-probe-output");
+        let probe = command_probe(
+            command_gen("echo at $0; echo err >&2; [ $0 -lt 5 ]"),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_probe_output(true);
+        let state = SearchState::new(r(0, 10));
+
+        probe.run(state.start_probe(ib(7), 0)).wait().unwrap();
+        let history = probe.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].result, Some(Continue(Bad)));
+        assert_eq!(
+            history[0].command,
+            Some(
+                ["sh", "-c", "echo at $0; echo err >&2; [ $0 -lt 5 ]", "7"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            history[0].output,
+            vec![(
+                "command".to_string(),
+                CommandOutput {
+                    stdout: "at 7\n".into(),
+                    stderr: "err\n".into(),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_run_with_artifacts_from_scratch() {
+        let dir = TestDir::new("probe-artifacts-scratch");
+        let artifacts = Arc::new(
+            Artifacts::new(dir.join("results"), vec!["*.core".into()], CollectFor::All).unwrap(),
+        );
+        let probe = command_probe(
+            command_gen("echo $0 > \"$TMPDIR/$0.core\""),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Point).unwrap()),
+        )
+        .with_artifacts(Some(artifacts.clone()));
+        let state = SearchState::new(r(0, 10));
+
+        probe.run(state.start_probe(ib(4), 0)).wait().unwrap();
+        probe.run(state.start_probe(ib(7), 0)).wait().unwrap();
+
+        // each probe only gets its own files
+        assert!(dir.join("results/4/4.core").exists());
+        assert!(!dir.join("results/7/4.core").exists());
+        assert_eq!(
+            fs::read_to_string(dir.join("results/7/7.core")).unwrap(),
+            "7\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_artifacts() {
+        let dir = TestDir::new("probe-artifacts");
+        let artifacts = Arc::new(
+            Artifacts::new(dir.join("results"), vec!["*.core".into()], CollectFor::Bad).unwrap(),
+        );
+        let probe = command_probe(
+            command_gen("echo $0 > $0.core; [ $0 -lt 5 ]"),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_cwd(Some(
+            TemplateString::parse(
+                &format!("{}/cwd", dir.display()),
+                "$X",
+                &ProbeContext::VARIABLE_NAMES,
+            )
+            .unwrap(),
+        ))
+        .with_artifacts(Some(artifacts.clone()));
+        let state = SearchState::new(r(0, 10));
+
+        probe.run(state.start_probe(ib(4), 0)).wait().unwrap();
+        probe.run(state.start_probe(ib(7), 0)).wait().unwrap();
+
+        assert_eq!(
+            artifacts.collected(),
+            BTreeMap::from([(ib(7), dir.join("results/7"))])
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("results/7/7.core")).unwrap(),
+            "7\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_pty() {
+        let dir = TestDir::new("probe-pty");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap());
+        let probe = |pty| {
+            command_probe(command_gen("test -t 1 && test $0 -lt 5"), scratch.clone()).with_pty(pty)
+        };
+        let state = SearchState::new(r(0, 10));
+
+        assert_eq!(
+            probe(Some(PtySize::default()))
+                .run(state.start_probe(ib(3), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+        assert_eq!(
+            probe(None)
+                .run(state.start_probe(ib(3), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+    }
+
+    #[test]
+    fn test_teardown_after_cancellation() {
+        let dir = TestDir::new("probe-cancel");
+        let marker = dir.join("torn-down");
+
+        let probe = command_probe(
+            command_gen("sleep 5"),
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker).unwrap()),
+        )
+        .with_teardown(hook(&format!("touch '{}'", marker.display())));
+
+        let task = probe.run(SearchState::new(r(0, 10)).start_probe(ib(4), 0));
+        thread::sleep(Duration::from_millis(100));
+        task.request_cancellation();
+
+        assert_eq!(task.wait(), None);
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn test_run_with_cwd_and_tmpdir() {
+        let dir = TestDir::new("probe-cwd");
+        let scratch =
+            Arc::new(ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Point).unwrap());
+
+        let probe = command_probe(
+            command_gen("pwd > \"$TMPDIR/pwd\"; test \"$0\" -lt 5"),
+            scratch.clone(),
+        )
+        .with_cwd(Some(
+            TemplateString::parse(
+                &format!("{}/builds/$X", dir.display()),
+                "$X",
+                &ProbeContext::VARIABLE_NAMES,
+            )
+            .unwrap(),
+        ));
+
+        let state = SearchState::new(r(0, 10));
+
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(3), 0))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Good))
+        );
+        assert_eq!(
+            probe
+                .run(state.start_probe(ib(7), 1))
+                .wait()
+                .map(unannotated),
+            Some(Continue(Bad))
+        );
+
+        assert!(dir.join("builds/3").is_dir());
+        assert!(dir.join("builds/7").is_dir());
+        assert!(scratch.kept().is_empty());
+    }
+
+    #[test]
+    fn test_run_keeps_failed_scratch() {
+        let dir = TestDir::new("probe-keep");
+        let scratch = Arc::new(
+            ScratchDirs::new_in(&dir.join("scratch"), ScratchMode::Worker)
+                .unwrap()
+                .with_keep_failed(true),
+        );
+
+        let probe = command_probe(
+            command_gen("echo $0 > \"$TMPDIR/out\"; test \"$0\" -lt 5"),
+            scratch.clone(),
+        );
+
+        let state = SearchState::new(r(0, 10));
+        probe.run(state.start_probe(ib(3), 0)).wait();
+        probe.run(state.start_probe(ib(7), 0)).wait();
+
+        let kept = scratch.kept();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0.point, ib(7));
+        assert_eq!(fs::read_to_string(kept[0].1.join("out")).unwrap(), "7\n");
+    }
+}
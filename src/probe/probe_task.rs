@@ -0,0 +1,342 @@
+use crate::collections::collect_collection::CollectVec;
+use crate::messaging::mailbox::Mailbox;
+use crate::task::cancellable_subprocess::{
+    CancellableSubprocess, SubprocessError, SubprocessOptions, SubprocessOutput,
+};
+use crate::task::cancellable_task::CancellableTask;
+use crate::threading::async_value::AsyncValue;
+use crossbeam_channel::{bounded, unbounded, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Default)]
+struct Running {
+    cancelled: bool,
+    // interrupts whatever the probe is currently blocked on
+    current: Option<Box<dyn Fn() + Send>>,
+}
+
+type Finished = (usize, Option<Result<SubprocessOutput, SubprocessError>>);
+
+/// Tags each result with the index of the subprocess it came from.
+struct Tagged(usize, Sender<Finished>);
+
+impl Mailbox<'static> for Tagged {
+    type Message = Option<Result<SubprocessOutput, SubprocessError>>;
+
+    fn send_msg(&self, msg: Self::Message) -> bool {
+        self.1.send((self.0, msg)).is_ok()
+    }
+}
+
+/// Runs the subprocesses making up a probe one after another on the `ProbeTask`'s thread.
+pub struct ProbeSteps {
+    running: Arc<Mutex<Running>>,
+}
+
+impl ProbeSteps {
+    fn spawn(
+        args: &[String],
+        options: &SubprocessOptions,
+    ) -> Result<CancellableSubprocess, SubprocessError> {
+        let args = args.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        CancellableSubprocess::new(&args, options)
+    }
+
+    /// `true` if the probe has been cancelled.
+    pub fn cancelled(&self) -> bool {
+        self.running.lock().unwrap().cancelled
+    }
+
+    /// Runs the given command to completion.
+    ///
+    /// Returns None if the probe was cancelled before or while the command ran, in which case the command is killed.
+    pub fn run(
+        &self,
+        args: &[String],
+        options: &SubprocessOptions,
+    ) -> Option<Result<SubprocessOutput, SubprocessError>> {
+        let subprocess = {
+            let mut running = self.running.lock().unwrap();
+            if running.cancelled {
+                return None;
+            }
+
+            let subprocess = match Self::spawn(args, options) {
+                Ok(s) => Arc::new(s),
+                Err(e) => return Some(Err(e)),
+            };
+            let subprocess_clone = subprocess.clone();
+            running.current = Some(Box::new(move || subprocess_clone.request_cancellation()));
+            subprocess
+        };
+
+        let ret = subprocess.wait();
+        self.running.lock().unwrap().current = None;
+
+        ret.filter(|_| !self.cancelled())
+    }
+
+    /// Runs the given commands, each with its own options, at the same time, passing each one's index and result to `on_result` as it finishes.
+    ///
+    /// Once `on_result` returns false, the commands still running are killed and their results are dropped. Returns once every command has exited, or None if the probe was cancelled.
+    pub fn run_concurrently(
+        &self,
+        commands: &[(Vec<String>, SubprocessOptions)],
+        mut on_result: impl FnMut(usize, Result<SubprocessOutput, SubprocessError>) -> bool,
+    ) -> Option<()> {
+        let (send, recv) = unbounded();
+
+        let subprocesses = {
+            let mut running = self.running.lock().unwrap();
+            if running.cancelled {
+                return None;
+            }
+
+            let mut subprocesses = Vec::new();
+            for (i, (args, options)) in commands.iter().enumerate() {
+                match Self::spawn(args, options) {
+                    Ok(s) => subprocesses.push((i, Arc::new(s))),
+                    Err(e) => send.send((i, Some(Err(e)))).unwrap(),
+                }
+            }
+
+            let subprocesses_clone = subprocesses.iter().map(|(_, s)| s.clone()).collect_vec();
+            running.current = Some(Box::new(move || {
+                subprocesses_clone
+                    .iter()
+                    .for_each(|s| s.request_cancellation())
+            }));
+            subprocesses
+        };
+
+        for (i, s) in subprocesses.iter() {
+            s.notify_when_done(Tagged(*i, send.clone()));
+        }
+
+        let mut wanted = true;
+        for _ in 0..commands.len() {
+            let (i, result) = recv.recv().unwrap();
+            if let (true, Some(result)) = (wanted && !self.cancelled(), result) {
+                wanted = on_result(i, result);
+                if !wanted {
+                    subprocesses
+                        .iter()
+                        .for_each(|(_, s)| s.request_cancellation());
+                }
+            }
+        }
+
+        self.running.lock().unwrap().current = None;
+
+        (!self.cancelled()).then_some(())
+    }
+
+    /// Waits for a task shared with something else, like a build.
+    ///
+    /// Returns None if the probe was cancelled before or while waiting. Cancelling the probe stops the wait, but does not cancel `task`.
+    pub fn wait_for<T>(&self, task: &impl CancellableTask<T>) -> Option<T>
+    where
+        T: Send + Sync + Clone + 'static,
+    {
+        let (send, recv) = bounded(2);
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.cancelled {
+                return None;
+            }
+
+            let send_clone = send.clone();
+            running.current = Some(Box::new(move || {
+                send_clone.send_msg(None);
+            }));
+        }
+
+        task.notify_when_done(send);
+        let ret = recv.recv().unwrap();
+        self.running.lock().unwrap().current = None;
+
+        ret.filter(|_| !self.cancelled())
+    }
+
+    /// Runs the given command to completion, even if the probe is cancelled.
+    pub fn run_uncancellable(
+        &self,
+        args: &[String],
+        options: &SubprocessOptions,
+    ) -> Result<SubprocessOutput, SubprocessError> {
+        let subprocess = Self::spawn(args, options)?;
+        subprocess
+            .wait()
+            .expect("the subprocess is never cancelled, so it always produces a value")
+    }
+}
+
+/// A probe made up of several subprocesses run in sequence, like setup, the command, and teardown.
+///
+/// Cancelling it kills the subprocess started with `ProbeSteps::run` that is currently running, and makes any later `run` return None immediately. The task only reports being done once the given function returns, so steps run with `run_uncancellable` (like teardown) always finish first.
+pub struct ProbeTask<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    value: AsyncValue<Option<T>>,
+    running: Arc<Mutex<Running>>,
+}
+
+impl<T> ProbeTask<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    /// Starts running `steps` on a new thread. If it returns None, or the task is cancelled, the task's value is None.
+    pub fn spawn(steps: impl FnOnce(&ProbeSteps) -> Option<T> + Send + 'static) -> Self {
+        let value = AsyncValue::new();
+        let running = Arc::new(Mutex::new(Running::default()));
+
+        let value_clone = value.clone();
+        let probe_steps = ProbeSteps {
+            running: running.clone(),
+        };
+
+        thread::spawn(move || {
+            let ret = steps(&probe_steps);
+            value_clone.send(ret.filter(|_| !probe_steps.cancelled()));
+        });
+
+        Self { value, running }
+    }
+}
+
+impl<T> CancellableTask<T> for ProbeTask<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    fn notify_when_done(&self, mailbox: impl Mailbox<'static, Message = Option<T>> + 'static) {
+        self.value.notify_when_done(mailbox);
+    }
+
+    fn request_cancellation(&self) {
+        let mut running = self.running.lock().unwrap();
+        running.cancelled = true;
+        if let Some(current) = &running.current {
+            current();
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::TestDir;
+    use std::time::{Duration, Instant};
+
+    fn args(script: &str) -> Vec<String> {
+        vec!["sh".into(), "-c".into(), script.into()]
+    }
+
+    #[test]
+    fn test_runs_steps_in_order() {
+        let task = ProbeTask::spawn(|steps| {
+            let options = SubprocessOptions::new();
+            let a = steps.run(&args("echo a"), &options)?.ok()?;
+            let b = steps.run(&args("echo b"), &options)?.ok()?;
+            Some(a.output.unwrap() + &b.output.unwrap())
+        });
+
+        assert_eq!(task.wait(), Some("a\nb\n".to_string()));
+    }
+
+    #[test]
+    fn test_cancel_still_runs_uncancellable_steps() {
+        let dir = TestDir::new("probe-task");
+        let marker = dir.join("marker");
+        let marker_clone = marker.clone();
+
+        let start = Instant::now();
+
+        let task = ProbeTask::spawn(move |steps| {
+            let options = SubprocessOptions::new();
+            let main = steps.run(&args("sleep 5"), &options);
+            let _ = steps.run_uncancellable(
+                &args(&format!("sleep 0.2; touch '{}'", marker_clone.display())),
+                &options,
+            );
+            main.map(|_| ())
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        task.request_cancellation();
+
+        assert_eq!(task.wait(), None);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn test_cancel_while_waiting_for_shared_task() {
+        let shared = Arc::new(ProbeTask::spawn(|steps| {
+            steps
+                .run(&args("sleep 0.3; echo built"), &SubprocessOptions::new())?
+                .ok()?
+                .output
+        }));
+
+        let shared_clone = shared.clone();
+        let waiter = ProbeTask::spawn(move |steps| steps.wait_for(shared_clone.as_ref()));
+
+        thread::sleep(Duration::from_millis(50));
+        waiter.request_cancellation();
+
+        assert_eq!(waiter.wait(), None);
+        assert_eq!(shared.wait(), Some("built\n".to_string()));
+
+        let shared_clone = shared.clone();
+        let waiter = ProbeTask::spawn(move |steps| steps.wait_for(shared_clone.as_ref()));
+        assert_eq!(waiter.wait(), Some("built\n".to_string()));
+    }
+
+    #[test]
+    fn test_run_concurrently_stops_early() {
+        let start = Instant::now();
+
+        let task = ProbeTask::spawn(|steps| {
+            let mut finished = Vec::new();
+            let commands =
+                ["sleep 5", "exit 3", "sleep 5"].map(|c| (args(c), SubprocessOptions::new()));
+            steps.run_concurrently(&commands, |i, result| {
+                finished.push((i, result.unwrap().status.code()));
+                false
+            })?;
+            Some(finished)
+        });
+
+        assert_eq!(task.wait(), Some(vec![(1, Some(3))]));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_run_concurrently_cancel() {
+        let task = ProbeTask::spawn(|steps| {
+            let commands = ["sleep 5", "sleep 5"].map(|c| (args(c), SubprocessOptions::new()));
+            steps.run_concurrently(&commands, |_, _| true)
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        task.request_cancellation();
+        assert_eq!(task.wait(), None);
+    }
+
+    #[test]
+    fn test_cancel_before_run() {
+        let task = ProbeTask::spawn(|steps| {
+            thread::sleep(Duration::from_millis(100));
+            steps
+                .run(&args("echo a"), &SubprocessOptions::new())?
+                .ok()?;
+            Some(())
+        });
+
+        task.request_cancellation();
+        assert_eq!(task.wait(), None);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::task::resource_usage::{format_bytes, format_duration, ResourceUsage};
+use std::time::Duration;
+
+/// Limits on what a probe's command may use before its point counts as bad.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ResourceLimits {
+    /// The peak resident set size, in bytes.
+    pub max_rss: Option<u64>,
+    pub wall: Option<Duration>,
+}
+
+impl ResourceLimits {
+    /// Describes the first limit that `usage` goes over, or returns None if it's within every limit.
+    pub fn exceeded(&self, usage: &ResourceUsage) -> Option<String> {
+        let rss = self
+            .max_rss
+            .filter(|limit| usage.max_rss > *limit)
+            .map(|limit| {
+                format!(
+                    "max RSS {} was over the limit of {}",
+                    format_bytes(usage.max_rss),
+                    format_bytes(limit)
+                )
+            });
+        let wall = self.wall.filter(|limit| usage.wall > *limit).map(|limit| {
+            format!(
+                "wall time {} was over the limit of {}",
+                format_duration(usage.wall),
+                format_duration(limit)
+            )
+        });
+
+        rss.or(wall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let limits = ResourceLimits {
+            max_rss: Some(1 << 30),
+            wall: Some(Duration::from_secs(10)),
+        };
+        let usage = ResourceUsage {
+            wall: Duration::from_secs(3),
+            max_rss: 512 << 20,
+            ..Default::default()
+        };
+
+        assert_eq!(limits.exceeded(&usage), None);
+        assert_eq!(
+            limits.exceeded(&ResourceUsage {
+                max_rss: 3 << 29,
+                ..usage
+            }),
+            Some("max RSS 1.5G was over the limit of 1G".into())
+        );
+        assert_eq!(
+            limits.exceeded(&ResourceUsage {
+                wall: Duration::from_millis(10500),
+                ..usage
+            }),
+            Some("wall time 10.5s was over the limit of 10s".into())
+        );
+        assert_eq!(ResourceLimits::default().exceeded(&usage), None);
+    }
+}
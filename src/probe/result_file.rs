@@ -0,0 +1,185 @@
+use crate::parasect::types::Annotations;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadOutput;
+use crate::parasect::types::ParasectPayloadResult::{Continue, Skip, Stop};
+use crate::probe::output_rules::OutputVerdict;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// What a probe wrote to `PARASECT_RESULT_FILE`.
+///
+/// The file holds a JSON object like `{"verdict": "skip", "note": "flaky infra", "metrics": {"ms": 123}}`, where every field is optional.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ResultFile {
+    pub verdict: Option<OutputVerdict>,
+    pub annotations: Annotations,
+}
+
+fn parse_verdict(value: &Value) -> Result<OutputVerdict, String> {
+    match value.as_str() {
+        Some("good") => Ok(OutputVerdict::Answer(Good)),
+        Some("bad") => Ok(OutputVerdict::Answer(Bad)),
+        Some("skip") => Ok(OutputVerdict::Skip),
+        _ => Err(format!(
+            "\"verdict\" must be \"good\", \"bad\" or \"skip\" (got {})",
+            value
+        )),
+    }
+}
+
+fn parse_metrics(value: &Value) -> Result<Vec<(String, String)>, String> {
+    let metrics = value
+        .as_object()
+        .ok_or_else(|| format!("\"metrics\" must be an object (got {})", value))?;
+
+    metrics
+        .iter()
+        .map(|(k, v)| match v {
+            Value::Number(n) => Ok((k.clone(), n.to_string())),
+            Value::String(s) => Ok((k.clone(), s.clone())),
+            Value::Bool(b) => Ok((k.clone(), b.to_string())),
+            _ => Err(format!(
+                "The metric \"{}\" must be a number, a string or a boolean (got {})",
+                k, v
+            )),
+        })
+        .collect()
+}
+
+impl ResultFile {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        let object: &Map<String, Value> = value
+            .as_object()
+            .ok_or_else(|| format!("Expected a JSON object (got {})", value))?;
+
+        let mut ret = Self::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "verdict" => ret.verdict = Some(parse_verdict(value)?),
+                "note" => {
+                    ret.annotations.note = Some(
+                        value
+                            .as_str()
+                            .ok_or_else(|| format!("\"note\" must be a string (got {})", value))?
+                            .to_string(),
+                    )
+                }
+                "metrics" => ret.annotations.metrics = parse_metrics(value)?.into_iter().collect(),
+                _ => return Err(format!("Unknown field \"{}\"", key)),
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Reads the result file at `path`, or returns None if the probe didn't write one.
+    pub fn read(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(s) => Self::parse(&s).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Applies the file to the output the probe would otherwise have had.
+    ///
+    /// The verdict (if any) replaces the result, unless the probe was stopped. The note and metrics are added to any annotations the output already has, replacing those with the same name.
+    pub fn apply(self, output: ParasectPayloadOutput) -> ParasectPayloadOutput {
+        let result = match (&output.result, self.verdict) {
+            (Stop(_), _) | (_, None) => output.result,
+            (_, Some(OutputVerdict::Answer(a))) => Continue(a),
+            (_, Some(OutputVerdict::Skip)) => Skip(
+                self.annotations
+                    .note
+                    .clone()
+                    .unwrap_or_else(|| "the result file said to skip the point".into()),
+            ),
+        };
+
+        ParasectPayloadOutput::new(result, output.annotations.merge(self.annotations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let file = ResultFile::parse(
+            r#"{"verdict":"skip","note":"flaky infra","metrics":{"ms":123,"host":"a"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(file.verdict, Some(OutputVerdict::Skip));
+        assert_eq!(file.annotations.note, Some("flaky infra".into()));
+        assert_eq!(
+            file.annotations.metrics.into_iter().collect::<Vec<_>>(),
+            vec![("host".into(), "a".into()), ("ms".into(), "123".into())]
+        );
+
+        assert_eq!(ResultFile::parse("{}"), Ok(ResultFile::default()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ResultFile::parse("{").is_err());
+        assert_eq!(
+            ResultFile::parse("[]"),
+            Err("Expected a JSON object (got [])".into())
+        );
+        assert_eq!(
+            ResultFile::parse(r#"{"verdict":"maybe"}"#),
+            Err("\"verdict\" must be \"good\", \"bad\" or \"skip\" (got \"maybe\")".into())
+        );
+        assert_eq!(
+            ResultFile::parse(r#"{"metrics":{"ms":[1]}}"#),
+            Err("The metric \"ms\" must be a number, a string or a boolean (got [1])".into())
+        );
+        assert_eq!(
+            ResultFile::parse(r#"{"verdcit":"bad"}"#),
+            Err("Unknown field \"verdcit\"".into())
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let file = ResultFile::parse(r#"{"verdict":"bad","note":"slow"}"#).unwrap();
+
+        assert_eq!(
+            file.clone().apply(Continue(Good).into()),
+            ParasectPayloadOutput::new(Continue(Bad), file.annotations.clone())
+        );
+        assert_eq!(
+            file.clone().apply(Stop("nope".into()).into()).result,
+            Stop("nope".into())
+        );
+
+        let skip = ResultFile::parse(r#"{"verdict":"skip"}"#).unwrap();
+        assert_eq!(
+            skip.apply(Continue(Bad).into()),
+            Skip("the result file said to skip the point".into()).into()
+        );
+
+        let note_only = ResultFile::parse(r#"{"note":"hi"}"#).unwrap();
+        assert_eq!(
+            note_only
+                .apply(Continue(Good).into())
+                .annotations
+                .to_string(),
+            "hi"
+        );
+
+        let metrics = ResultFile::parse(r#"{"metrics":{"ms":5}}"#).unwrap();
+        assert_eq!(
+            metrics
+                .apply(ParasectPayloadOutput::new(Continue(Good), file.annotations))
+                .annotations
+                .to_string(),
+            "slow; ms=5"
+        );
+    }
+}
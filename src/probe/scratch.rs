@@ -0,0 +1,196 @@
+use crate::parasect::probe_context::ProbeContext;
+use clap::ValueEnum;
+use ibig::IBig;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{env, fs, io, process};
+
+/// Which probes share a scratch directory.
+#[derive(ValueEnum, PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum ScratchMode {
+    /// Each worker reuses its own directory for all of its probes.
+    #[default]
+    Worker,
+    /// Each probe gets a fresh directory, deleted after the probe.
+    Point,
+}
+
+/// A temporary directory holding the scratch directories of the probes.
+///
+/// Everything is deleted when this struct is dropped, except the directories of failed probes if `with_keep_failed` is set.
+#[derive(Debug)]
+pub struct ScratchDirs {
+    root: PathBuf,
+    mode: ScratchMode,
+    keep_failed: bool,
+    kept: Mutex<Vec<(ProbeContext, PathBuf)>>,
+}
+
+impl ScratchDirs {
+    /// Creates a fresh root directory under the system's temporary directory.
+    pub fn new(mode: ScratchMode) -> io::Result<Self> {
+        Self::new_in(&env::temp_dir(), mode)
+    }
+
+    pub fn new_in(parent: &Path, mode: ScratchMode) -> io::Result<Self> {
+        let root = parent.join(format!("parasect-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+
+        Ok(Self {
+            root,
+            mode,
+            keep_failed: false,
+            kept: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Keeps the scratch directory of each failed probe for post-mortem instead of deleting it.
+    pub fn with_keep_failed(mut self, keep_failed: bool) -> Self {
+        self.keep_failed = keep_failed;
+        self
+    }
+
+    fn point_dir(&self, ctx: &ProbeContext) -> PathBuf {
+        if ctx.attempt == 1 {
+            self.root.join(format!("point-{}", ctx.point))
+        } else {
+            self.root
+                .join(format!("point-{}-attempt-{}", ctx.point, ctx.attempt))
+        }
+    }
+
+    /// The scratch directory for the given probe, created if it doesn't exist yet.
+    pub fn dir_for(&self, ctx: &ProbeContext) -> io::Result<PathBuf> {
+        let dir = match self.mode {
+            ScratchMode::Worker => self.root.join(format!("worker-{}", ctx.worker_id)),
+            ScratchMode::Point => self.point_dir(ctx),
+        };
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// The directory for the build artifacts of the given point, created if it doesn't exist yet.
+    ///
+    /// Artifacts are shared by every probe of the point, and live until the scratch directories are dropped.
+    pub fn artifact_dir(&self, point: &IBig) -> io::Result<PathBuf> {
+        let dir = self.root.join("artifacts").join(format!("point-{}", point));
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Cleans up after the given probe is done with its directory.
+    ///
+    /// If the probe failed and failed directories are kept, the directory is moved out of the way so the worker gets a fresh one next time.
+    pub fn finish(&self, ctx: &ProbeContext, failed: bool) {
+        let dir = match self.dir_for(ctx) {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+
+        if failed && self.keep_failed {
+            let kept = self.point_dir(ctx);
+            if kept == dir || fs::rename(&dir, &kept).is_ok() {
+                self.kept.lock().unwrap().push((ctx.clone(), kept));
+            }
+        } else if self.mode == ScratchMode::Point {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    /// The directories kept for post-mortem so far, along with the probe each one belongs to.
+    pub fn kept(&self) -> Vec<(ProbeContext, PathBuf)> {
+        self.kept.lock().unwrap().clone()
+    }
+}
+
+impl Drop for ScratchDirs {
+    fn drop(&mut self) {
+        let kept = self.kept.lock().unwrap();
+
+        if kept.is_empty() {
+            let _ = fs::remove_dir_all(&self.root);
+            return;
+        }
+
+        for entry in fs::read_dir(&self.root).into_iter().flatten().flatten() {
+            if !kept.iter().any(|(_, k)| k == &entry.path()) {
+                let _ = fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parasect::probe_context::SearchState;
+    use crate::test_util::test_util::test_util::{ib, r, TestDir};
+
+    #[test]
+    fn test_worker_dirs() {
+        let parent = TestDir::new("scratch-worker");
+        let scratch = ScratchDirs::new_in(&parent, ScratchMode::Worker).unwrap();
+        let state = SearchState::new(r(0, 10));
+
+        let w0 = scratch.dir_for(&state.start_probe(ib(5), 0)).unwrap();
+        let w1 = scratch.dir_for(&state.start_probe(ib(6), 1)).unwrap();
+
+        assert_ne!(w0, w1);
+        assert!(w0.is_dir() && w1.is_dir());
+
+        let ctx = state.start_probe(ib(7), 0);
+        assert_eq!(scratch.dir_for(&ctx).unwrap(), w0);
+
+        scratch.finish(&ctx, false);
+        assert!(w0.is_dir());
+
+        drop(scratch);
+        assert!(!w0.exists());
+    }
+
+    #[test]
+    fn test_point_dirs() {
+        let parent = TestDir::new("scratch-point");
+        let scratch = ScratchDirs::new_in(&parent, ScratchMode::Point).unwrap();
+        let state = SearchState::new(r(0, 10));
+
+        let ctx = state.start_probe(ib(5), 0);
+        let dir = scratch.dir_for(&ctx).unwrap();
+        assert!(dir.ends_with("point-5"));
+
+        let retry = scratch.dir_for(&state.start_probe(ib(5), 1)).unwrap();
+        assert!(retry.ends_with("point-5-attempt-2"));
+
+        scratch.finish(&ctx, true);
+        assert!(!dir.exists());
+        assert!(scratch.kept().is_empty());
+    }
+
+    #[test]
+    fn test_keep_failed() {
+        let parent = TestDir::new("scratch-keep");
+        let scratch = ScratchDirs::new_in(&parent, ScratchMode::Worker)
+            .unwrap()
+            .with_keep_failed(true);
+        let state = SearchState::new(r(0, 10));
+
+        let good = state.start_probe(ib(3), 0);
+        scratch.dir_for(&good).unwrap();
+        scratch.finish(&good, false);
+
+        let bad = state.start_probe(ib(8), 0);
+        let dir = scratch.dir_for(&bad).unwrap();
+        fs::write(dir.join("log"), "oops").unwrap();
+        scratch.finish(&bad, true);
+
+        let kept = scratch.kept();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, bad);
+        assert!(kept[0].1.ends_with("point-8"));
+
+        drop(scratch);
+        assert_eq!(fs::read_to_string(kept[0].1.join("log")).unwrap(), "oops");
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::cli::error_handling::CliResult;
+use crate::template::expression::Variables;
+use crate::template::template_string::TemplateString;
+use crate::ui::line::mkline;
+use crate::ui::segment::{Attributes, Color};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A file that is rendered from a template into the probe's scratch directory before each probe.
+///
+/// Given on the command line as `SRC:DEST`, where `DEST` is relative to the scratch directory.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TemplateFile {
+    source: PathBuf,
+    destination: PathBuf,
+    template: TemplateString,
+}
+
+impl TemplateFile {
+    /// Parses a `SRC:DEST` spec and reads the template from `SRC`.
+    ///
+    /// `variable_names` are the named variables the template may reference, like in `TemplateString::parse`.
+    pub fn load(spec: &str, substitution_string: &str, variable_names: &[&str]) -> CliResult<Self> {
+        let (source, destination) = spec
+            .split_once(':')
+            .filter(|(s, d)| !s.is_empty() && !d.is_empty())
+            .ok_or_else(|| {
+                vec![mkline!(
+                    "--template-file must be of the form SRC:DEST (got \"",
+                    spec,
+                    "\")."
+                )]
+            })?;
+
+        let destination = PathBuf::from(destination);
+        if !destination
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(vec![mkline!(
+                "The destination of --template-file must be a relative path within the scratch directory (got \"",
+                destination.display().to_string(),
+                "\")."
+            )]);
+        }
+
+        let contents = fs::read_to_string(source).map_err(|e| {
+            vec![mkline!(
+                "Failed to read the template file \"",
+                source,
+                "\": ",
+                e.to_string()
+            )]
+        })?;
+
+        let template = TemplateString::parse(&contents, substitution_string, variable_names)
+            .map_err(|e| {
+                vec![mkline!(
+                    "Invalid substitution ",
+                    (&e.source, Color::Blue, Attributes::Bold),
+                    " in the template file \"",
+                    source,
+                    "\": ",
+                    e.message
+                )]
+            })?;
+
+        Ok(Self {
+            source: PathBuf::from(source),
+            destination,
+            template,
+        })
+    }
+
+    /// `true` if the template references the value being probed.
+    pub fn references_value(&self) -> bool {
+        self.template.references_value() || self.template.references_variable("PARASECT_VALUE")
+    }
+
+    /// Renders the template to its destination within `dir`, creating directories as needed.
+    ///
+    /// Returns the path of the rendered file.
+    pub fn render_into(&self, dir: &Path, variables: &Variables) -> Result<PathBuf, String> {
+        let contents = self.template.render(variables).map_err(|e| {
+            format!(
+                "Failed to render the template file \"{}\": {}",
+                self.source.display(),
+                e
+            )
+        })?;
+
+        let path = dir.join(&self.destination);
+        let write = || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, contents)
+        };
+
+        write().map_err(|e| format!("Failed to write \"{}\": {}", path.display(), e))?;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::{ib, TestDir};
+
+    #[test]
+    fn test_render_into() {
+        let dir = TestDir::new("template-file");
+        let source = dir.join("config.tmpl");
+        fs::write(&source, "size: ${X*2}\nworker: ${W}\nhome: ${HOME}\n").unwrap();
+
+        let file = TemplateFile::load(
+            &format!("{}:out/config.yaml", source.display()),
+            "$X",
+            &["W"],
+        )
+        .unwrap();
+
+        let out = dir.join("worker");
+        let path = file
+            .render_into(&out, &Variables::new(ib(21)).with("W", 3))
+            .unwrap();
+
+        assert!(file.references_value());
+        assert_eq!(path, out.join("out/config.yaml"));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "size: 42\nworker: 3\nhome: ${HOME}\n"
+        );
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert_eq!(
+            TemplateFile::load("config.tmpl", "$X", &[]).unwrap_err(),
+            vec![mkline!(
+                "--template-file must be of the form SRC:DEST (got \"config.tmpl\")."
+            )]
+        );
+
+        assert_eq!(
+            TemplateFile::load("config.tmpl:../config.yaml", "$X", &[]).unwrap_err(),
+            vec![mkline!(
+                "The destination of --template-file must be a relative path within the scratch directory (got \"../config.yaml\")."
+            )]
+        );
+
+        assert!(TemplateFile::load("/nonexistent/config.tmpl:config.yaml", "$X", &[]).is_err());
+    }
+}
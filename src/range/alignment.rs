@@ -0,0 +1,131 @@
+use ibig::ops::RemEuclid;
+use ibig::{IBig, UBig};
+use std::fmt::{Display, Formatter};
+
+/// Restricts a search space to the numbers `offset + k * step` for any integer `k`.
+///
+/// The default alignment (step 1, offset 0) allows every number.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Hash, Debug)]
+pub struct Alignment {
+    // stored as an IBig to avoid converting back and forth during arithmetic. always > 0.
+    step: IBig,
+    offset: IBig,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self::new(UBig::from(1usize), IBig::from(0))
+    }
+}
+
+impl Display for Alignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("{}k + {}", self.step, self.offset()))
+    }
+}
+
+impl Alignment {
+    /// Creates an alignment allowing the numbers `offset + k * step`.
+    ///
+    /// Panics if `step` is 0.
+    pub fn new(step: impl Into<UBig>, offset: impl Into<IBig>) -> Self {
+        let step = IBig::from(step.into());
+        assert_ne!(step, IBig::from(0), "The step of an alignment cannot be 0.");
+
+        let offset = offset.into().rem_euclid(&step);
+
+        Self { step, offset }
+    }
+
+    /// The smallest aligned number that is >= `n`.
+    pub fn align_up(&self, n: &IBig) -> IBig {
+        let down = self.align_down(n);
+        if &down == n {
+            down
+        } else {
+            down + &self.step
+        }
+    }
+
+    /// The largest aligned number that is <= `n`.
+    pub fn align_down(&self, n: &IBig) -> IBig {
+        n - (n - &self.offset).rem_euclid(&self.step)
+    }
+
+    /// `true` if `n` is one of the allowed numbers.
+    pub fn is_aligned(&self, n: &IBig) -> bool {
+        &self.align_down(n) == n
+    }
+
+    /// The offset of the alignment, normalized to `[0, step)`.
+    pub fn offset(&self) -> IBig {
+        self.offset.clone()
+    }
+
+    /// The distance between two consecutive aligned numbers.
+    pub fn step(&self) -> IBig {
+        self.step.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::{ib, ub};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_default_aligns_everything() {
+        let a = Alignment::default();
+
+        for i in -5..5 {
+            assert!(a.is_aligned(&ib(i)));
+            assert_eq!(a.align_up(&ib(i)), ib(i));
+            assert_eq!(a.align_down(&ib(i)), ib(i));
+        }
+    }
+
+    #[test]
+    fn test_align() {
+        let a = Alignment::new(ub(64u8), ib(1));
+
+        assert_eq!(a.align_down(&ib(64)), ib(1));
+        assert_eq!(a.align_up(&ib(64)), ib(65));
+        assert_eq!(a.align_up(&ib(65)), ib(65));
+        assert_eq!(a.align_down(&ib(-1)), ib(-63));
+        assert_eq!(a.align_up(&ib(-64)), ib(-63));
+        assert!(a.is_aligned(&ib(129)));
+        assert!(!a.is_aligned(&ib(128)));
+    }
+
+    #[test]
+    fn test_offset_is_normalized() {
+        assert_eq!(
+            Alignment::new(ub(2u8), ib(-1)),
+            Alignment::new(ub(2u8), ib(1))
+        );
+        assert_eq!(Alignment::new(ub(4u8), ib(9)).offset(), ib(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_step_panics() {
+        Alignment::new(ub(0u8), ib(0));
+    }
+
+    proptest! {
+        #[test]
+        fn test_align_bounds(n in -1000..1000, step in 1u32..100, offset in -100..100) {
+            let a = Alignment::new(step, offset);
+            let n = ib(n);
+
+            let up = a.align_up(&n);
+            let down = a.align_down(&n);
+
+            prop_assert!(a.is_aligned(&up));
+            prop_assert!(a.is_aligned(&down));
+            prop_assert!(down <= n && n <= up);
+            prop_assert!(&up - &down <= a.step());
+        }
+    }
+}
@@ -1,41 +1,55 @@
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
 use crate::range::numeric_range_set::NumericRangeSet;
 use crate::util::macros::unwrap_or;
-use ibig::IBig;
+use ibig::{IBig, UBig};
 use std::collections::VecDeque;
 use std::sync::{Mutex, RwLock};
 
 /// Produces a sequence of points that bisect the input space.
 ///
 /// Ranges can also be "invalidated", preventing them from being selected in the future.
+///
+/// Only numbers matching the queue's `Alignment` are produced as split points.
 pub struct BisectingRangeQueue {
     // mutex because all operations on this mutate it
     range_queue: Mutex<VecDeque<NumericRange>>,
     // rwlock because reads can happen independently of writes
     invalid: RwLock<NumericRangeSet>,
+    alignment: Alignment,
 }
 
 impl BisectingRangeQueue {
     /// Creates a new BisectingRangeQueue that bisects the given range.
+    #[allow(unused)]
     pub fn new(initial_range: NumericRange) -> Self {
+        Self::new_aligned(initial_range, Alignment::default())
+    }
+
+    /// Creates a new BisectingRangeQueue that bisects the given range, only splitting on aligned numbers.
+    pub fn new_aligned(initial_range: NumericRange, alignment: Alignment) -> Self {
         let mut q = VecDeque::new();
         q.push_back(initial_range.clone());
 
         Self {
             range_queue: Mutex::new(q),
             invalid: RwLock::new(NumericRangeSet::new()),
+            alignment,
         }
     }
 
-    fn split(range: &NumericRange) -> (IBig, NumericRange, NumericRange) {
-        let (low, high) = range.as_tuple().expect("should not split an empty range");
+    /// Splits the range on the aligned number closest to (but not above) its midpoint.
+    ///
+    /// Returns None if there are no aligned numbers in the range.
+    fn split(&self, range: &NumericRange) -> Option<(IBig, NumericRange, NumericRange)> {
+        let (low, high) = range.align(&self.alignment).as_tuple()?;
 
-        let mid = (low + high) / 2;
+        let mid = self.alignment.align_down(&((low + high) / 2));
 
         let left = range.truncate_end(&(&mid - 1));
         let right = range.truncate_start(&(&mid + 1));
 
-        (mid, left, right)
+        Some((mid, left, right))
     }
 
     fn pop_next_valid_node(&self) -> Option<NumericRange> {
@@ -54,7 +68,9 @@ impl BisectingRangeQueue {
     }
 
     fn append(&self, range: NumericRange) {
-        if range.is_empty() || self.invalid.read().unwrap().contains_range(&range) {
+        if range.aligned_len(&self.alignment) == UBig::from(0usize)
+            || self.invalid.read().unwrap().contains_range(&range)
+        {
             return;
         }
 
@@ -67,9 +83,13 @@ impl BisectingRangeQueue {
     ///
     /// Either or both ranges returned can be empty.
     pub fn dequeue(&self) -> Option<(IBig, NumericRange, NumericRange)> {
-        let range = unwrap_or!(self.pop_next_valid_node(), return None);
+        let (split_point, left, right) = loop {
+            let range = unwrap_or!(self.pop_next_valid_node(), return None);
 
-        let (split_point, left, right) = Self::split(&range);
+            if let Some(split) = self.split(&range) {
+                break split;
+            }
+        };
 
         self.append(left.clone());
         self.append(right.clone());
@@ -77,6 +97,21 @@ impl BisectingRangeQueue {
         Some((split_point, left, right))
     }
 
+    /// Returns up to `n` split points that upcoming dequeue() calls will produce, in order,
+    /// assuming nothing is invalidated in the meantime. The queue is not modified.
+    pub fn peek(&self, n: usize) -> Vec<IBig> {
+        let range_queue = self.range_queue.lock().unwrap();
+        let invalid = self.invalid.read().unwrap();
+
+        range_queue
+            .iter()
+            .filter(|range| !invalid.contains_range(range))
+            .filter_map(|range| self.split(range))
+            .map(|(point, _, _)| point)
+            .take(n)
+            .collect()
+    }
+
     /// Marks a range (and all ranges within that range) as invalid, meaning they will not be
     /// present in subsequent dequeue() calls.
     ///
@@ -166,6 +201,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dequeue_aligned_produces_only_aligned_elements() {
+        let mut ns = HashSet::new();
+        let q = BisectingRangeQueue::new_aligned(r(0, 40), Alignment::new(8u8, 3));
+
+        while let Some((pt, _, _)) = q.dequeue() {
+            ns.insert(pt);
+        }
+
+        assert_eq!(
+            ns,
+            [3, 11, 19, 27, 35].into_iter().map(ib).collect_hashset()
+        );
+    }
+
+    #[test]
+    fn test_dequeue_aligned_check_first() {
+        let q = BisectingRangeQueue::new_aligned(r(0, 40), Alignment::new(8u8, 3));
+
+        assert_eq!(q.dequeue(), Some((ib(19), r(0, 18), r(20, 40))));
+    }
+
+    #[test]
+    fn test_dequeue_no_aligned_elements() {
+        let q = BisectingRangeQueue::new_aligned(r(4, 10), Alignment::new(8u8, 3));
+
+        assert_eq!(q.dequeue(), None);
+    }
+
+    #[test]
+    fn test_peek() {
+        let q = BisectingRangeQueue::new(r(0, 14));
+
+        assert_eq!(q.peek(3), vec![ib(7)]);
+        assert_eq!(q.dequeue().map(|x| x.0), Some(ib(7)));
+        assert_eq!(q.peek(3), vec![ib(3), ib(11)]);
+
+        q.invalidate(&r(0, 6));
+        assert_eq!(q.peek(3), vec![ib(11)]);
+        assert_eq!(q.dequeue().map(|x| x.0), Some(ib(11)));
+    }
+
     proptest! {
         #[test]
         fn test_binary_search(a in 1..100, b in 1..100) {
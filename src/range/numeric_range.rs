@@ -1,4 +1,5 @@
 use crate::collections::collect_collection::CollectVec;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::MaybeSplitNumericRange::*;
 use crate::util::macros::unwrap_or;
 use ibig::{IBig, UBig};
@@ -320,39 +321,66 @@ impl NumericRange {
     ///
     /// Panics if 0 partitions are requested.
     pub fn partition(&self, partitions: usize) -> Vec<Self> {
+        self.partition_aligned(partitions, &Alignment::default())
+    }
+
+    /// Partitions the range into at most `partitions` connected partitions, each holding the same amount of aligned numbers, remainders spread towards the front.
+    ///
+    /// Every partition ends on an aligned number except the last, which ends on the end of the range. You will get fewer than `partitions` partitions if the range has fewer aligned numbers than the number of partitions. A range without aligned numbers always partitions into an empty vec.
+    ///
+    /// Panics if 0 partitions are requested.
+    pub fn partition_aligned(&self, partitions: usize, alignment: &Alignment) -> Vec<Self> {
         assert_ne!(partitions, 0, "Cannot partition into 0 partitions.");
 
-        let mut ptr = unwrap_or!(self.first(), return Vec::new());
+        let aligned_first = unwrap_or!(self.align(alignment).first(), return Vec::new());
+        let aligned_len = self.aligned_len(alignment);
 
-        if UBig::from(partitions) > self.len() {
-            return (0..self.len().try_into().unwrap())
-                .into_iter()
-                .map(|i| Self::from_point(&ptr + i))
-                .collect_vec();
-        }
+        let partitions =
+            usize::try_from(&aligned_len).map_or(partitions, |len| min(len, partitions));
 
-        let size_per = self.len() / partitions;
-        let mut remainder = self.len() % partitions;
+        let size_per = &aligned_len / partitions;
+        let mut remainder = &aligned_len % partitions;
 
         let mut ret = Vec::new();
+        let mut start = self.low.clone();
+        let mut aligned_index = UBig::from(0usize);
 
-        for _ in 0..partitions {
-            let delta = &size_per + if remainder > 0 { 0 } else { -1 };
+        while ret.len() < partitions {
+            aligned_index += &size_per;
             if remainder > 0 {
+                aligned_index += 1;
                 remainder -= 1;
             }
 
-            ret.push(Self::from_endpoints_inclusive(
-                ptr.clone(),
-                &ptr + IBig::from(&delta),
-            ));
+            let end = if ret.len() + 1 == partitions {
+                self.high.clone()
+            } else {
+                &aligned_first + IBig::from(&aligned_index - 1) * alignment.step()
+            };
 
-            ptr += IBig::from(&delta) + 1;
+            ret.push(Self::from_endpoints_inclusive(start, end.clone()));
+            start = end + 1;
         }
 
         ret
     }
 
+    /// Shrinks the range so its endpoints are the first and last aligned numbers within it.
+    ///
+    /// Returns an empty range if there are no aligned numbers in the range.
+    pub fn align(&self, alignment: &Alignment) -> NumericRange {
+        let (low, high) = unwrap_or!(self.as_tuple(), return Self::empty());
+        Self::from_endpoints_inclusive(alignment.align_up(&low), alignment.align_down(&high))
+    }
+
+    /// How many aligned numbers there are in the range.
+    pub fn aligned_len(&self, alignment: &Alignment) -> UBig {
+        match self.align(alignment).as_tuple() {
+            None => UBig::from(0usize),
+            Some((low, high)) => UBig::try_from((high - low) / alignment.step() + 1).unwrap(),
+        }
+    }
+
     /// Truncates the end of the range to a maximum of `end`.
     pub fn truncate_end(&self, end: &IBig) -> NumericRange {
         NumericRange::from_endpoints_inclusive(
@@ -656,6 +684,38 @@ mod tests {
         assert_eq!(empty().partition(4), Vec::new());
     }
 
+    #[test]
+    fn test_partition_aligned() {
+        let a = Alignment::new(ub(4u8), ib(1));
+
+        assert_eq!(r(0, 20).partition_aligned(2, &a), vec!(r(0, 9), r(10, 20)));
+        assert_eq!(
+            r(0, 20).partition_aligned(10, &a),
+            vec!(r(0, 1), r(2, 5), r(6, 9), r(10, 13), r(14, 20))
+        );
+        assert_eq!(r(2, 4).partition_aligned(3, &a), Vec::new());
+        assert_eq!(empty().partition_aligned(3, &a), Vec::new());
+    }
+
+    #[test]
+    fn test_align() {
+        let a = Alignment::new(ub(4u8), ib(1));
+
+        assert_eq!(r(0, 20).align(&a), r(1, 17));
+        assert_eq!(r(1, 17).align(&a), r(1, 17));
+        assert_eq!(r(2, 4).align(&a), empty());
+        assert_eq!(empty().align(&a), empty());
+    }
+
+    #[test]
+    fn test_aligned_len() {
+        let a = Alignment::new(ub(4u8), ib(1));
+
+        assert_eq!(r(0, 20).aligned_len(&a), ub(5u8));
+        assert_eq!(r(2, 4).aligned_len(&a), ub(0u8));
+        assert_eq!(r(0, 20).aligned_len(&Alignment::default()), ub(21u8));
+    }
+
     #[test]
     fn test_sub() {
         assert_eq!(&r(1, 10) - 5, Split(r(1, 4), r(6, 10)));
@@ -906,6 +966,24 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_partition_aligned_connected(a in 1..1000usize, b in 1..1000usize, c in 1..1000usize, step in 1u32..20) {
+            let mut nums = [a, b, c];
+            nums.sort();
+            let (partitions, lo, hi) = (nums[0], nums[1], nums[2]);
+            let alignment = Alignment::new(step, 3);
+            prop_assume!(r(lo, hi).aligned_len(&alignment) > ub(0u8));
+
+            let parts = r(lo, hi).partition_aligned(partitions, &alignment);
+
+            prop_assert_eq!(consolidate_range_stream(parts.clone().into_iter()), vec!(r(lo, hi)));
+
+            for (a, b) in parts.iter().zip(parts.iter().skip(1)) {
+                prop_assert!(alignment.is_aligned(&a.last().unwrap()));
+                prop_assert_eq!(a.last().unwrap() + 1, b.first().unwrap());
+            }
+        }
+
         #[test]
         fn test_sub_self_is_empty(a in 1..1000, b in 1..1000) {
             let range = r(a, b);
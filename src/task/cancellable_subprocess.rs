@@ -1,20 +1,34 @@
 use crate::messaging::mailbox::Mailbox;
 use crate::task::cancellable_subprocess::SubprocessError::*;
 use crate::task::cancellable_task::CancellableTask;
+use crate::task::child_process::ChildProcess;
+use crate::task::pty::{read_output, Pty, PtySize};
+use crate::task::resource_usage::{format_bytes, ResourceUsage};
 use crate::threading::async_value::AsyncValue;
 use crate::util::functional::compose_once;
-use shared_child::SharedChild;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
-use std::io::Read;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread::JoinHandle;
+use std::time::Duration;
 use std::{io, thread};
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct SubprocessOutput {
     pub status: ExitStatus,
     pub output: Option<String>,
+    /// Only captured if `SubprocessOptions::with_capture_stderr` is set. Otherwise stderr goes to parasect's stdout, or only to the log if there is one.
+    ///
+    /// Always None when running in a pty, since stderr is then part of `output`.
+    pub stderr: Option<String>,
+    pub usage: ResourceUsage,
+    /// Whether the process group was killed because it ran past `SubprocessOptions::with_deadline`.
+    pub timed_out: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -38,45 +52,301 @@ impl Display for SubprocessError {
     }
 }
 
+/// How a `CancellableSubprocess` is stopped when it's cancelled: `signal` is sent to its process group, and if it hasn't exited after `grace`, SIGKILL is.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Cancellation {
+    pub signal: libc::c_int,
+    pub grace: Duration,
+}
+
+impl Default for Cancellation {
+    fn default() -> Self {
+        Self {
+            signal: libc::SIGTERM,
+            grace: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Files that a subprocess's stdout and stderr are copied to as they're written. Both are appended to.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct OutputLog {
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+}
+
+/// How much of each output stream is kept in memory and copied to the log, unless set otherwise.
+pub const DEFAULT_OUTPUT_LIMIT: usize = 16 << 20;
+
+/// How a `CancellableSubprocess` is launched, beyond its arguments.
+///
+/// By default the subprocess inherits parasect's environment and working directory.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SubprocessOptions {
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+    capture_stderr: bool,
+    stderr_to_stderr: bool,
+    pty: Option<PtySize>,
+    cancellation: Cancellation,
+    log: Option<OutputLog>,
+    output_limit: usize,
+    deadline: Option<Duration>,
+}
+
+impl Default for SubprocessOptions {
+    fn default() -> Self {
+        Self {
+            env: BTreeMap::new(),
+            cwd: None,
+            capture_stderr: false,
+            stderr_to_stderr: false,
+            pty: None,
+            cancellation: Cancellation::default(),
+            log: None,
+            output_limit: DEFAULT_OUTPUT_LIMIT,
+            deadline: None,
+        }
+    }
+}
+
+#[allow(unused)]
+impl SubprocessOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets an environment variable in addition to the inherited ones.
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_envs<K: Into<String>, V: Into<String>>(
+        mut self,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.env
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    pub fn with_cwd(mut self, cwd: impl AsRef<Path>) -> Self {
+        self.cwd = Some(cwd.as_ref().to_path_buf());
+        self
+    }
+
+    /// The directory the subprocess runs in, if it isn't parasect's.
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// Captures stderr into `SubprocessOutput::stderr` instead of passing it through.
+    pub fn with_capture_stderr(mut self, capture_stderr: bool) -> Self {
+        self.capture_stderr = capture_stderr;
+        self
+    }
+
+    /// Passes stderr through to parasect's stderr instead of its stdout, e.g. to keep it out of machine-readable output. Has no effect if stderr is captured or logged.
+    pub fn with_stderr_to_stderr(mut self, stderr_to_stderr: bool) -> Self {
+        self.stderr_to_stderr = stderr_to_stderr;
+        self
+    }
+
+    /// Runs the subprocess in a pseudo-terminal of the given size instead of with pipes, for programs that behave differently when attached to a terminal.
+    ///
+    /// Everything the subprocess prints goes to `SubprocessOutput::output`, with escape sequences stripped, and it can't read parasect's stdin.
+    pub fn with_pty(mut self, pty: Option<PtySize>) -> Self {
+        self.pty = pty;
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Copies stdout and stderr to files as the subprocess runs. Stderr then goes only to its file, unless it's also captured.
+    pub fn with_log(mut self, log: Option<OutputLog>) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Limits how many bytes of each stream are kept in `SubprocessOutput` and copied to the log. The rest is read and dropped.
+    pub fn with_output_limit(mut self, limit: usize) -> Self {
+        self.output_limit = limit;
+        self
+    }
+
+    /// Kills the subprocess's process group once it has run for this long, in which case it finishes with `SubprocessOutput::timed_out` set.
+    pub fn with_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+}
+
+fn open_log(path: &Path) -> Result<File, SubprocessError> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| {
+            let msg = format!("failed to open the log file \"{}\": {}", path.display(), e);
+            ProcessSpawnError(Arc::new(io::Error::new(e.kind(), msg)))
+        })
+}
+
+/// Copies `data` to `log`, or as much of it as fits in what's left of `limit` after `logged` bytes, with a note when it's cut off.
+fn write_log(log: &mut File, data: &[u8], logged: usize, limit: usize) {
+    let room = limit.saturating_sub(logged);
+    if room == 0 {
+        return;
+    }
+
+    let _ = log.write_all(&data[..data.len().min(room)]);
+    if data.len() > room {
+        let _ = write!(
+            log,
+            "\n[parasect: output cut off after {}]\n",
+            format_bytes(limit as u64)
+        );
+    }
+}
+
+/// Reads `r` to the end, copying it to `log` as it arrives and returning it if `keep` is set, in both cases up to `limit` bytes.
+fn capture(mut r: impl Read, mut log: Option<File>, keep: bool, limit: usize) -> Option<String> {
+    let mut kept = Vec::new();
+    let mut logged = 0;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match r.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        };
+        if keep {
+            let room = limit.saturating_sub(kept.len());
+            kept.extend_from_slice(&buf[..n.min(room)]);
+        }
+        if let Some(log) = &mut log {
+            write_log(log, &buf[..n], logged, limit);
+            logged += n;
+        }
+    }
+
+    keep.then(|| String::from_utf8_lossy(&kept).into_owned())
+}
+
+/// Like `capture`, for a pty: reads the output with escape sequences stripped, copying it to `log` as it arrives and returning it, in both cases up to `limit` bytes.
+fn capture_pty(master: File, mut log: Option<File>, limit: usize) -> Option<String> {
+    let mut kept = String::new();
+    let mut logged = 0;
+    read_output(master, limit, |text| {
+        if kept.len() < limit {
+            kept.push_str(text);
+        }
+        if let Some(log) = &mut log {
+            write_log(log, text.as_bytes(), logged, limit);
+            logged += text.len();
+        }
+    })?;
+
+    Some(truncate(kept, limit))
+}
+
+/// Cuts `s` down to at most `limit` bytes, on a character boundary.
+fn truncate(mut s: String, limit: usize) -> String {
+    if s.len() > limit {
+        let mut end = limit;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    s
+}
+
 /// A subprocess that can be cancelled mid-execution.
 ///
-/// Cancellation sends a SIGKILL
+/// It runs in its own process group, which cancellation signals as configured by `SubprocessOptions::with_cancellation`. The task reports being cancelled right away, without waiting for the group to exit.
 pub struct CancellableSubprocess {
-    child: Arc<SharedChild>,
+    child: Arc<ChildProcess>,
+    cancellation: Cancellation,
     msg: Arc<AsyncValue<Option<Result<SubprocessOutput, SubprocessError>>>>,
     _thread: JoinHandle<()>,
 }
 
 impl CancellableSubprocess {
-    pub fn new(args: &[&str]) -> Result<CancellableSubprocess, SubprocessError> {
-        let child = SharedChild::spawn(
-            &mut Command::new(args[0])
-                .args(&args[1..])
-                .stdout(Stdio::piped())
-                .stderr(io::stdout()),
-        )
-        .map_err(compose_once(ProcessSpawnError, Arc::new))?;
+    pub fn new(
+        args: &[&str],
+        options: &SubprocessOptions,
+    ) -> Result<CancellableSubprocess, SubprocessError> {
+        let mut command = Command::new(args[0]);
+        command.args(&args[1..]).envs(&options.env);
+
+        let pty = match options.pty {
+            Some(size) => Some(
+                Pty::open(size)
+                    .and_then(|pty| pty.attach(&mut command))
+                    .map_err(compose_once(ProcessSpawnError, Arc::new))?,
+            ),
+            None => {
+                command.stdout(Stdio::piped());
+                if options.capture_stderr || options.log.is_some() {
+                    command.stderr(Stdio::piped());
+                } else if options.stderr_to_stderr {
+                    command.stderr(io::stderr());
+                } else {
+                    command.stderr(io::stdout());
+                }
+                None
+            }
+        };
+
+        if let Some(cwd) = &options.cwd {
+            command.current_dir(cwd);
+        }
+
+        let (stdout_log, stderr_log) = match &options.log {
+            Some(log) => (Some(open_log(&log.stdout)?), Some(open_log(&log.stderr)?)),
+            None => (None, None),
+        };
+        let capture_stderr = options.capture_stderr;
+        let limit = options.output_limit;
 
-        let child = Arc::new(child);
         let msg = Arc::new(AsyncValue::new());
+        let (spawned_send, spawned_recv) = mpsc::channel();
+        let timed_out = Arc::new(AtomicBool::new(false));
 
+        // the child is spawned by the thread that waits for it, since on Linux it's killed when the thread that spawned it exits
         let thread = {
-            let child_clone = child.clone();
             let msg_clone = msg.clone();
+            let timed_out = timed_out.clone();
             thread::spawn(move || {
-                let mut output = String::new();
-
-                let output_option = if let Err(_) = child_clone
-                    .take_stdout()
-                    .unwrap()
-                    .read_to_string(&mut output)
-                {
-                    None
-                } else {
-                    Some(output)
+                let child_clone = match ChildProcess::spawn(&mut command) {
+                    Ok(child) => Arc::new(child),
+                    Err(e) => {
+                        let _ = spawned_send.send(Err(e));
+                        return;
+                    }
+                };
+                // the command holds the child's end of the pty, which must be closed for reads to end when the child exits
+                drop(command);
+                let _ = spawned_send.send(Ok(child_clone.clone()));
+
+                // read stderr on its own thread so neither pipe fills up while the other is being read
+                let stderr_thread = child_clone.take_stderr().map(|stderr| {
+                    thread::spawn(move || capture(stderr, stderr_log, capture_stderr, limit))
+                });
+
+                let output_option = match pty {
+                    Some(master) => capture_pty(master, stdout_log, limit),
+                    None => capture(child_clone.take_stdout().unwrap(), stdout_log, true, limit),
                 };
+                let stderr = stderr_thread.and_then(|t| t.join().ok().flatten());
 
-                let status = match child_clone.wait() {
+                let (status, usage) = match child_clone.wait() {
                     Err(e) => {
                         msg_clone.send(Some(Err(ProcessWaitError(Arc::new(e)))));
                         let _ = child_clone.kill();
@@ -87,13 +357,33 @@ impl CancellableSubprocess {
 
                 msg_clone.send(Some(Ok(SubprocessOutput {
                     output: output_option,
+                    stderr,
                     status,
+                    usage,
+                    timed_out: timed_out.load(Ordering::SeqCst),
                 })));
             })
         };
 
+        let child = spawned_recv
+            .recv()
+            .expect("the thread always reports whether the child spawned")
+            .map_err(compose_once(ProcessSpawnError, Arc::new))?;
+
+        if let Some(deadline) = options.deadline {
+            let child = child.clone();
+            thread::spawn(move || {
+                if !child.wait_reaped(deadline) {
+                    // set first, since the kill is what lets the waiting thread finish
+                    timed_out.store(true, Ordering::SeqCst);
+                    let _ = child.kill();
+                }
+            });
+        }
+
         let ret = Self {
             child,
+            cancellation: options.cancellation,
             msg,
             _thread: thread,
         };
@@ -113,7 +403,17 @@ impl CancellableTask<Result<SubprocessOutput, SubprocessError>> for CancellableS
 
     fn request_cancellation(&self) -> () {
         self.msg.send_msg(None);
-        let _ = self.child.kill();
+        let _ = self.child.signal(self.cancellation.signal);
+
+        if self.cancellation.signal != libc::SIGKILL {
+            let child = self.child.clone();
+            let grace = self.cancellation.grace;
+            thread::spawn(move || {
+                if !child.wait_reaped(grace) {
+                    let _ = child.kill();
+                }
+            });
+        }
     }
 }
 
@@ -121,11 +421,13 @@ impl CancellableTask<Result<SubprocessOutput, SubprocessError>> for CancellableS
 #[cfg(target_os = "linux")]
 mod tests {
     use super::*;
+    use crate::test_util::test_util::test_util::TestDir;
+    use std::os::unix::process::ExitStatusExt;
     use std::time::{Duration, Instant};
 
     #[test]
     fn test_echo() {
-        let sp = CancellableSubprocess::new(&["echo", "foo"]).unwrap();
+        let sp = CancellableSubprocess::new(&["echo", "foo"], &SubprocessOptions::new()).unwrap();
 
         let result_arc = sp.wait().unwrap();
         let output = match result_arc.as_ref() {
@@ -136,11 +438,262 @@ mod tests {
         assert_eq!(output.output, Some("foo\n".to_string()));
     }
 
+    #[test]
+    fn test_env_and_cwd() {
+        let sp = CancellableSubprocess::new(
+            &["sh", "-c", "echo $FOO; pwd"],
+            &SubprocessOptions::new()
+                .with_env("FOO", "bar")
+                .with_cwd("/"),
+        )
+        .unwrap();
+
+        let result_arc = sp.wait().unwrap();
+        let output = match result_arc.as_ref() {
+            Ok(thing) => thing,
+            Err(e) => panic!("{:?}", e),
+        };
+
+        assert_eq!(output.output, Some("bar\n/\n".to_string()));
+    }
+
+    #[test]
+    fn test_capture_stderr() {
+        let sp = CancellableSubprocess::new(
+            &["sh", "-c", "echo out; echo err >&2"],
+            &SubprocessOptions::new().with_capture_stderr(true),
+        )
+        .unwrap();
+
+        let output = sp.wait().unwrap().unwrap();
+
+        assert_eq!(output.output, Some("out\n".to_string()));
+        assert_eq!(output.stderr, Some("err\n".to_string()));
+    }
+
+    #[test]
+    fn test_log() {
+        let dir = TestDir::new("subprocess-log");
+        let log = OutputLog {
+            stdout: dir.join("out"),
+            stderr: dir.join("err"),
+        };
+
+        let sp = CancellableSubprocess::new(
+            &[
+                "sh",
+                "-c",
+                "echo out; echo err >&2; head -c 100 /dev/zero | tr '\\0' x",
+            ],
+            &SubprocessOptions::new()
+                .with_log(Some(log.clone()))
+                .with_output_limit(10),
+        )
+        .unwrap();
+
+        let output = sp.wait().unwrap().unwrap();
+
+        assert_eq!(output.output, Some("out\nxxxxxx".to_string()));
+        assert_eq!(output.stderr, None);
+        assert_eq!(
+            std::fs::read_to_string(&log.stdout).unwrap(),
+            "out\nxxxxxx\n[parasect: output cut off after 10B]\n"
+        );
+        assert_eq!(std::fs::read_to_string(&log.stderr).unwrap(), "err\n");
+    }
+
+    fn wait_for_file(path: &Path) -> String {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(2) {
+            if let Ok(s) = std::fs::read_to_string(path) {
+                if s.ends_with('\n') {
+                    return s;
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("{} was never written", path.display());
+    }
+
+    fn is_alive(pid: &str) -> bool {
+        unsafe { libc::kill(pid.trim().parse().unwrap(), 0) == 0 }
+    }
+
+    #[test]
+    fn test_cancel_signals_group() {
+        let dir = TestDir::new("subprocess-cancel");
+
+        let sp = CancellableSubprocess::new(
+            &[
+                "sh",
+                "-c",
+                "trap 'echo bye > cleaned; exit 0' USR1; sleep 5 & echo $! > pid; wait",
+            ],
+            &SubprocessOptions::new()
+                .with_cwd(&dir)
+                .with_cancellation(Cancellation {
+                    signal: libc::SIGUSR1,
+                    grace: Duration::from_secs(5),
+                }),
+        )
+        .unwrap();
+        let pid = wait_for_file(&dir.join("pid"));
+
+        sp.request_cancellation();
+
+        assert!(sp.wait().is_none());
+        assert_eq!(wait_for_file(&dir.join("cleaned")), "bye\n");
+        // the grandchild got the signal too
+        let start = Instant::now();
+        while is_alive(&pid) {
+            assert!(start.elapsed() < Duration::from_secs(2));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_cancel_escalates_to_kill() {
+        let dir = TestDir::new("subprocess-escalate");
+
+        let sp = CancellableSubprocess::new(
+            &["sh", "-c", "trap '' TERM; sleep 5 & echo $! > pid; wait"],
+            &SubprocessOptions::new()
+                .with_cwd(&dir)
+                .with_cancellation(Cancellation {
+                    signal: libc::SIGTERM,
+                    grace: Duration::from_millis(200),
+                }),
+        )
+        .unwrap();
+        let pid = wait_for_file(&dir.join("pid"));
+
+        sp.request_cancellation();
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(is_alive(&pid));
+        let start = Instant::now();
+        while is_alive(&pid) {
+            assert!(start.elapsed() < Duration::from_secs(2));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_deadline() {
+        let start = Instant::now();
+
+        let sp = CancellableSubprocess::new(
+            &["sh", "-c", "sleep 5 & wait"],
+            &SubprocessOptions::new().with_deadline(Some(Duration::from_millis(100))),
+        )
+        .unwrap();
+
+        let output = sp.wait().unwrap().unwrap();
+        assert!(output.timed_out);
+        assert_eq!(output.status.signal(), Some(libc::SIGKILL));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let sp = CancellableSubprocess::new(
+            &["true"],
+            &SubprocessOptions::new().with_deadline(Some(Duration::from_secs(5))),
+        )
+        .unwrap();
+        assert!(!sp.wait().unwrap().unwrap().timed_out);
+    }
+
+    #[test]
+    fn test_pty() {
+        let sp = CancellableSubprocess::new(
+            &[
+                "sh",
+                "-c",
+                "test -t 1 && printf '\\033[31mtty\\033[0m\\n'; stty size; echo err >&2",
+            ],
+            &SubprocessOptions::new()
+                .with_capture_stderr(true)
+                .with_pty(Some(PtySize {
+                    cols: 100,
+                    rows: 30,
+                })),
+        )
+        .unwrap();
+
+        let output = sp.wait().unwrap().unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.output, Some("tty\n30 100\nerr\n".to_string()));
+        assert_eq!(output.stderr, None);
+    }
+
+    #[test]
+    fn test_pty_log() {
+        let dir = TestDir::new("subprocess-pty-log");
+        let log = OutputLog {
+            stdout: dir.join("out"),
+            stderr: dir.join("err"),
+        };
+
+        let sp = CancellableSubprocess::new(
+            &[
+                "sh",
+                "-c",
+                "printf '\\033[31mred\\033[0m\\n'; sleep 5; head -c 100 /dev/zero | tr '\\0' x",
+            ],
+            &SubprocessOptions::new()
+                .with_pty(Some(PtySize::default()))
+                .with_log(Some(log.clone()))
+                .with_output_limit(10),
+        )
+        .unwrap();
+
+        // the first line is logged while the command is still running
+        assert_eq!(wait_for_file(&log.stdout), "red\n");
+        sp.request_cancellation();
+        assert!(sp.wait().is_none());
+
+        let sp = CancellableSubprocess::new(
+            &["sh", "-c", "echo red; head -c 100 /dev/zero | tr '\\0' x"],
+            &SubprocessOptions::new()
+                .with_pty(Some(PtySize::default()))
+                .with_log(Some(log.clone()))
+                .with_output_limit(10),
+        )
+        .unwrap();
+
+        let output = sp.wait().unwrap().unwrap();
+
+        assert_eq!(output.output, Some("red\nxxxxxx".to_string()));
+        assert_eq!(
+            std::fs::read_to_string(&log.stdout).unwrap(),
+            "red\nred\nxxxxxx\n[parasect: output cut off after 10B]\n"
+        );
+    }
+
+    #[test]
+    fn test_cancel_pty() {
+        let start = Instant::now();
+
+        let sp = CancellableSubprocess::new(
+            &["sleep", "5"],
+            &SubprocessOptions::new().with_pty(Some(PtySize::default())),
+        )
+        .unwrap();
+
+        let result_option = thread::scope(|scope| {
+            let t = scope.spawn(|| sp.wait());
+            sp.request_cancellation();
+            t.join().unwrap()
+        });
+
+        assert!(result_option.is_none());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
     #[test]
     fn test_cancel() {
         let start = Instant::now();
 
-        let sp = CancellableSubprocess::new(&["sleep", "5"]).unwrap();
+        let sp = CancellableSubprocess::new(&["sleep", "5"], &SubprocessOptions::new()).unwrap();
 
         let result_option = thread::scope(|scope| {
             let t = scope.spawn(|| sp.wait());
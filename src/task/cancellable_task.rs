@@ -25,6 +25,7 @@ where
     fn notify_when_done(&self, mailbox: impl Mailbox<'static, Message = Option<T>> + 'static);
 
     /// Maps the result of the CancellableTask.
+    #[allow(unused)]
     fn map<R>(
         self,
         mapper: impl FnOnce(T) -> R + Send + 'static,
@@ -0,0 +1,265 @@
+use crate::task::resource_usage::ResourceUsage;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{ChildStderr, ChildStdout, Command, ExitStatus};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+const SIGNALS: [(&str, libc::c_int); 7] = [
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("TERM", libc::SIGTERM),
+];
+
+/// Parses a signal name like `TERM` or `SIGINT` (in any case), or a signal number.
+pub fn parse_signal(s: &str) -> Result<libc::c_int, String> {
+    if let Ok(n) = s.parse::<libc::c_int>() {
+        // Linux has 64 signals, including the real-time ones
+        return Some(n)
+            .filter(|n| (1..=64).contains(n))
+            .ok_or_else(|| format!("{} isn't a signal number", n));
+    }
+
+    let upper = s.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, signal)| *signal)
+        .ok_or_else(|| {
+            format!(
+                "Unknown signal \"{}\" (expected one of {} or a number)",
+                s,
+                SIGNALS.map(|(n, _)| n).join(", ")
+            )
+        })
+}
+
+/// A child process that one thread can wait on while others signal it.
+///
+/// The child leads its own process group, so that signals also reach anything it started, like the compilers run by `make`.
+///
+/// Waiting happens in two steps: first block until the child exits without reaping it, then reap it with `wait4` (which also gives its resource usage) under a lock. Signals still go to the process group after the child is reaped, so that anything it left running can be stopped too. The kernel doesn't hand out a pid again while a process group by that id has members, so they only reach an unrelated group if every member exited and the pid was reused in between.
+pub struct ChildProcess {
+    pid: libc::pid_t,
+    started: Instant,
+    reaped: Mutex<bool>,
+    reaped_changed: Condvar,
+    stdout: Mutex<Option<ChildStdout>>,
+    stderr: Mutex<Option<ChildStderr>>,
+}
+
+impl ChildProcess {
+    /// Spawns `command` in a new process group (unless it starts a new session, which is one already).
+    ///
+    /// On Linux, the child is killed if the calling thread exits first, e.g. because parasect crashed, so the thread should outlive the child.
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        let parent = unsafe { libc::getpid() };
+        unsafe {
+            command.pre_exec(move || {
+                if libc::getpgrp() != libc::getpid() && libc::setpgid(0, 0) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) < 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    // the parent may have died before the death signal was set up
+                    if libc::getppid() != parent {
+                        return Err(io::Error::from_raw_os_error(libc::ESRCH));
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        let started = Instant::now();
+        // dropping the std handle neither waits for nor kills the process, so it can be discarded
+        let mut child = command.spawn()?;
+
+        Ok(Self {
+            pid: child.id() as libc::pid_t,
+            started,
+            reaped: Mutex::new(false),
+            reaped_changed: Condvar::new(),
+            stdout: Mutex::new(child.stdout.take()),
+            stderr: Mutex::new(child.stderr.take()),
+        })
+    }
+
+    pub fn take_stdout(&self) -> Option<ChildStdout> {
+        self.stdout.lock().unwrap().take()
+    }
+
+    pub fn take_stderr(&self) -> Option<ChildStderr> {
+        self.stderr.lock().unwrap().take()
+    }
+
+    /// Waits for the child to exit and reaps it, returning its status and resource usage.
+    ///
+    /// Must be called at most once.
+    pub fn wait(&self) -> io::Result<(ExitStatus, ResourceUsage)> {
+        loop {
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let ret = unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    self.pid as libc::id_t,
+                    &mut info,
+                    libc::WEXITED | libc::WNOWAIT,
+                )
+            };
+            if ret == 0 {
+                break;
+            }
+
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+        let wall = self.started.elapsed();
+
+        let mut reaped = self.reaped.lock().unwrap();
+        let mut status = 0;
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        // the child already exited, so this doesn't block
+        if unsafe { libc::wait4(self.pid, &mut status, 0, &mut rusage) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        *reaped = true;
+        self.reaped_changed.notify_all();
+
+        Ok((
+            ExitStatus::from_raw(status),
+            ResourceUsage::from_rusage(&rusage, wall),
+        ))
+    }
+
+    /// Waits until the child has been reaped by `wait`, or until `timeout` passes. Returns `true` if it was reaped.
+    pub fn wait_reaped(&self, timeout: Duration) -> bool {
+        let reaped = self.reaped.lock().unwrap();
+        let (reaped, _) = self
+            .reaped_changed
+            .wait_timeout_while(reaped, timeout, |reaped| !*reaped)
+            .unwrap();
+        *reaped
+    }
+
+    /// Sends `signal` to the child's process group, which may outlive the child itself. Does nothing once the whole group has exited.
+    pub fn signal(&self, signal: libc::c_int) -> io::Result<()> {
+        if unsafe { libc::killpg(self.pid, signal) } == 0 {
+            return Ok(());
+        }
+
+        match io::Error::last_os_error() {
+            // the whole group already exited
+            e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+            e => Err(e),
+        }
+    }
+
+    pub fn kill(&self) -> io::Result<()> {
+        self.signal(libc::SIGKILL)
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::wait_for_condition;
+    use std::fs;
+    use std::io::Read;
+    use std::process::Stdio;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("TERM"), Ok(libc::SIGTERM));
+        assert_eq!(parse_signal("sigint"), Ok(libc::SIGINT));
+        assert_eq!(parse_signal("9"), Ok(libc::SIGKILL));
+        assert_eq!(parse_signal("0"), Err("0 isn't a signal number".into()));
+        assert_eq!(
+            parse_signal("STOP"),
+            Err(
+                "Unknown signal \"STOP\" (expected one of HUP, INT, QUIT, KILL, USR1, USR2, TERM or a number)"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_wait_reports_usage() {
+        let child =
+            ChildProcess::spawn(Command::new("sh").args(["-c", "sleep 0.1; exit 3"])).unwrap();
+
+        let (status, usage) = child.wait().unwrap();
+
+        assert_eq!(status.code(), Some(3));
+        assert!(usage.wall >= Duration::from_millis(100));
+        assert!(usage.max_rss > 0);
+        // signalling a group that's gone is a no-op rather than an error
+        assert!(child.kill().is_ok());
+    }
+
+    #[test]
+    fn test_kill() {
+        let child = ChildProcess::spawn(Command::new("sleep").arg("5")).unwrap();
+
+        child.kill().unwrap();
+        let (status, _) = child.wait().unwrap();
+
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        assert!(child.wait_reaped(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_signal_reaches_group() {
+        let child =
+            ChildProcess::spawn(Command::new("sh").args(["-c", "sleep 5; sleep 5"])).unwrap();
+        assert!(!child.wait_reaped(Duration::from_millis(100)));
+
+        // sh waits for its foreground sleep before handling SIGTERM, so this only finishes quickly if sleep got it too
+        let start = Instant::now();
+        child.signal(libc::SIGTERM).unwrap();
+        let (status, _) = child.wait().unwrap();
+
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_signal_reaches_group_after_reap() {
+        let child = ChildProcess::spawn(
+            Command::new("sh")
+                .args(["-c", "sleep 5 >/dev/null & echo $!"])
+                .stdout(Stdio::piped()),
+        )
+        .unwrap();
+        let mut out = String::new();
+        child
+            .take_stdout()
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        let straggler = out.trim().to_string();
+        assert!(child.wait().unwrap().0.success());
+
+        child.kill().unwrap();
+
+        // the straggler was reparented, so it may linger as a zombie until its new parent reaps it
+        wait_for_condition(
+            || match fs::read_to_string(format!("/proc/{}/stat", straggler)) {
+                Ok(stat) => stat.rsplit_once(") ").unwrap().1.starts_with('Z'),
+                Err(_) => true,
+            },
+            Duration::from_secs(2),
+            "the straggler survived",
+        );
+    }
+}
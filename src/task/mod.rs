@@ -1,10 +1,13 @@
 pub mod cancellable_subprocess;
 pub mod cancellable_task;
+pub mod child_process;
 pub mod free_cancellable_task;
 #[cfg(test)]
 pub mod function_cancellable_task;
 pub mod ignore_cancel_cancellable_task;
 pub mod map_cancellable_task;
+pub mod pty;
+pub mod resource_usage;
 pub mod result_cancellable_task;
 #[cfg(test)]
 pub mod test_cancellable_task;
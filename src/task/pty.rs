@@ -0,0 +1,229 @@
+use std::ffi::CStr;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+/// The size of a pseudo-terminal's window, in characters.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct PtySize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+impl Default for PtySize {
+    fn default() -> Self {
+        Self { cols: 80, rows: 24 }
+    }
+}
+
+impl PtySize {
+    /// Parses `COLSxROWS`, e.g. `120x40`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Expected COLSxROWS, e.g. 120x40 (got \"{}\")", s);
+        let (cols, rows) = s.split_once('x').ok_or_else(invalid)?;
+        let parse = |n: &str| n.parse::<u16>().ok().filter(|n| *n > 0).ok_or_else(invalid);
+
+        Ok(Self {
+            cols: parse(cols)?,
+            rows: parse(rows)?,
+        })
+    }
+
+    /// The size of the terminal parasect's stdout is attached to, if it is one.
+    pub fn of_terminal() -> Option<Self> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let ret = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+        (ret == 0 && size.ws_col > 0 && size.ws_row > 0).then_some(Self {
+            cols: size.ws_col,
+            rows: size.ws_row,
+        })
+    }
+}
+
+fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// A pseudo-terminal for one child: parasect reads the child's output from `master`, and the child gets `slave` as its terminal.
+pub struct Pty {
+    master: File,
+    slave: OwnedFd,
+}
+
+impl Pty {
+    /// Opens a new pseudo-terminal with the given window size.
+    ///
+    /// Both ends are close-on-exec, so they can't leak into children spawned concurrently by other threads, which would keep the terminal open after our child exits.
+    pub fn open(size: PtySize) -> io::Result<Self> {
+        unsafe {
+            let master = OwnedFd::from_raw_fd(check(libc::posix_openpt(
+                libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+            ))?);
+            check(libc::grantpt(master.as_raw_fd()))?;
+            check(libc::unlockpt(master.as_raw_fd()))?;
+
+            let mut name = [0 as libc::c_char; 128];
+            let ret = libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len());
+            if ret != 0 {
+                return Err(io::Error::from_raw_os_error(ret));
+            }
+            let slave = OwnedFd::from_raw_fd(check(libc::open(
+                CStr::from_ptr(name.as_ptr()).as_ptr(),
+                libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+            ))?);
+
+            let winsize = libc::winsize {
+                ws_row: size.rows,
+                ws_col: size.cols,
+                ws_xpixel: 0,
+                ws_ypixel: 0,
+            };
+            check(libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize))?;
+
+            Ok(Self {
+                master: File::from(master),
+                slave,
+            })
+        }
+    }
+
+    /// Makes the terminal the stdin, stdout, stderr and controlling terminal of `command`, which runs in a new session.
+    ///
+    /// Returns the end to read the output from. Once `command` is spawned and dropped, that end reports EOF when the child (and anything else it left the terminal to) exits.
+    pub fn attach(self, command: &mut Command) -> io::Result<File> {
+        command
+            .stdin(Stdio::from(self.slave.try_clone()?))
+            .stdout(Stdio::from(self.slave.try_clone()?))
+            .stderr(Stdio::from(self.slave));
+
+        unsafe {
+            command.pre_exec(|| {
+                check(libc::setsid())?;
+                check(libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0))?;
+                Ok(())
+            });
+        }
+
+        Ok(self.master)
+    }
+}
+
+/// Reads everything the child writes to the terminal, passing it to `on_text` with escape sequences stripped as it arrives.
+///
+/// The text is passed on a line at a time, so that what a `\r` overwrites is stripped along with it. A line longer than `max_line` bytes is passed on in pieces rather than held back whole. Returns None if reading failed, after passing on what was read.
+pub fn read_output(mut master: File, max_line: usize, mut on_text: impl FnMut(&str)) -> Option<()> {
+    let mut emit = |bytes: &[u8]| on_text(&strip_escapes(&String::from_utf8_lossy(bytes)));
+    let mut pending = Vec::new();
+    let mut buf = [0u8; 8192];
+    let ret = loop {
+        let n = match master.read(&mut buf) {
+            Ok(0) => break Some(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // Linux reports EIO rather than EOF once every slave is closed
+            Err(e) if e.raw_os_error() == Some(libc::EIO) => break Some(()),
+            Err(_) => break None,
+        };
+
+        pending.extend_from_slice(&buf[..n]);
+        let end = match pending.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None if pending.len() > max_line => pending.len(),
+            None => continue,
+        };
+        emit(&pending[..end]);
+        pending.drain(..end);
+    };
+
+    if !pending.is_empty() {
+        emit(&pending);
+    }
+    ret
+}
+
+/// Turns what a terminal was sent into plain text for logs.
+///
+/// Escape sequences (colors, cursor movement, titles, etc.) and control characters are removed, `\r\n` becomes `\n`, and a line that was overwritten after a `\r` (e.g. by a progress bar) keeps only its last version.
+pub fn strip_escapes(s: &str) -> String {
+    let mut plain = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters and intermediates, then a final byte
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: ends with BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // other sequences are intermediates followed by a final byte, e.g. `ESC ( B`
+                Some(c) if ('\x20'..='\x2f').contains(&c) => {
+                    while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\n' | '\r' | '\t' => plain.push(c),
+            c if c.is_control() => {}
+            c => plain.push(c),
+        }
+    }
+
+    plain
+        .split('\n')
+        .map(|line| {
+            let line = line.trim_end_matches('\r');
+            line.rsplit('\r').next().unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_parse() {
+        assert_eq!(
+            PtySize::parse("120x40"),
+            Ok(PtySize {
+                cols: 120,
+                rows: 40
+            })
+        );
+        assert_eq!(
+            PtySize::parse("120"),
+            Err("Expected COLSxROWS, e.g. 120x40 (got \"120\")".into())
+        );
+        assert!(PtySize::parse("0x40").is_err());
+    }
+
+    #[test]
+    fn test_strip_escapes() {
+        assert_eq!(strip_escapes("\x1b[1;31mred\x1b[0m\r\n"), "red\n");
+        assert_eq!(strip_escapes("\x1b]0;title\x07a\x1b]2;t\x1b\\b"), "ab");
+        assert_eq!(strip_escapes("\x1b(Bplain\x1b=\x07"), "plain");
+        assert_eq!(strip_escapes("10%\r50%\r100%\r\ndone\r\n"), "100%\ndone\n");
+        assert_eq!(strip_escapes("a\tb\x1b[2K\x1b[1Gc"), "a\tbc");
+    }
+}
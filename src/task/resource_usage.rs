@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+/// How much a finished process (and the descendants it waited for) used, as reported by `wait4`.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct ResourceUsage {
+    pub wall: Duration,
+    pub user: Duration,
+    pub sys: Duration,
+    /// The peak resident set size, in bytes.
+    pub max_rss: u64,
+}
+
+fn timeval_to_duration(t: &libc::timeval) -> Duration {
+    Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64)
+}
+
+impl ResourceUsage {
+    pub fn from_rusage(rusage: &libc::rusage, wall: Duration) -> Self {
+        Self {
+            wall,
+            user: timeval_to_duration(&rusage.ru_utime),
+            sys: timeval_to_duration(&rusage.ru_stime),
+            // Linux reports kilobytes
+            max_rss: rusage.ru_maxrss as u64 * 1024,
+        }
+    }
+
+    /// The largest of each measurement, e.g. to summarize several commands run for one probe.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            wall: self.wall.max(other.wall),
+            user: self.user.max(other.user),
+            sys: self.sys.max(other.sys),
+            max_rss: self.max_rss.max(other.max_rss),
+        }
+    }
+
+    /// The measurements as named values for display, e.g. `("wall", "1.25s")`.
+    pub fn to_metrics(self) -> Vec<(String, String)> {
+        vec![
+            ("wall".into(), format_duration(self.wall)),
+            ("user".into(), format_duration(self.user)),
+            ("sys".into(), format_duration(self.sys)),
+            ("max_rss".into(), format_bytes(self.max_rss)),
+        ]
+    }
+}
+
+/// Formats a duration with millisecond precision, e.g. `250ms` or `1.25s`.
+pub fn format_duration(d: Duration) -> String {
+    if d < Duration::from_secs(1) {
+        format!("{}ms", d.as_millis())
+    } else {
+        let s = format!("{:.3}", d.as_secs_f64());
+        format!("{}s", s.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+const UNITS: [(&str, u64); 4] = [
+    ("T", 1 << 40),
+    ("G", 1 << 30),
+    ("M", 1 << 20),
+    ("K", 1 << 10),
+];
+
+/// Formats a size in bytes with a binary unit, e.g. `512B`, `4K` or `1.5G`.
+pub fn format_bytes(bytes: u64) -> String {
+    UNITS
+        .iter()
+        .find(|(_, size)| bytes >= *size)
+        .map(|(unit, size)| {
+            let s = format!("{:.1}", bytes as f64 / *size as f64);
+            format!("{}{}", s.trim_end_matches(".0"), unit)
+        })
+        .unwrap_or_else(|| format!("{}B", bytes))
+}
+
+/// Parses a duration like `10s`, `500ms`, `2m` or `1h`. A plain number is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let seconds = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => {
+            return Err(format!(
+                "Unknown unit \"{}\" (expected ms, s, m or h)",
+                unit
+            ))
+        }
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("\"{}\" isn't a duration", s))?;
+
+    Duration::try_from_secs_f64(number * seconds)
+        .map_err(|_| format!("\"{}\" is too long a duration", s))
+}
+
+/// Parses a size like `2G`, `512M`, `100K` or `4096`, where units are powers of 1024.
+pub fn parse_bytes(s: &str) -> Result<u64, String> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let unit = unit.trim_end_matches(['B', 'b']).trim_end_matches('i');
+
+    let size = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        u => UNITS
+            .iter()
+            .find(|(name, _)| *name == u)
+            .map(|(_, size)| *size)
+            .ok_or_else(|| format!("Unknown unit \"{}\" (expected K, M, G or T)", unit))?,
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("\"{}\" isn't a size", s))?;
+
+    // u64::MAX rounds up to 2^64 as a float, so anything from there on doesn't fit.
+    let bytes = number * size as f64;
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(format!("\"{}\" is too large a size", s));
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1250)), "1.25s");
+        assert_eq!(format_duration(Duration::from_secs(3)), "3s");
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(4096), "4K");
+        assert_eq!(format_bytes(3 << 29), "1.5G");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_duration("10s"), Ok(Duration::from_secs(10)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1.5"), Ok(Duration::from_millis(1500)));
+        assert!(parse_duration("10 parsecs").is_err());
+        assert_eq!(
+            parse_duration("99999999999999999999h"),
+            Err("\"99999999999999999999h\" is too long a duration".into())
+        );
+        assert!(parse_duration(&"9".repeat(400)).is_err());
+
+        assert_eq!(parse_bytes("2G"), Ok(2 << 30));
+        assert_eq!(parse_bytes("512MiB"), Ok(512 << 20));
+        assert_eq!(parse_bytes("1.5k"), Ok(1536));
+        assert_eq!(parse_bytes("4096"), Ok(4096));
+        assert_eq!(
+            parse_bytes("16777216T"),
+            Err("\"16777216T\" is too large a size".into())
+        );
+        assert!(parse_bytes(&"9".repeat(400)).is_err());
+        assert_eq!(
+            parse_bytes("3X"),
+            Err("Unknown unit \"X\" (expected K, M, G or T)".into())
+        );
+    }
+}
@@ -3,6 +3,7 @@ use crate::task::cancellable_task::CancellableTask;
 use crate::task::map_cancellable_task::MapValueCancellableTask;
 use crate::threading::async_value::AsyncValue;
 
+#[allow(unused)]
 pub struct ResultCancellableTask<T, E, C>
 where
     T: Send + Sync + Clone + 'static,
@@ -19,6 +20,7 @@ where
     E: Send + Sync + Clone + 'static,
     C: CancellableTask<T>,
 {
+    #[allow(unused)]
     pub fn new(result: Result<C, E>) -> Self {
         match result {
             Ok(t) => {
@@ -0,0 +1,335 @@
+use crate::template::expression::Expression::*;
+use crate::template::expression::Token::*;
+use ibig::IBig;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+enum Token {
+    Number(IBig),
+    Identifier(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+}
+
+/// An integer arithmetic expression that can appear in a template placeholder, e.g. `X*4096+1`.
+///
+/// Supports `+`, `-`, `*`, `/` (truncating), `%`, unary `-`, parentheses, decimal and `0x` hex literals, the value being probed, and named variables.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Expression {
+    /// The number currently being probed.
+    Value,
+    Variable(String),
+    Literal(IBig),
+    Negate(Box<Expression>),
+    Binary(char, Box<Expression>, Box<Expression>),
+}
+
+/// The values that the identifiers in an `Expression` evaluate to.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Variables {
+    value: IBig,
+    named: BTreeMap<String, IBig>,
+}
+
+impl Variables {
+    /// Makes a set of variables with only the value being probed.
+    pub fn new(value: IBig) -> Self {
+        Self {
+            value,
+            named: BTreeMap::new(),
+        }
+    }
+
+    /// Adds (or replaces) a named variable.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<IBig>) -> Self {
+        self.named.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn value(&self) -> &IBig {
+        &self.value
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// `true` if `s` is a valid identifier, meaning it could be referenced in an expression.
+pub fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(is_identifier_start) && chars.all(is_identifier_char)
+}
+
+fn take_while(chars: &mut Peekable<Chars>, predicate: impl Fn(char) -> bool) -> String {
+    let mut ret = String::new();
+    while let Some(c) = chars.next_if(|c| predicate(*c)) {
+        ret.push(c);
+    }
+    ret
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut chars = s.chars().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() {
+            let literal = take_while(&mut chars, is_identifier_char);
+            let parsed = match literal.strip_prefix("0x") {
+                Some(hex) => IBig::from_str_radix(hex, 16),
+                None => IBig::from_str(&literal),
+            };
+            tokens.push(Number(
+                parsed.map_err(|_| format!("\"{}\" is not a valid number", literal))?,
+            ));
+        } else if is_identifier_start(c) {
+            tokens.push(Identifier(take_while(&mut chars, is_identifier_char)));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' | '%' => Operator(c),
+                '(' => OpenParen,
+                ')' => CloseParen,
+                _ => return Err(format!("unexpected character '{}'", c)),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+    value_name: Option<&'a str>,
+    variable_names: &'a [&'a str],
+}
+
+impl<'a> Parser<'a> {
+    fn binary_level(
+        &mut self,
+        operators: &[char],
+        mut next_level: impl FnMut(&mut Self) -> Result<Expression, String>,
+    ) -> Result<Expression, String> {
+        let mut lhs = next_level(self)?;
+
+        while let Some(Operator(op)) = self
+            .tokens
+            .next_if(|t| matches!(t, Operator(op) if operators.contains(op)))
+        {
+            let rhs = next_level(self)?;
+            lhs = Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        self.binary_level(&['+', '-'], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        self.binary_level(&['*', '/', '%'], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.tokens.next_if_eq(&Operator('-')).is_some() {
+            return Ok(Negate(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.tokens.next() {
+            Some(Number(n)) => Ok(Literal(n)),
+            Some(Identifier(id)) if Some(id.as_str()) == self.value_name => Ok(Value),
+            Some(Identifier(id)) if self.variable_names.contains(&id.as_str()) => Ok(Variable(id)),
+            Some(Identifier(id)) => Err(format!("unknown variable \"{}\"", id)),
+            Some(OpenParen) => {
+                let inner = self.sum()?;
+                match self.tokens.next() {
+                    Some(CloseParen) => Ok(inner),
+                    _ => Err("missing closing parenthesis".into()),
+                }
+            }
+            Some(CloseParen) => Err("unexpected ')'".into()),
+            Some(Operator(op)) => Err(format!("unexpected operator '{}'", op)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+}
+
+impl Expression {
+    /// Parses an expression, where the identifier `value_name` refers to the value being probed, and any of `variable_names` refer to named variables.
+    pub fn parse(
+        s: &str,
+        value_name: Option<&str>,
+        variable_names: &[&str],
+    ) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?.into_iter().peekable(),
+            value_name,
+            variable_names,
+        };
+
+        let ret = parser.sum()?;
+
+        match parser.tokens.next() {
+            None => Ok(ret),
+            Some(CloseParen) => Err("unexpected ')'".into()),
+            Some(_) => Err("expected an operator".into()),
+        }
+    }
+
+    /// `true` if `s` is made only of what an expression can contain, and names the value or a named variable but nothing else, i.e. it's meant as an expression whether or not it parses.
+    pub fn looks_like(s: &str, value_name: Option<&str>, variable_names: &[&str]) -> bool {
+        let tokens = match tokenize(s) {
+            Ok(tokens) => tokens,
+            Err(_) => return false,
+        };
+        let mut identifiers = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Identifier(id) => Some(id.as_str()),
+                _ => None,
+            })
+            .peekable();
+
+        identifiers.peek().is_some()
+            && identifiers.all(|id| Some(id) == value_name || variable_names.contains(&id))
+    }
+
+    /// `true` if the value being probed appears anywhere in the expression.
+    pub fn references_value(&self) -> bool {
+        self.any(&|e| *e == Value)
+    }
+
+    /// `true` if the named variable appears anywhere in the expression.
+    pub fn references_variable(&self, name: &str) -> bool {
+        self.any(&|e| matches!(e, Variable(n) if n == name))
+    }
+
+    fn any(&self, predicate: &impl Fn(&Expression) -> bool) -> bool {
+        predicate(self)
+            || match self {
+                Value | Variable(_) | Literal(_) => false,
+                Negate(e) => e.any(predicate),
+                Binary(_, lhs, rhs) => lhs.any(predicate) || rhs.any(predicate),
+            }
+    }
+
+    /// Evaluates the expression with the given variables.
+    ///
+    /// Fails if a division by zero happens, or if a referenced variable isn't given.
+    pub fn evaluate(&self, variables: &Variables) -> Result<IBig, String> {
+        match self {
+            Value => Ok(variables.value.clone()),
+            Variable(name) => variables
+                .named
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("\"{}\" is not available here", name)),
+            Literal(n) => Ok(n.clone()),
+            Negate(e) => Ok(-e.evaluate(variables)?),
+            Binary(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(variables)?;
+                let rhs = rhs.evaluate(variables)?;
+
+                if (*op == '/' || *op == '%') && rhs == IBig::from(0) {
+                    return Err("division by zero".into());
+                }
+
+                Ok(match op {
+                    '+' => lhs + rhs,
+                    '-' => lhs - rhs,
+                    '*' => lhs * rhs,
+                    '/' => lhs / rhs,
+                    '%' => lhs % rhs,
+                    _ => unreachable!("the parser only produces known operators"),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::ib;
+
+    fn eval(s: &str, x: i64) -> Result<IBig, String> {
+        Expression::parse(s, Some("X"), &["Y"])?.evaluate(&Variables::new(ib(x)).with("Y", 2))
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(eval("X", 5), Ok(ib(5)));
+        assert_eq!(eval("X*4096", 5), Ok(ib(20480)));
+        assert_eq!(eval("X + 1", 5), Ok(ib(6)));
+        assert_eq!(eval("1 + X * 2", 5), Ok(ib(11)));
+        assert_eq!(eval("(1 + X) * 2", 5), Ok(ib(12)));
+        assert_eq!(eval("X - 2 - 1", 5), Ok(ib(2)));
+        assert_eq!(eval("-X % 3", 5), Ok(ib(-2)));
+        assert_eq!(eval("X / 2", 5), Ok(ib(2)));
+        assert_eq!(eval("0x10 * X", 2), Ok(ib(32)));
+        assert_eq!(eval("X * Y", 5), Ok(ib(10)));
+    }
+
+    #[test]
+    fn test_missing_variable() {
+        assert_eq!(
+            Expression::parse("X + Y", Some("X"), &["Y"])
+                .unwrap()
+                .evaluate(&Variables::new(ib(5))),
+            Err("\"Y\" is not available here".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(eval("X*", 5), Err("unexpected end of expression".into()));
+        assert_eq!(eval("X Y", 5), Err("expected an operator".into()));
+        assert_eq!(eval("Z + X", 5), Err("unknown variable \"Z\"".into()));
+        assert_eq!(eval("(X + 1", 5), Err("missing closing parenthesis".into()));
+        assert_eq!(eval("X + 1)", 5), Err("unexpected ')'".into()));
+        assert_eq!(eval("X ^ 2", 5), Err("unexpected character '^'".into()));
+        assert_eq!(
+            eval("12ab", 5),
+            Err("\"12ab\" is not a valid number".into())
+        );
+        assert_eq!(eval("", 5), Err("unexpected end of expression".into()));
+    }
+
+    #[test]
+    fn test_looks_like() {
+        assert!(Expression::looks_like("X**2", Some("X"), &[]));
+        assert!(Expression::looks_like("(N+1", Some("X"), &["N"]));
+        assert!(!Expression::looks_like("X+Y", Some("X"), &[]));
+        assert!(!Expression::looks_like("X#*/", Some("X"), &[]));
+        assert!(!Expression::looks_like("1+", Some("X"), &[]));
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(eval("10 / (X - 5)", 5), Err("division by zero".into()));
+        assert_eq!(eval("10 % (X - 5)", 6), Ok(ib(0)));
+    }
+
+    #[test]
+    fn test_is_identifier() {
+        assert!(is_identifier("X"));
+        assert!(is_identifier("_x1"));
+        assert!(!is_identifier("1x"));
+        assert!(!is_identifier(""));
+        assert!(!is_identifier("$X"));
+    }
+}
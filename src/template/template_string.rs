@@ -0,0 +1,440 @@
+use crate::template::expression::{is_identifier, Expression, Variables};
+use crate::template::template_string::TemplatePiece::*;
+use ibig::IBig;
+use std::fmt::{Display, Formatter};
+
+/// How a placeholder's number is written out, e.g. the `08x` in `${X:08x}`.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct NumberFormat {
+    radix: u32,
+    uppercase: bool,
+    width: usize,
+    zero_pad: bool,
+}
+
+impl NumberFormat {
+    /// Parses a format spec of the form `[0][width][d|x|X|o|b]`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let digits_end = spec
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(spec.len());
+        let (width, radix) = spec.split_at(digits_end);
+
+        let (radix, uppercase) = match radix {
+            "" | "d" => (10, false),
+            "x" => (16, false),
+            "X" => (16, true),
+            "o" => (8, false),
+            "b" => (2, false),
+            _ => return Err(format!("unknown format \"{}\"", spec)),
+        };
+
+        Ok(Self {
+            radix,
+            uppercase,
+            width: if width.is_empty() {
+                0
+            } else {
+                width
+                    .parse()
+                    .map_err(|_| format!("format width \"{}\" is too large", width))?
+            },
+            zero_pad: width.starts_with('0'),
+        })
+    }
+
+    pub fn format(&self, n: &IBig) -> String {
+        let digits = match (self.radix, self.uppercase) {
+            (16, false) => format!("{:x}", n),
+            (16, true) => format!("{:X}", n),
+            (8, _) => format!("{:o}", n),
+            (2, _) => format!("{:b}", n),
+            _ => n.to_string(),
+        };
+
+        let (sign, digits) = match digits.strip_prefix('-') {
+            Some(d) => ("-", d),
+            None => ("", digits.as_str()),
+        };
+
+        let pad_len = self.width.saturating_sub(sign.len() + digits.len());
+
+        if self.zero_pad {
+            format!("{}{}{}", sign, "0".repeat(pad_len), digits)
+        } else {
+            format!("{}{}{}", " ".repeat(pad_len), sign, digits)
+        }
+    }
+}
+
+/// A `${...}` placeholder or bare substitution string within a template.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Placeholder {
+    source: String,
+    expression: Expression,
+    format: NumberFormat,
+}
+
+impl Placeholder {
+    /// The text of the placeholder as it appeared in the template.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// `true` if the placeholder is the bare substitution string (e.g. `$X`) instead of a `${...}` expression.
+    pub fn is_bare(&self) -> bool {
+        !self.source.starts_with("${")
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TemplatePiece {
+    Literal(String),
+    Substitution(Placeholder),
+}
+
+/// An error found while parsing a template.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TemplateError {
+    /// The text of the offending placeholder.
+    pub source: String,
+    pub message: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("{}: {}", self.source, self.message))
+    }
+}
+
+/// A string with placeholders that are substituted with (a function of) the number being probed.
+///
+/// Placeholders are either the bare substitution string (e.g. `$X`), or `${EXPR}`/`${EXPR:FORMAT}` where `EXPR` is an arithmetic expression over the substitution string's name (e.g. `${X*4096}`, `${X:08}`, `${X+1:x}`).
+///
+/// Expressions can also reference a set of named variables given when parsing (e.g. `${PARASECT_WORKER_ID}`).
+///
+/// A `${...}` that isn't an expression over the substitution string's name or a named variable (e.g. `${HOME}` or `${HOME:-$X}`) is left as-is, so it can still be expanded by a shell.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TemplateString {
+    pieces: Vec<TemplatePiece>,
+}
+
+/// The name expressions use to refer to the value, e.g. `X` for `$X`, if the substitution string has one.
+pub fn value_name(substitution_string: &str) -> Option<&str> {
+    let name = substitution_string.strip_prefix('$')?;
+    let name = name
+        .strip_prefix('{')
+        .and_then(|n| n.strip_suffix('}'))
+        .unwrap_or(name);
+
+    Some(name).filter(|n| is_identifier(n))
+}
+
+/// Parses the body of a `${...}`, or returns `None` if it isn't a placeholder, i.e. isn't an expression that references the value or a named variable.
+///
+/// Fails if the body is meant as such an expression but doesn't parse, e.g. `${X**2}`, or if the expression is followed by something that looks like a format but isn't one, e.g. `${X:q}`. Anything else (e.g. `${X:-1}` or `${X#*/}`) is left to the shell.
+fn parse_placeholder(
+    source: &str,
+    body: &str,
+    value_name: Option<&str>,
+    variable_names: &[&str],
+) -> Result<Option<Placeholder>, String> {
+    let (source_expression, format) = body.split_once(':').unwrap_or((body, ""));
+
+    let expression = match Expression::parse(source_expression, value_name, variable_names) {
+        Ok(e)
+            if e.references_value() || variable_names.iter().any(|v| e.references_variable(v)) =>
+        {
+            e
+        }
+        Err(e) if Expression::looks_like(source_expression, value_name, variable_names) => {
+            return Err(e)
+        }
+        _ => return Ok(None),
+    };
+    if !format.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Ok(None);
+    }
+
+    Ok(Some(Placeholder {
+        source: source.to_string(),
+        expression,
+        format: NumberFormat::parse(format)?,
+    }))
+}
+
+impl TemplateString {
+    /// Parses the placeholders out of `s`.
+    ///
+    /// Fails if a `${...}` placeholder referencing the value or one of `variable_names` has a malformed expression or format.
+    pub fn parse(
+        s: &str,
+        substitution_string: &str,
+        variable_names: &[&str],
+    ) -> Result<Self, TemplateError> {
+        let value_name = value_name(substitution_string);
+
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut rest = s;
+
+        while !rest.is_empty() {
+            let braced = match rest
+                .strip_prefix("${")
+                .and_then(|r| r.find('}').map(|end| &r[..end]))
+            {
+                Some(body) => {
+                    let source = &rest[..body.len() + 3];
+                    parse_placeholder(source, body, value_name, variable_names).map_err(
+                        |message| TemplateError {
+                            source: source.to_string(),
+                            message,
+                        },
+                    )?
+                }
+                None => None,
+            };
+
+            let (piece, len) = if let Some(placeholder) = braced {
+                let len = placeholder.source.len();
+                (Some(placeholder), len)
+            } else if !substitution_string.is_empty() && rest.starts_with(substitution_string) {
+                let placeholder = Placeholder {
+                    source: substitution_string.to_string(),
+                    expression: Expression::Value,
+                    format: NumberFormat::default(),
+                };
+                (Some(placeholder), substitution_string.len())
+            } else {
+                let c = rest.chars().next().unwrap();
+                literal.push(c);
+                (None, c.len_utf8())
+            };
+
+            if let Some(p) = piece {
+                if !literal.is_empty() {
+                    pieces.push(Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(Substitution(p));
+            }
+
+            rest = &rest[len..];
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Literal(literal));
+        }
+
+        Ok(Self { pieces })
+    }
+
+    pub fn pieces(&self) -> &[TemplatePiece] {
+        &self.pieces
+    }
+
+    /// `true` if at least one placeholder in the template references the value being probed.
+    pub fn references_value(&self) -> bool {
+        self.pieces
+            .iter()
+            .any(|p| matches!(p, Substitution(p) if p.expression.references_value()))
+    }
+
+    /// `true` if at least one placeholder in the template references the given named variable.
+    pub fn references_variable(&self, name: &str) -> bool {
+        self.pieces
+            .iter()
+            .any(|p| matches!(p, Substitution(p) if p.expression.references_variable(name)))
+    }
+
+    /// Substitutes every placeholder using the given variables.
+    ///
+    /// Fails if evaluating a placeholder fails, e.g. when dividing by zero.
+    pub fn render(&self, variables: &Variables) -> Result<String, String> {
+        self.render_inner(variables, false)
+    }
+
+    /// Substitutes the `${...}` placeholders using the given variables, leaving bare substitution strings as-is.
+    ///
+    /// Used when the bare substitution string is expanded by something else, like a shell variable.
+    pub fn render_expressions(&self, variables: &Variables) -> Result<String, String> {
+        self.render_inner(variables, true)
+    }
+
+    fn render_inner(&self, variables: &Variables, keep_bare: bool) -> Result<String, String> {
+        let mut ret = String::new();
+
+        for piece in self.pieces.iter() {
+            match piece {
+                Literal(s) => ret.push_str(s),
+                Substitution(p) if keep_bare && p.is_bare() => ret.push_str(&p.source),
+                Substitution(p) => {
+                    let n = p
+                        .expression
+                        .evaluate(variables)
+                        .map_err(|e| format!("{}: {}", p.source, e))?;
+                    ret.push_str(&p.format.format(&n));
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::ib;
+
+    fn vars(x: i64) -> Variables {
+        Variables::new(ib(x))
+    }
+
+    fn render(s: &str, x: i64) -> String {
+        TemplateString::parse(s, "$X", &[])
+            .unwrap()
+            .render(&vars(x))
+            .unwrap()
+    }
+
+    fn parse_err(s: &str) -> TemplateError {
+        TemplateString::parse(s, "$X", &[]).unwrap_err()
+    }
+
+    #[test]
+    fn test_render_plain() {
+        assert_eq!(render("--flag=$X", 69), "--flag=69");
+        assert_eq!(render("$X-$X", 69), "69-69");
+        assert_eq!(render("nothing", 69), "nothing");
+        assert_eq!(render("", 69), "");
+    }
+
+    #[test]
+    fn test_render_expressions() {
+        assert_eq!(render("${X*4096}", 2), "8192");
+        assert_eq!(render("--rev=${X+1}", 2), "--rev=3");
+        assert_eq!(render("${X:x}", 255), "ff");
+        assert_eq!(render("${X:X}", 255), "FF");
+        assert_eq!(render("build-${X:06}", 123), "build-000123");
+        assert_eq!(render("${X:6}", 123), "   123");
+        assert_eq!(render("${X:04x}", 255), "00ff");
+        assert_eq!(render("${X:b}", 5), "101");
+        assert_eq!(render("${X:o}", 8), "10");
+        assert_eq!(render("${X:05}", -12), "-0012");
+    }
+
+    #[test]
+    fn test_unrelated_braces_are_literal() {
+        assert_eq!(render("${HOME}/$X", 5), "${HOME}/5");
+        assert_eq!(render("${X", 5), "${X");
+        assert_eq!(render("{X}", 5), "{X}");
+        assert_eq!(render("${1}", 5), "${1}");
+    }
+
+    #[test]
+    fn test_shell_expansions_are_literal() {
+        assert_eq!(render("${HOME:-$X}", 5), "${HOME:-5}");
+        assert_eq!(render("${X:-1}", 5), "${X:-1}");
+        assert_eq!(render("${X+Y}", 5), "${X+Y}");
+        assert_eq!(render("${X#*/}", 5), "${X#*/}");
+
+        let t = TemplateString::parse("${HOME:-$X}", "$X", &[]).unwrap();
+        assert_eq!(
+            t.render_expressions(&vars(5)),
+            Ok("${HOME:-$X}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_custom_substitution_string() {
+        let t = TemplateString::parse("${N*2}/$N/$X", "$N", &[]).unwrap();
+        assert_eq!(t.render(&vars(3)), Ok("6/3/$X".to_string()));
+
+        let t = TemplateString::parse("@@/${X}", "@@", &[]).unwrap();
+        assert_eq!(t.render(&vars(3)), Ok("3/${X}".to_string()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_err("--size=${X*2:q}"),
+            TemplateError {
+                source: "${X*2:q}".into(),
+                message: "unknown format \"q\"".into()
+            }
+        );
+        assert_eq!(
+            parse_err("echo ${X**2}"),
+            TemplateError {
+                source: "${X**2}".into(),
+                message: "unexpected operator '*'".into()
+            }
+        );
+        assert_eq!(
+            parse_err("${(X+1}"),
+            TemplateError {
+                source: "${(X+1}".into(),
+                message: "missing closing parenthesis".into()
+            }
+        );
+        assert_eq!(
+            parse_err("${X:08z}"),
+            TemplateError {
+                source: "${X:08z}".into(),
+                message: "unknown format \"08z\"".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_render_expressions_keeps_bare() {
+        let t = TemplateString::parse("echo \"$X\" ${X*2}", "$X", &[]).unwrap();
+        assert_eq!(
+            t.render_expressions(&vars(3)),
+            Ok("echo \"$X\" 6".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_error() {
+        let t = TemplateString::parse("${100/X}", "$X", &[]).unwrap();
+        assert_eq!(t.render(&vars(0)), Err("${100/X}: division by zero".into()));
+    }
+
+    #[test]
+    fn test_references_value() {
+        assert!(TemplateString::parse("a$X", "$X", &[])
+            .unwrap()
+            .references_value());
+        assert!(TemplateString::parse("${X:x}", "$X", &[])
+            .unwrap()
+            .references_value());
+        assert!(!TemplateString::parse("${HOME}", "$X", &[])
+            .unwrap()
+            .references_value());
+    }
+
+    #[test]
+    fn test_named_variables() {
+        let t = TemplateString::parse("${W}-${X+W}-${HOME}-$W", "$X", &["W"]).unwrap();
+        assert_eq!(
+            t.render(&vars(3).with("W", 2)),
+            Ok("2-5-${HOME}-$W".to_string())
+        );
+        assert!(t.references_value());
+        assert!(t.references_variable("W"));
+
+        let t = TemplateString::parse("${W}", "$X", &["W"]).unwrap();
+        assert_eq!(t.pieces().len(), 1);
+        assert!(!t.references_value());
+        assert!(!t.references_variable("V"));
+    }
+
+    #[test]
+    fn test_value_name() {
+        assert_eq!(value_name("$X"), Some("X"));
+        assert_eq!(value_name("${REV}"), Some("REV"));
+        assert_eq!(value_name("@@"), None);
+        assert_eq!(value_name("$1"), None);
+    }
+}
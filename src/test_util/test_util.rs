@@ -2,8 +2,10 @@
 pub mod test_util {
     use crate::range::numeric_range::NumericRange;
     use ibig::{IBig, UBig};
-    use std::thread;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
     use std::time::{Duration, Instant};
+    use std::{env, fs, process, thread};
 
     pub fn ib(n: impl Into<IBig>) -> IBig {
         n.into()
@@ -35,4 +37,38 @@ pub mod test_util {
             thread::sleep(Duration::from_millis(5));
         }
     }
+
+    /// An empty directory for a test to work in, removed along with everything in it when dropped.
+    ///
+    /// `name` must be unique among the tests, since they run in parallel.
+    pub struct TestDir(PathBuf);
+
+    impl TestDir {
+        pub fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("parasect-test-{}-{}", process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Deref for TestDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TestDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
 }
@@ -0,0 +1,472 @@
+use crate::parasect::event::Event::*;
+use crate::parasect::event::{Event, ProbeNote};
+use crate::parasect::types::ParasectPayloadAnswer;
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::worker::PointCompletionMessageType::*;
+use crate::parasect::worker::WorkerMessage;
+use crate::range::alignment::Alignment;
+use crate::range::numeric_range::NumericRange;
+use crate::util::json::{
+    ibig_from_json, ibig_to_json, range_from_json, range_to_json, result_from_json, result_to_json,
+};
+use crossbeam_channel::{bounded, select, Receiver, Sender};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{FromRawFd, RawFd};
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// The version of the event log's format, which goes up whenever a record changes in a way that could trip up its readers.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Where `--events` writes to.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum EventSink {
+    Stdout,
+    File(PathBuf),
+    /// A file descriptor parasect was started with, e.g. 3 for `3>events.jsonl`.
+    Fd(RawFd),
+}
+
+impl EventSink {
+    /// Parses `jsonl` (for stdout), `jsonl:PATH` or `jsonl:fd:N`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (format, to) = s.split_once(':').unwrap_or((s, ""));
+        if format != "jsonl" {
+            return Err(format!(
+                "Unknown format \"{}\" (expected jsonl, jsonl:PATH or jsonl:fd:N)",
+                format
+            ));
+        }
+
+        match (to, to.strip_prefix("fd:")) {
+            ("", _) => Ok(Self::Stdout),
+            (_, Some(fd)) => fd
+                .parse()
+                .map(Self::Fd)
+                .map_err(|_| format!("\"{}\" isn't a file descriptor", fd)),
+            (path, None) => Ok(Self::File(path.into())),
+        }
+    }
+
+    /// Creates the file, or takes over the file descriptor.
+    ///
+    /// The descriptor is moved to a close-on-exec copy so the commands parasect runs don't inherit it, and closed unless it's stdin, stdout or stderr, which parasect still needs.
+    pub fn open(&self) -> Result<Box<dyn Write + Send>, String> {
+        match self {
+            Self::Stdout => Ok(Box::new(io::stdout())),
+            Self::File(path) => File::create(path)
+                .map(|f| Box::new(f) as Box<dyn Write + Send>)
+                .map_err(|e| format!("Failed to create \"{}\": {}", path.display(), e)),
+            Self::Fd(fd) => {
+                let copy = unsafe { libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, 0) };
+                if copy < 0 {
+                    return Err(match io::Error::last_os_error() {
+                        e if e.raw_os_error() == Some(libc::EBADF) => {
+                            format!("File descriptor {} isn't open", fd)
+                        }
+                        e => format!("Failed to take over file descriptor {}: {}", fd, e),
+                    });
+                }
+                if *fd > libc::STDERR_FILENO {
+                    unsafe { libc::close(*fd) };
+                }
+                Ok(Box::new(unsafe { File::from_raw_fd(copy) }))
+            }
+        }
+    }
+}
+
+fn answer_to_json(answer: &ParasectPayloadAnswer) -> Value {
+    match answer {
+        Good => json!("good"),
+        Bad => json!("bad"),
+    }
+}
+
+/// An event as a JSON object, without the fields every record has.
+pub fn event_to_json(event: &Event) -> Value {
+    match event {
+        WorkerMessageSent(wm) => {
+            let (kind, result) = match &wm.msg_type {
+                Started => ("started", Value::Null),
+                Completed(result, annotations) => {
+                    ("completed", result_to_json(result, annotations))
+                }
+                Cancelled => ("cancelled", Value::Null),
+            };
+            json!({
+                "event": "worker_message",
+                "worker": wm.thread_id,
+                "point": ibig_to_json(&wm.point),
+                "left": range_to_json(&wm.left),
+                "right": range_to_json(&wm.right),
+                "type": kind,
+                "result": result,
+            })
+        }
+        RangeInvalidated(range, answer) => json!({
+            "event": "range_invalidated",
+            "range": range_to_json(range),
+            "answer": answer_to_json(answer),
+        }),
+        ParasectCancelled(reason) => json!({
+            "event": "parasect_cancelled",
+            "reason": reason,
+        }),
+        ProbeNoted(note) => json!({
+            "event": "probe_noted",
+            "worker": note.worker_id,
+            "point": ibig_to_json(&note.point),
+            "source": note.source,
+            "answer": note.answer.as_ref().map(answer_to_json),
+            "detail": note.detail,
+        }),
+    }
+}
+
+fn answer_from_json(value: &Value) -> Result<ParasectPayloadAnswer, String> {
+    match value.as_str() {
+        Some("good") => Ok(Good),
+        Some("bad") => Ok(Bad),
+        _ => Err(format!("Expected \"good\" or \"bad\" (got {})", value)),
+    }
+}
+
+/// Reads an event written by `event_to_json`, ignoring any other fields.
+pub fn event_from_json(value: &Value) -> Result<Event, String> {
+    let field = |name: &str| value.get(name).unwrap_or(&Value::Null);
+    let string = |name: &str| {
+        field(name)
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| format!("Expected \"{}\" to be a string (got {})", name, field(name)))
+    };
+    let worker = || {
+        field("worker")
+            .as_u64()
+            .map(|w| w as usize)
+            .ok_or_else(|| format!("Expected a worker (got {})", field("worker")))
+    };
+
+    match field("event").as_str() {
+        Some("worker_message") => Ok(WorkerMessageSent(WorkerMessage {
+            thread_id: worker()?,
+            point: ibig_from_json(field("point"))?,
+            left: range_from_json(field("left"))?,
+            right: range_from_json(field("right"))?,
+            msg_type: match field("type").as_str() {
+                Some("started") => Started,
+                Some("completed") => {
+                    let (result, annotations) = result_from_json(field("result"))?;
+                    Completed(result, annotations)
+                }
+                Some("cancelled") => Cancelled,
+                _ => return Err(format!("Unknown type {}", field("type"))),
+            },
+        })),
+        Some("range_invalidated") => Ok(RangeInvalidated(
+            range_from_json(field("range"))?,
+            answer_from_json(field("answer"))?,
+        )),
+        Some("parasect_cancelled") => Ok(ParasectCancelled(string("reason")?)),
+        Some("probe_noted") => Ok(ProbeNoted(ProbeNote {
+            worker_id: worker()?,
+            point: ibig_from_json(field("point"))?,
+            source: string("source")?,
+            answer: match field("answer") {
+                Value::Null => None,
+                answer => Some(answer_from_json(answer)?),
+            },
+            detail: string("detail")?,
+        })),
+        _ => Err(format!("Unknown event {}", field("event"))),
+    }
+}
+
+/// The first record of the log, which describes the search so that it can be followed without the command line.
+pub fn search_started(
+    range: &NumericRange,
+    alignment: &Alignment,
+    command: &[String],
+    substitution_string: &str,
+) -> Value {
+    json!({
+        "event": "search_started",
+        "range": range_to_json(range),
+        "step": ibig_to_json(&alignment.step()),
+        "offset": ibig_to_json(&alignment.offset()),
+        "command": command,
+        "substitution_string": substitution_string,
+    })
+}
+
+/// Adds the fields every record has: the schema version, the wall-clock time in seconds since the Unix epoch, and the seconds since the log started.
+fn record(mut event: Value, started: Instant) -> Value {
+    if let Value::Object(fields) = &mut event {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        fields.insert("schema".into(), json!(SCHEMA_VERSION));
+        fields.insert("time".into(), json!(time.as_secs_f64()));
+        fields.insert("elapsed".into(), json!(started.elapsed().as_secs_f64()));
+    }
+    event
+}
+
+/// Writes every event to a stream as one line of JSON each, and passes it on to the interface.
+///
+/// Each line is flushed as soon as it's written, so the log can be followed while the search runs. If writing fails, the rest of the events are only passed on.
+pub struct EventLog {
+    done: Sender<()>,
+    thread: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl EventLog {
+    /// Writes `header` right away, and then every event that enters `events` until `finish()`.
+    pub fn start(
+        mut writer: Box<dyn Write + Send>,
+        header: Value,
+        events: Receiver<Event>,
+        forward: Sender<Event>,
+    ) -> Self {
+        let (done, done_receiver) = bounded(1);
+
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let mut write = |value: Value| {
+                writeln!(writer, "{}", record(value, started)).and_then(|_| writer.flush())
+            };
+
+            let mut result = write(header);
+            let mut handle = |event: Event| {
+                if result.is_ok() {
+                    result = write(event_to_json(&event));
+                }
+                let _ = forward.send(event);
+            };
+
+            loop {
+                select! {
+                    recv(events) -> event => match event {
+                        Ok(event) => handle(event),
+                        Err(_) => break,
+                    },
+                    recv(done_receiver) -> _ => {
+                        events.try_iter().for_each(&mut handle);
+                        break;
+                    }
+                }
+            }
+
+            result.map_err(|e| format!("Failed to write the event log: {}", e))
+        });
+
+        Self {
+            done,
+            thread: Some(thread),
+        }
+    }
+
+    /// Writes the events that were sent so far, and stops. Returns the error that stopped the log from being written, if any.
+    pub fn finish(mut self) -> Result<(), String> {
+        let _ = self.done.send(());
+        self.thread.take().unwrap().join().unwrap()
+    }
+}
+
+impl Drop for EventLog {
+    fn drop(&mut self) {
+        let _ = self.done.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parasect::types::Annotations;
+    use crate::parasect::types::ParasectPayloadResult::Continue;
+    use crate::test_util::test_util::test_util::{empty, ib, r};
+    use crossbeam_channel::unbounded;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_sink() {
+        assert_eq!(EventSink::parse("jsonl"), Ok(EventSink::Stdout));
+        assert_eq!(
+            EventSink::parse("jsonl:out/events.jsonl"),
+            Ok(EventSink::File("out/events.jsonl".into()))
+        );
+        assert_eq!(EventSink::parse("jsonl:fd:3"), Ok(EventSink::Fd(3)));
+        assert_eq!(
+            EventSink::parse("jsonl:fd:x"),
+            Err("\"x\" isn't a file descriptor".into())
+        );
+        assert_eq!(
+            EventSink::parse("csv:a"),
+            Err("Unknown format \"csv\" (expected jsonl, jsonl:PATH or jsonl:fd:N)".into())
+        );
+    }
+
+    #[test]
+    fn test_open_fd() {
+        let mut fds = [0; 2];
+        // close-on-exec, so that commands other tests run can't hold on to it either
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        let [read, write] = fds;
+
+        let mut sink = EventSink::Fd(write).open().unwrap();
+        sink.write_all(b"event\n").unwrap();
+        drop(sink);
+
+        // the read only ends if no copy of the write end is left open
+        let mut out = String::new();
+        unsafe { File::from_raw_fd(read) }
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, "event\n");
+
+        drop(EventSink::Fd(libc::STDERR_FILENO).open().unwrap());
+        assert!(unsafe { libc::fcntl(libc::STDERR_FILENO, libc::F_GETFD) } >= 0);
+
+        assert_eq!(
+            EventSink::Fd(RawFd::MAX).open().err(),
+            Some(format!("File descriptor {} isn't open", RawFd::MAX))
+        );
+    }
+
+    #[test]
+    fn test_event_to_json() {
+        assert_eq!(
+            event_to_json(&WorkerMessageSent(WorkerMessage {
+                thread_id: 2,
+                point: ib(4),
+                left: r(1, 3),
+                right: empty(),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
+            })),
+            json!({
+                "event": "worker_message",
+                "worker": 2,
+                "point": 4,
+                "left": [1, 3],
+                "right": null,
+                "type": "completed",
+                "result": {"verdict": "bad", "detail": null, "annotations": null},
+            })
+        );
+        assert_eq!(
+            event_to_json(&RangeInvalidated(r(4, 9), Bad)),
+            json!({"event": "range_invalidated", "range": [4, 9], "answer": "bad"})
+        );
+        assert_eq!(
+            event_to_json(&ParasectCancelled("nope".into())),
+            json!({"event": "parasect_cancelled", "reason": "nope"})
+        );
+        assert_eq!(
+            event_to_json(&ProbeNoted(ProbeNote {
+                worker_id: 2,
+                point: ib(4),
+                source: "check lint".into(),
+                answer: None,
+                detail: "cancelled".into(),
+            })),
+            json!({
+                "event": "probe_noted",
+                "worker": 2,
+                "point": 4,
+                "source": "check lint",
+                "answer": null,
+                "detail": "cancelled",
+            })
+        );
+    }
+
+    #[test]
+    fn test_event_roundtrip() {
+        let events = [
+            WorkerMessageSent(WorkerMessage {
+                thread_id: 2,
+                point: ib(4),
+                left: r(1, 3),
+                right: empty(),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
+            }),
+            WorkerMessageSent(WorkerMessage {
+                thread_id: 0,
+                point: ib(1) << 70,
+                left: empty(),
+                right: r(5, 7),
+                msg_type: Started,
+            }),
+            RangeInvalidated(r(4, 9), Good),
+            ParasectCancelled("nope".into()),
+            ProbeNoted(ProbeNote {
+                worker_id: 2,
+                point: ib(4),
+                source: "setup".into(),
+                answer: Some(Bad),
+                detail: "exit status: 1".into(),
+            }),
+        ];
+
+        for event in events {
+            let record = record(event_to_json(&event), Instant::now());
+            assert_eq!(event_from_json(&record), Ok(event));
+        }
+        assert_eq!(
+            event_from_json(&json!({"event": "range_invalidated", "range": [1, 2]})),
+            Err("Expected \"good\" or \"bad\" (got null)".into())
+        );
+        assert_eq!(
+            event_from_json(&json!({"event": "nope"})),
+            Err("Unknown event \"nope\"".into())
+        );
+    }
+
+    #[test]
+    fn test_event_log() {
+        let buffer = SharedBuffer::default();
+        let (sender, receiver) = unbounded();
+        let (forward, forwarded) = unbounded();
+        let header = search_started(&r(0, 10), &Alignment::default(), &["foo".into()], "$X");
+
+        let log = EventLog::start(Box::new(buffer.clone()), header, receiver, forward);
+        for i in 0..100 {
+            sender.send(ParasectCancelled(i.to_string())).unwrap();
+        }
+        assert_eq!(log.finish(), Ok(()));
+
+        let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let records = written
+            .lines()
+            .map(|l| serde_json::from_str::<Value>(l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 101);
+        assert_eq!(records[0]["event"], json!("search_started"));
+        assert_eq!(records[0]["range"], json!([0, 10]));
+        assert_eq!(records[0]["schema"], json!(SCHEMA_VERSION));
+        assert_eq!(records[100]["reason"], json!("99"));
+        assert!(records[100]["elapsed"].as_f64().unwrap() >= 0.0);
+        assert_eq!(forwarded.try_iter().count(), 100);
+    }
+}
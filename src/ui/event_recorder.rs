@@ -0,0 +1,91 @@
+use crate::parasect::event::Event;
+use crossbeam_channel::{bounded, select, Receiver, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Keeps every event, with when it happened, and passes it on to the interface, e.g. for a report once the search is done.
+pub struct EventRecorder {
+    done: Sender<()>,
+    thread: Option<JoinHandle<Vec<(Duration, Event)>>>,
+}
+
+impl EventRecorder {
+    /// Keeps every event that enters `events` until `finish()`, along with how long after the start it arrived.
+    pub fn start(events: Receiver<Event>, forward: Sender<Event>) -> Self {
+        let (done, done_receiver) = bounded(1);
+
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let mut recorded = Vec::new();
+            let mut handle = |event: Event| {
+                recorded.push((started.elapsed(), event.clone()));
+                let _ = forward.send(event);
+            };
+
+            loop {
+                select! {
+                    recv(events) -> event => match event {
+                        Ok(event) => handle(event),
+                        Err(_) => break,
+                    },
+                    recv(done_receiver) -> _ => {
+                        events.try_iter().for_each(&mut handle);
+                        break;
+                    }
+                }
+            }
+
+            recorded
+        });
+
+        Self {
+            done,
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops, and returns every event that was sent so far, in order.
+    pub fn finish(mut self) -> Vec<(Duration, Event)> {
+        let _ = self.done.send(());
+        self.thread.take().unwrap().join().unwrap()
+    }
+}
+
+impl Drop for EventRecorder {
+    fn drop(&mut self) {
+        let _ = self.done.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parasect::event::Event::{ParasectCancelled, RangeInvalidated};
+    use crate::parasect::types::ParasectPayloadAnswer::Good;
+    use crate::test_util::test_util::test_util::r;
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn test_event_recorder() {
+        let (sender, events) = unbounded();
+        let (forward, forwarded) = unbounded();
+        let recorder = EventRecorder::start(events, forward);
+
+        sender.send(RangeInvalidated(r(0, 4), Good)).unwrap();
+        sender.send(ParasectCancelled("no".into())).unwrap();
+        let recorded = recorder.finish();
+
+        assert_eq!(
+            recorded.into_iter().map(|(_, e)| e).collect::<Vec<_>>(),
+            vec![
+                RangeInvalidated(r(0, 4), Good),
+                ParasectCancelled("no".into())
+            ]
+        );
+        assert_eq!(forwarded.try_iter().count(), 2);
+    }
+}
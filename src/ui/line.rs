@@ -129,6 +129,8 @@ impl Line {
     pub fn truncate(&self, width: usize) -> Self {
         if self.len() <= width {
             return self.clone();
+        } else if width == 0 {
+            return Self::new(Vec::new());
         }
 
         let mut new_segments = Vec::new();
@@ -137,7 +139,9 @@ impl Line {
         for seg in self.segments.iter() {
             if total_len + seg.len() >= width {
                 let remaining = width - total_len;
-                new_segments.push(seg.map_content(|c| c[..remaining - 1].to_string() + "…"));
+                new_segments.push(seg.map_content(|c| {
+                    c.graphemes(true).take(remaining - 1).collect::<String>() + "…"
+                }));
                 break;
             }
 
@@ -389,6 +393,8 @@ mod tests {
     pub fn test_truncate() {
         let line = Line::from("123456789");
         assert_eq!(line.truncate(3), "12…".into());
+        assert_eq!(line.truncate(0), Line::new(Vec::new()));
+        assert_eq!(Line::from("←→↑↓").truncate(3), "←→…".into());
     }
 
     #[test]
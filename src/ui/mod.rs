@@ -1,7 +1,10 @@
+pub mod event_log;
+pub mod event_recorder;
 pub mod line;
 mod no_tty_ui;
-mod progress_bar;
+pub mod progress_bar;
 mod recent_log_display;
+pub mod replay;
 mod screen;
 pub mod segment;
 mod tty_ui;
@@ -1,8 +1,10 @@
 use crate::messaging::listener::Listener;
 use crate::parasect::event::Event;
 use crate::parasect::event::Event::*;
+use crate::parasect::types::ParasectPayloadResult;
 use crate::parasect::types::ParasectPayloadResult::*;
 use crate::parasect::worker::PointCompletionMessageType::*;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
 use crossbeam_channel::Receiver;
 
@@ -16,6 +18,14 @@ pub struct NoTtyUi {
 }
 
 impl NoTtyUi {
+    fn describe_result(result: &ParasectPayloadResult) -> String {
+        match result {
+            Continue(v) => format!("result was {}", v),
+            Skip(msg) => format!("skipping it for reason: {}", msg),
+            Stop(msg) => format!("aborting parasect execution for reason: {}", msg),
+        }
+    }
+
     fn make_log_message(e: &Event) -> String {
         match e {
             WorkerMessageSent(wm) => match &wm.msg_type {
@@ -23,13 +33,13 @@ impl NoTtyUi {
                     "[Thread {}] Started processing point {}, between left half {} and right half {}.",
                     wm.thread_id, wm.point, wm.left, wm.right
                 ),
-                Completed(Continue(v)) => format!(
-                    "[Thread {}] Finished processing point {}, result was {}.",
-                    wm.thread_id, wm.point, v
+                Completed(result, annotations) if !annotations.is_empty() => format!(
+                    "[Thread {}] Finished processing point {}, {} [{}].",
+                    wm.thread_id, wm.point, Self::describe_result(result), annotations
                 ),
-                Completed(Stop(msg)) => format!(
-                    "[Thread {}] Finished processing point {}, aborting parasect execution for reason: {}.",
-                    wm.thread_id, wm.point, msg
+                Completed(result, _) => format!(
+                    "[Thread {}] Finished processing point {}, {}.",
+                    wm.thread_id, wm.point, Self::describe_result(result)
                 ),
                 Cancelled => format!(
                     "[Thread {}] Cancelled processing point {} because it is in a range that has been eliminated.",
@@ -43,16 +53,34 @@ impl NoTtyUi {
             ParasectCancelled(msg) => format!(
                 "[FATAL] Aborting parasect execution for reason: {}",
                 msg
-            )
+            ),
+            ProbeNoted(note) => match &note.answer {
+                Some(answer) if note.detail.is_empty() => format!(
+                    "[Thread {}] Point {}: {} was {}.",
+                    note.worker_id, note.point, note.source, answer
+                ),
+                Some(answer) => format!(
+                    "[Thread {}] Point {}: {} was {} ({}).",
+                    note.worker_id, note.point, note.source, answer, note.detail
+                ),
+                None => format!(
+                    "[Thread {}] Point {}: {}: {}.",
+                    note.worker_id, note.point, note.source, note.detail
+                ),
+            }
         }
     }
 
     pub fn start(
         initial_range: NumericRange,
+        alignment: Alignment,
         command_string: String,
         event_receiver: Receiver<Event>,
     ) -> Self {
         println!("Parasecting over range {}", initial_range);
+        if alignment != Alignment::default() {
+            println!("Only probing numbers of the form {}", alignment);
+        }
         println!("Command: {}", command_string);
         Self {
             _receiver_loop: Listener::spawn(event_receiver, |event| {
@@ -65,6 +93,8 @@ impl NoTtyUi {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parasect::event::ProbeNote;
+    use crate::parasect::types::Annotations;
     use crate::parasect::types::ParasectPayloadAnswer::*;
     use crate::parasect::worker::WorkerMessage;
     use crate::test_util::test_util::test_util::{ib, r};
@@ -88,7 +118,7 @@ mod tests {
                 left: r(1, 3),
                 point: ib(4),
                 right: r(5, 7),
-                msg_type: Completed(Continue(Good)),
+                msg_type: Completed(Continue(Good), Annotations::default()),
             })),
             "[Thread 5] Finished processing point 4, result was Good."
         );
@@ -99,7 +129,7 @@ mod tests {
                 left: r(1, 3),
                 point: ib(4),
                 right: r(5, 7),
-                msg_type: Completed(Continue(Bad)),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
             })),
             "[Thread 5] Finished processing point 4, result was Bad."
         );
@@ -110,11 +140,39 @@ mod tests {
                 left: r(1, 3),
                 point: ib(4),
                 right: r(5, 7),
-                msg_type: Completed(Stop("nope".into())),
+                msg_type: Completed(Skip("setup failed".into()), Annotations::default()),
+            })),
+            "[Thread 5] Finished processing point 4, skipping it for reason: setup failed."
+        );
+
+        assert_eq!(
+            NoTtyUi::make_log_message(&WorkerMessageSent(WorkerMessage {
+                thread_id: 5,
+                left: r(1, 3),
+                point: ib(4),
+                right: r(5, 7),
+                msg_type: Completed(Stop("nope".into()), Annotations::default()),
             })),
             "[Thread 5] Finished processing point 4, aborting parasect execution for reason: nope."
         );
 
+        assert_eq!(
+            NoTtyUi::make_log_message(&WorkerMessageSent(WorkerMessage {
+                thread_id: 5,
+                left: r(1, 3),
+                point: ib(4),
+                right: r(5, 7),
+                msg_type: Completed(
+                    Skip("flaky".into()),
+                    Annotations {
+                        note: Some("flaky infra".into()),
+                        metrics: [("ms".to_string(), "123".to_string())].into(),
+                    }
+                ),
+            })),
+            "[Thread 5] Finished processing point 4, skipping it for reason: flaky [flaky infra; ms=123]."
+        );
+
         assert_eq!(
             NoTtyUi::make_log_message(&WorkerMessageSent(WorkerMessage {
                 thread_id: 5,
@@ -135,5 +193,27 @@ mod tests {
             NoTtyUi::make_log_message(&ParasectCancelled("nope".into())),
             "[FATAL] Aborting parasect execution for reason: nope"
         );
+
+        let note = ProbeNote {
+            worker_id: 5,
+            point: ib(4),
+            source: "check lint".into(),
+            answer: Some(Bad),
+            detail: "exit status: 1".into(),
+        };
+
+        assert_eq!(
+            NoTtyUi::make_log_message(&ProbeNoted(note.clone())),
+            "[Thread 5] Point 4: check lint was Bad (exit status: 1)."
+        );
+
+        assert_eq!(
+            NoTtyUi::make_log_message(&ProbeNoted(ProbeNote {
+                answer: None,
+                detail: "cancelled".into(),
+                ..note
+            })),
+            "[Thread 5] Point 4: check lint: cancelled."
+        );
     }
 }
@@ -4,6 +4,7 @@ use crate::parasect::event::Event::*;
 use crate::parasect::types::ParasectPayloadAnswer::*;
 use crate::parasect::worker::PointCompletionMessageType::*;
 use crate::parasect::worker::WorkerMessage;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
 use crate::range::numeric_range_set::NumericRangeSet;
 use crate::ui::line::Line;
@@ -20,8 +21,11 @@ use std::sync::{Arc, RwLock};
 /// * With 3 `max_height`, gets rid of the carats in the bounds bar.
 /// * With 2 `max_height`, also reduces the color bar to 1 height.
 /// * With 1 `max_height`, only displays the bounds bar numbers.
+///
+/// Each cell of the color bar covers the same amount of aligned numbers (see `with_alignment()`).
 pub struct ProgressBar {
     _receiver_listener: Listener<'static, Event>,
+    alignment: Alignment,
     good_ranges: Arc<RwLock<NumericRangeSet>>,
     bad_ranges: Arc<RwLock<NumericRangeSet>>,
     valid_ranges: Arc<RwLock<NumericRangeSet>>,
@@ -43,39 +47,86 @@ impl ProgressBar {
         let valid_ranges_clone = valid_ranges.clone();
 
         Self {
+            alignment: Alignment::default(),
             good_ranges,
             bad_ranges,
             active,
             valid_ranges,
-            _receiver_listener: Listener::spawn(event_receiver, move |event| match event {
-                RangeInvalidated(r, Good) => {
-                    valid_ranges_clone.write().unwrap().remove(&r);
-                    good_ranges_clone.write().unwrap().add(r);
-                }
-                RangeInvalidated(r, Bad) => {
-                    valid_ranges_clone.write().unwrap().remove(&r);
-                    bad_ranges_clone.write().unwrap().add(r);
-                }
-                WorkerMessageSent(WorkerMessage {
-                    point,
-                    msg_type: Started,
-                    ..
-                }) => active_clone
-                    .write()
-                    .unwrap()
-                    .add(NumericRange::from_point(point)),
-                WorkerMessageSent(WorkerMessage {
-                    point,
-                    msg_type: Completed(_),
-                    ..
-                }) => active_clone
-                    .write()
-                    .unwrap()
-                    .remove(&NumericRange::from_point(point)),
-                _ => {}
+            _receiver_listener: Listener::spawn(event_receiver, move |event| {
+                apply_event(
+                    event,
+                    &good_ranges_clone,
+                    &bad_ranges_clone,
+                    &valid_ranges_clone,
+                    &active_clone,
+                )
             }),
         }
     }
+
+    /// Sizes the cells of the color bar by the amount of aligned numbers they contain, instead of all numbers.
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+/// Updates the state of a progress bar with `event`.
+fn apply_event(
+    event: Event,
+    good_ranges: &RwLock<NumericRangeSet>,
+    bad_ranges: &RwLock<NumericRangeSet>,
+    valid_ranges: &RwLock<NumericRangeSet>,
+    active: &RwLock<NumericRangeSet>,
+) {
+    match event {
+        RangeInvalidated(r, Good) => {
+            valid_ranges.write().unwrap().remove(&r);
+            good_ranges.write().unwrap().add(r);
+        }
+        RangeInvalidated(r, Bad) => {
+            valid_ranges.write().unwrap().remove(&r);
+            bad_ranges.write().unwrap().add(r);
+        }
+        WorkerMessageSent(WorkerMessage {
+            point,
+            msg_type: Started,
+            ..
+        }) => active.write().unwrap().add(NumericRange::from_point(point)),
+        WorkerMessageSent(WorkerMessage {
+            point,
+            msg_type: Completed(..),
+            ..
+        }) => active
+            .write()
+            .unwrap()
+            .remove(&NumericRange::from_point(point)),
+        _ => {}
+    }
+}
+
+/// The color bar of the whole of `range` once all of `events` happened, e.g. for a report of a finished search. Nothing is shown as active.
+pub fn final_color_bar<I: IntoIterator<Item = Event>>(
+    range: &NumericRange,
+    alignment: &Alignment,
+    events: I,
+    width: usize,
+) -> Line {
+    let [good_ranges, bad_ranges, valid_ranges, active] =
+        [(); 4].map(|_| RwLock::new(NumericRangeSet::new()));
+    for event in events {
+        apply_event(event, &good_ranges, &bad_ranges, &valid_ranges, &active);
+    }
+
+    let bar = render_color_bar(
+        &good_ranges.read().unwrap(),
+        &bad_ranges.read().unwrap(),
+        range,
+        &NumericRangeSet::new(),
+        alignment,
+        width,
+    );
+    bar
 }
 
 /// Gets the color of a cell from the given range.
@@ -104,18 +155,30 @@ fn range_color(
     }
 }
 
-fn bound_partition_to_width(bounds: &NumericRange, width: usize) -> Vec<NumericRange> {
-    if bounds.len() >= UBig::from(width) {
-        bounds.partition(width)
+fn bound_partition_to_width(
+    bounds: &NumericRange,
+    width: usize,
+    alignment: &Alignment,
+) -> Vec<NumericRange> {
+    let aligned_len = bounds.aligned_len(alignment);
+
+    if aligned_len >= UBig::from(width) {
+        bounds.partition_aligned(width, alignment)
+    } else if aligned_len == UBig::from(0usize) {
+        Vec::new()
     } else {
         let mut ret = Vec::new();
 
-        let wparts = NumericRange::from_endpoints_excluding_end(0, width)
-            .partition(usize::try_from(bounds.len()).unwrap());
+        let aligned_len = usize::try_from(aligned_len).unwrap();
+        let wparts = NumericRange::from_endpoints_excluding_end(0, width).partition(aligned_len);
 
-        for (num, part) in bounds.iter().zip(wparts) {
+        for (range, part) in bounds
+            .partition_aligned(aligned_len, alignment)
+            .into_iter()
+            .zip(wparts)
+        {
             for _ in 0..usize::try_from(part.len()).unwrap() {
-                ret.push(NumericRange::from_point(num.clone()));
+                ret.push(range.clone());
             }
         }
 
@@ -128,9 +191,10 @@ fn render_color_bar(
     bad_ranges: &NumericRangeSet,
     bounds: &NumericRange,
     active: &NumericRangeSet,
+    alignment: &Alignment,
     width: usize,
 ) -> Line {
-    let partitions = bound_partition_to_width(bounds, width);
+    let partitions = bound_partition_to_width(bounds, width, alignment);
 
     let segments = partitions.into_iter().map(|r| {
         let color = range_color(good_ranges, bad_ranges, &r);
@@ -178,7 +242,14 @@ impl UiComponent for ProgressBar {
             return Vec::new();
         }
 
-        let color_bar = render_color_bar(&good_ranges, &bad_ranges, &bounds, &active, width);
+        let color_bar = render_color_bar(
+            &good_ranges,
+            &bad_ranges,
+            &bounds,
+            &active,
+            &self.alignment,
+            width,
+        );
 
         let mut ret = Vec::new();
 
@@ -209,7 +280,7 @@ mod tests {
     use super::*;
     use crate::collections::collect_collection::CollectVec;
     use crate::parasect::types::ParasectPayloadResult::*;
-    use crate::parasect::types::{ParasectPayloadAnswer, ParasectPayloadResult};
+    use crate::parasect::types::{Annotations, ParasectPayloadAnswer, ParasectPayloadResult};
     use crate::test_util::test_util::test_util::{empty, ib, r};
     use crate::ui::line::mkline;
     use crossbeam_channel::unbounded;
@@ -240,7 +311,7 @@ mod tests {
             left,
             point: midpoint,
             right,
-            msg_type: Completed(result),
+            msg_type: Completed(result, Annotations::default()),
         })
     }
 
@@ -494,12 +565,32 @@ mod tests {
         assert_contents_eq(&pb.valid_ranges, [r(16, 19)]);
     }
 
+    #[test]
+    fn test_final_color_bar() {
+        let events = [
+            start(r(0, 4), ib(5), r(6, 9)),
+            stop(r(0, 4), ib(5), r(6, 9), Continue(Good)),
+            r_invalid(r(0, 5), Good),
+            start(r(6, 6), ib(7), r(8, 9)),
+            r_invalid(r(8, 9), Bad),
+        ];
+
+        assert_eq!(
+            final_color_bar(&r(0, 9), &Alignment::default(), events, 5),
+            mkline!(
+                ("███", Color::Green, Attributes::empty()),
+                ("█", Color::Blue, Attributes::empty()),
+                ("█", Color::Red, Attributes::empty())
+            )
+        );
+    }
+
     #[test]
     fn test_bound_partition_snug() {
         let bounds = r(0, 4);
 
         assert_eq!(
-            bound_partition_to_width(&bounds, 5),
+            bound_partition_to_width(&bounds, 5, &Alignment::default()),
             vec![r(0, 0), r(1, 1), r(2, 2), r(3, 3), r(4, 4)]
         )
     }
@@ -509,7 +600,7 @@ mod tests {
         let bounds = r(0, 16);
 
         assert_eq!(
-            bound_partition_to_width(&bounds, 5),
+            bound_partition_to_width(&bounds, 5, &Alignment::default()),
             vec![r(0, 3), r(4, 7), r(8, 10), r(11, 13), r(14, 16)]
         )
     }
@@ -519,17 +610,27 @@ mod tests {
         let bounds = r(0, 2);
 
         assert_eq!(
-            bound_partition_to_width(&bounds, 5),
+            bound_partition_to_width(&bounds, 5, &Alignment::default()),
             vec![r(0, 0), r(0, 0), r(1, 1), r(1, 1), r(2, 2)]
         )
     }
 
+    #[test]
+    fn test_bound_partition_aligned_thin() {
+        let bounds = r(1, 17);
+
+        assert_eq!(
+            bound_partition_to_width(&bounds, 5, &Alignment::new(8u8, 1)),
+            vec![r(1, 1), r(1, 1), r(2, 9), r(2, 9), r(10, 17)]
+        )
+    }
+
     proptest! {
         #[test]
         fn test_bound_partition_equals_width(a in 1..2000, b in 1..2000, c in 1..1000usize) {
             prop_assume!(a < b);
 
-            assert_eq!(bound_partition_to_width(&r(a, b), c).len(), c);
+            assert_eq!(bound_partition_to_width(&r(a, b), c, &Alignment::default()).len(), c);
         }
     }
 }
@@ -1,10 +1,10 @@
 use crate::collections::collect_collection::CollectVec;
 use crate::messaging::listener::Listener;
-use crate::parasect::event::Event;
 use crate::parasect::event::Event::*;
+use crate::parasect::event::{Event, ProbeNote};
 use crate::parasect::types::ParasectPayloadAnswer::*;
 use crate::parasect::types::ParasectPayloadResult::*;
-use crate::parasect::types::{ParasectPayloadAnswer, ParasectPayloadResult};
+use crate::parasect::types::{Annotations, ParasectPayloadAnswer, ParasectPayloadResult};
 use crate::parasect::worker::PointCompletionMessageType::*;
 use crate::parasect::worker::WorkerMessage;
 use crate::ui::line::mkline;
@@ -19,13 +19,14 @@ use std::sync::{Arc, RwLock};
 #[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Debug)]
 enum LogType {
     Thread(usize),
+    Note(usize),
     RangeInvalidation,
     Cancellation,
 }
 
 /// Displays the most recently updated logs from the given event stream.
 ///
-/// Logs of the same type (logs for the same thread ID, range invalidations, cancellations) overwrite the previous entry of that type.
+/// Logs of the same type (logs for the same thread ID, notes from the same thread ID's probes, range invalidations, cancellations) overwrite the previous entry of that type.
 ///
 /// Produces `max(max_height, logs.len())` lines when rendered.
 pub struct RecentLogDisplay {
@@ -49,10 +50,27 @@ impl RecentLogDisplay {
     fn result_segment(p: &ParasectPayloadResult) -> Segment {
         match p {
             Continue(a) => Self::answer_segment(&a),
+            Skip(_) => ("Skip", Color::Yellow, Attributes::Bold).into(),
             Stop(s) => (format!("Abort ({s})"), Color::Magenta, Attributes::Bold).into(),
         }
     }
 
+    /// The result's annotations, or an empty segment if there are none.
+    fn annotations_segment(a: &Annotations) -> Segment {
+        if a.is_empty() {
+            "".into()
+        } else {
+            (format!(" [{}]", a), Color::Yellow).into()
+        }
+    }
+
+    fn note_answer_segment(note: &ProbeNote) -> Segment {
+        match &note.answer {
+            Some(a) => Self::answer_segment(a),
+            None => (&note.detail, Color::Yellow).into(),
+        }
+    }
+
     /// Makes a shorter log message if the terminal is too thin to render the long one.
     fn make_log_message_short(event: &Event) -> Line {
         match event {
@@ -62,7 +80,7 @@ impl RecentLogDisplay {
                     ": ",
                     (&wm.point, Color::Blue, Attributes::Bold)
                 ),
-                Completed(c) => mkline!(
+                Completed(c, _) => mkline!(
                     wm.thread_id,
                     ": ",
                     (&wm.point, Color::Blue, Attributes::Bold),
@@ -86,6 +104,15 @@ impl RecentLogDisplay {
                     Self::answer_segment(&ans)
                 )
             }
+            ProbeNoted(note) => mkline!(
+                note.worker_id,
+                ": ",
+                (&note.point, Color::Blue, Attributes::Bold),
+                " ",
+                &note.source,
+                " ",
+                Self::note_answer_segment(note)
+            ),
         }
     }
 
@@ -108,7 +135,7 @@ impl RecentLogDisplay {
                         "]"
                     )
                 }
-                Completed(status) => {
+                Completed(status, annotations) => {
                     mkline!(
                         "Thread ",
                         wm.thread_id,
@@ -116,6 +143,7 @@ impl RecentLogDisplay {
                         ("completed", Color::Green, Attributes::Bold),
                         " status=",
                         Self::result_segment(status),
+                        Self::annotations_segment(annotations),
                         " x=",
                         (&wm.point, Color::Blue, Attributes::Bold),
                         " range=[",
@@ -158,6 +186,25 @@ impl RecentLogDisplay {
                     Self::answer_segment(ans)
                 )
             }
+            ProbeNoted(note) => {
+                let line = mkline!(
+                    "Thread ",
+                    note.worker_id,
+                    ": ",
+                    (&note.source, Attributes::Bold),
+                    " ",
+                    Self::note_answer_segment(note),
+                    " x=",
+                    (&note.point, Color::Blue, Attributes::Bold)
+                );
+
+                match note.answer {
+                    Some(_) if !note.detail.is_empty() => {
+                        Line::join([line, mkline!(" (", &note.detail, ")")])
+                    }
+                    _ => line,
+                }
+            }
         }
     }
 
@@ -174,6 +221,7 @@ impl RecentLogDisplay {
     fn event_log_type(event: &Event) -> LogType {
         match event {
             WorkerMessageSent(WorkerMessage { thread_id, .. }) => Thread(*thread_id),
+            ProbeNoted(ProbeNote { worker_id, .. }) => Note(*worker_id),
             RangeInvalidated(_, _) => RangeInvalidation,
             ParasectCancelled(_) => Cancellation,
         }
@@ -253,7 +301,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_short(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Continue(Good)),
+                msg_type: Completed(Continue(Good), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -266,7 +314,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_short(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Continue(Bad)),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -279,7 +327,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_short(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Stop("nope".into())),
+                msg_type: Completed(Stop("nope".into()), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -343,7 +391,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_long(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Continue(Good)),
+                msg_type: Completed(Continue(Good), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -363,7 +411,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_long(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Continue(Bad)),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -383,7 +431,7 @@ mod tests {
 
         assert_eq!(
             RecentLogDisplay::make_log_message_long(&WorkerMessageSent(WorkerMessage {
-                msg_type: Completed(Stop("nope".into())),
+                msg_type: Completed(Stop("nope".into()), Annotations::default()),
                 ..test_wm()
             })),
             mkline!(
@@ -401,6 +449,33 @@ mod tests {
             )
         );
 
+        assert_eq!(
+            RecentLogDisplay::make_log_message_long(&WorkerMessageSent(WorkerMessage {
+                msg_type: Completed(
+                    Continue(Bad),
+                    Annotations {
+                        note: None,
+                        metrics: [("ms".to_string(), "123".to_string())].into(),
+                    }
+                ),
+                ..test_wm()
+            })),
+            mkline!(
+                "Thread 420: ",
+                ("completed", Color::Green, Attributes::Bold),
+                " status=",
+                ("Bad", Color::Red, Attributes::Bold),
+                (" [ms=123]", Color::Yellow),
+                " x=",
+                ("69", Color::Blue, Attributes::Bold),
+                " range=[",
+                ("66", Color::Blue),
+                ", ",
+                ("72", Color::Blue),
+                "]"
+            )
+        );
+
         assert_eq!(
             RecentLogDisplay::make_log_message_long(&RangeInvalidated(r(1, 5), Good)),
             mkline!(
@@ -463,6 +538,67 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_make_log_message_note() {
+        let note = ProbeNote {
+            worker_id: 3,
+            point: ib(69),
+            source: "check lint".into(),
+            answer: Some(Bad),
+            detail: "exit status: 1".into(),
+        };
+
+        assert_eq!(
+            RecentLogDisplay::make_log_message(&ProbeNoted(note.clone()), 80),
+            mkline!(
+                "Thread 3: ",
+                ("check lint", Attributes::Bold),
+                " ",
+                ("Bad", Color::Red, Attributes::Bold),
+                " x=",
+                ("69", Color::Blue, Attributes::Bold),
+                " (exit status: 1)"
+            )
+        );
+
+        assert_eq!(
+            RecentLogDisplay::make_log_message(&ProbeNoted(note.clone()), 20),
+            mkline!(
+                "3: ",
+                ("69", Color::Blue, Attributes::Bold),
+                " check lint ",
+                ("Bad", Color::Red, Attributes::Bold)
+            )
+        );
+
+        assert_eq!(
+            RecentLogDisplay::make_log_message(
+                &ProbeNoted(ProbeNote {
+                    answer: None,
+                    detail: "cancelled".into(),
+                    ..note.clone()
+                }),
+                80
+            ),
+            mkline!(
+                "Thread 3: ",
+                ("check lint", Attributes::Bold),
+                " ",
+                ("cancelled", Color::Yellow),
+                " x=",
+                ("69", Color::Blue, Attributes::Bold)
+            )
+        );
+
+        assert_ne!(
+            RecentLogDisplay::event_log_type(&ProbeNoted(note)),
+            RecentLogDisplay::event_log_type(&WorkerMessageSent(WorkerMessage {
+                thread_id: 3,
+                ..test_wm()
+            }))
+        );
+    }
+
     #[test]
     pub fn test_event_log_type() {
         let t1 = RecentLogDisplay::event_log_type(&WorkerMessageSent(test_wm()));
@@ -514,14 +650,14 @@ mod tests {
                 left: r(0, 19),
                 point: ib(20),
                 right: r(21, 40),
-                msg_type: Completed(Continue(Bad)),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
             }),
             WorkerMessageSent(WorkerMessage {
                 thread_id: 1,
                 left: r(0, 9),
                 point: ib(10),
                 right: r(11, 19),
-                msg_type: Completed(Continue(Good)),
+                msg_type: Completed(Continue(Good), Annotations::default()),
             }),
             RangeInvalidated(r(20, 40), Bad),
             RangeInvalidated(r(0, 10), Good),
@@ -584,14 +720,14 @@ mod tests {
                 left: r(0, 19),
                 point: ib(20),
                 right: r(21, 40),
-                msg_type: Completed(Continue(Bad)),
+                msg_type: Completed(Continue(Bad), Annotations::default()),
             }),
             WorkerMessageSent(WorkerMessage {
                 thread_id: 1,
                 left: r(0, 9),
                 point: ib(10),
                 right: r(11, 19),
-                msg_type: Completed(Continue(Good)),
+                msg_type: Completed(Continue(Good), Annotations::default()),
             }),
             WorkerMessageSent(WorkerMessage {
                 thread_id: 2,
@@ -614,7 +750,7 @@ mod tests {
                 left: r(11, 14),
                 point: ib(15),
                 right: r(16, 19),
-                msg_type: Completed(Stop("nope".into())),
+                msg_type: Completed(Stop("nope".into()), Annotations::default()),
             }),
             ParasectCancelled("nope".into()),
         ];
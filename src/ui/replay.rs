@@ -0,0 +1,357 @@
+use crate::parasect::event::Event;
+use crate::range::alignment::Alignment;
+use crate::range::numeric_range::NumericRange;
+use crate::ui::event_log::{event_from_json, SCHEMA_VERSION};
+use crate::ui::line::{mkline, Line};
+use crate::ui::screen::screen::Screen;
+use crate::ui::screen::terminal_screen::TerminalScreen;
+use crate::ui::segment::{Attributes, Color};
+use crate::ui::tty_ui::TtyPrinter;
+use crate::util::json::{ibig_from_json, range_from_json};
+use crossbeam_channel::{unbounded, RecvTimeoutError, Sender};
+use ibig::UBig;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+
+/// A search recorded with `--events`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Recording {
+    pub range: NumericRange,
+    pub alignment: Alignment,
+    pub command: Vec<String>,
+    pub substitution_string: String,
+    /// Every event, with how long after the start of the recording it happened.
+    pub events: Vec<(Duration, Event)>,
+}
+
+fn parse_record(line: &str) -> Result<(Duration, Value), String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+
+    match value.get("schema").and_then(|s| s.as_u64()) {
+        Some(schema) if schema > SCHEMA_VERSION => {
+            return Err(format!(
+                "It was recorded by a newer parasect (schema {}, but only {} is supported)",
+                schema, SCHEMA_VERSION
+            ))
+        }
+        Some(_) => {}
+        None => return Err("Expected a schema version".into()),
+    }
+
+    let elapsed = value
+        .get("elapsed")
+        .and_then(|e| e.as_f64())
+        .and_then(|e| Duration::try_from_secs_f64(e).ok())
+        .ok_or("Expected the seconds elapsed")?;
+    Ok((elapsed, value))
+}
+
+impl Recording {
+    /// Reads the lines written by `EventLog`. Fails on the first line that can't be read.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty());
+        let at = |i: usize| move |e: String| format!("Line {}: {}", i + 1, e);
+
+        let (i, header) = lines.next().ok_or("The recording is empty")?;
+        let (_, header) = parse_record(header).map_err(at(i))?;
+        if header.get("event").and_then(|e| e.as_str()) != Some("search_started") {
+            return Err(at(i)("Expected the search_started record first".into()));
+        }
+
+        let field = |name: &str| header.get(name).unwrap_or(&Value::Null);
+        let step = UBig::try_from(ibig_from_json(field("step")).map_err(at(i))?)
+            .ok()
+            .filter(|s| *s != UBig::from(0usize))
+            .ok_or_else(|| at(i)("Expected a positive step".into()))?;
+        let command = serde_json::from_value(field("command").clone())
+            .map_err(|e| at(i)(format!("Expected the command: {}", e)))?;
+
+        Ok(Self {
+            range: range_from_json(field("range")).map_err(at(i))?,
+            alignment: Alignment::new(step, ibig_from_json(field("offset")).map_err(at(i))?),
+            command,
+            substitution_string: field("substitution_string")
+                .as_str()
+                .ok_or_else(|| at(i)("Expected the substitution string".into()))?
+                .into(),
+            events: lines
+                .map(|(i, line)| {
+                    let (elapsed, value) = parse_record(line).map_err(at(i))?;
+                    let event = event_from_json(&value).map_err(at(i))?;
+                    Ok((elapsed, event))
+                })
+                .collect::<Result<_, String>>()?,
+        })
+    }
+}
+
+/// Where a replay is in its recording, and how fast it moves on.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Playback {
+    times: Vec<Duration>,
+    /// How many events are shown.
+    position: usize,
+    /// The time in the recording.
+    time: Duration,
+    speed: f64,
+    paused: bool,
+}
+
+impl Playback {
+    /// `times` are those of the events, in order.
+    pub fn new(times: Vec<Duration>, speed: f64, paused: bool) -> Self {
+        Self {
+            times,
+            position: 0,
+            time: Duration::ZERO,
+            speed,
+            paused,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn finished(&self) -> bool {
+        self.position == self.times.len()
+    }
+
+    /// Lets `real` time pass, which shows the events that happened in the meantime (at the playback's speed), unless it's paused.
+    pub fn tick(&mut self, real: Duration) {
+        if self.paused || self.finished() {
+            return;
+        }
+
+        self.time += real.mul_f64(self.speed);
+        while self
+            .times
+            .get(self.position)
+            .is_some_and(|t| *t <= self.time)
+        {
+            self.position += 1;
+        }
+        if self.finished() {
+            self.time = self.times.last().copied().unwrap_or_default();
+        }
+    }
+
+    /// Shows the first `position` events, and pauses.
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.times.len());
+        self.time = match self.position {
+            0 => Duration::ZERO,
+            p => self.times[p - 1],
+        };
+        self.paused = true;
+    }
+
+    /// Shows `delta` more (or fewer) events, and pauses.
+    pub fn step(&mut self, delta: isize) {
+        self.seek(self.position.saturating_add_signed(delta));
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Multiplies the speed by `factor`, within reason.
+    pub fn change_speed(&mut self, factor: f64) {
+        self.speed = (self.speed * factor).clamp(1.0 / 64.0, 1024.0);
+    }
+
+    fn status(&self) -> Line {
+        let state = match (self.finished(), self.paused) {
+            (true, _) => "Finished",
+            (false, true) => "Paused",
+            (false, false) => "Playing",
+        };
+        let total = self.times.last().copied().unwrap_or_default();
+
+        mkline!(
+            (state, Color::Blue, Attributes::Bold),
+            format!(
+                " at {}x, event {}/{}, {:.1}s/{:.1}s. ",
+                self.speed,
+                self.position,
+                self.times.len(),
+                self.time.as_secs_f64(),
+                total.as_secs_f64()
+            ),
+            "Space pauses, ←/→ step, ↑/↓ step by 10, Home/End jump, +/- change the speed, q quits."
+        )
+    }
+}
+
+/// Feeds the first events of a recording to a fresh printer, which can only be moved forwards.
+fn restart_printer(
+    recording: &Recording,
+    title: &Line,
+    screen: &Arc<Mutex<TerminalScreen>>,
+) -> (Sender<Event>, TtyPrinter<Arc<Mutex<TerminalScreen>>>) {
+    let (sender, receiver) = unbounded();
+    screen.lock().unwrap().reset();
+    let printer = TtyPrinter::new(
+        receiver,
+        title.clone(),
+        recording.range.clone(),
+        recording.alignment.clone(),
+        screen.clone(),
+    );
+    (sender, printer)
+}
+
+/// Plays `recording` back in the TTY interface until `q` is pressed, starting at `speed` times the recorded speed, or paused.
+pub fn replay(recording: &Recording, title: Line, speed: f64, paused: bool) -> Result<(), String> {
+    if !TerminalScreen::output_is_tty() {
+        return Err("Replaying needs a terminal".into());
+    }
+
+    let screen = Arc::new(Mutex::new(TerminalScreen::new()));
+    // after the screen, so that the terminal is back to normal when the screen prints its last newline
+    let _raw = io::stdout()
+        .into_raw_mode()
+        .map_err(|e| format!("Failed to read keys from the terminal: {}", e))?;
+
+    let (key_sender, keys) = unbounded();
+    thread::spawn(move || {
+        for key in io::stdin().keys().map_while(Result::ok) {
+            if key_sender.send(key).is_err() {
+                return;
+            }
+        }
+    });
+
+    let times = recording.events.iter().map(|(t, _)| *t).collect();
+    let mut playback = Playback::new(times, speed, paused);
+    let (mut sender, mut printer) = restart_printer(recording, &title, &screen);
+    let mut shown = 0;
+    let mut last_tick = Instant::now();
+
+    loop {
+        match keys.recv_timeout(Duration::from_millis(50)) {
+            Ok(Key::Char('q') | Key::Esc | Key::Ctrl('c')) => return Ok(()),
+            Ok(Key::Char(' ')) => playback.toggle_pause(),
+            Ok(Key::Right | Key::Char('l')) => playback.step(1),
+            Ok(Key::Left | Key::Char('h')) => playback.step(-1),
+            Ok(Key::Up | Key::Char('k')) => playback.step(10),
+            Ok(Key::Down | Key::Char('j')) => playback.step(-10),
+            Ok(Key::Home) => playback.seek(0),
+            Ok(Key::End) => playback.seek(usize::MAX),
+            Ok(Key::Char('+')) => playback.change_speed(2.0),
+            Ok(Key::Char('-')) => playback.change_speed(0.5),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let now = Instant::now();
+        playback.tick(now - last_tick);
+        last_tick = now;
+
+        // the interface only moves forwards, so going back means starting over
+        if playback.position() < shown {
+            (sender, printer) = restart_printer(recording, &title, &screen);
+            shown = 0;
+        }
+        for (_, event) in &recording.events[shown..playback.position()] {
+            let _ = sender.send(event.clone());
+        }
+        shown = playback.position();
+
+        printer.set_status(playback.status());
+        printer.print_frame();
+        let _ = io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parasect::event::Event::RangeInvalidated;
+    use crate::parasect::types::ParasectPayloadAnswer::Good;
+    use crate::test_util::test_util::test_util::r;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = [
+            r#"{"schema":1,"time":5.0,"elapsed":0.0,"event":"search_started","range":[0,20],"step":2,"offset":1,"command":["foo","$X"],"substitution_string":"$X"}"#,
+            "",
+            r#"{"schema":1,"time":5.5,"elapsed":0.5,"event":"range_invalidated","range":[1,9],"answer":"good"}"#,
+        ]
+        .join("\n");
+
+        assert_eq!(
+            Recording::parse(&text),
+            Ok(Recording {
+                range: r(0, 20),
+                alignment: Alignment::new(2usize, 1),
+                command: vec!["foo".into(), "$X".into()],
+                substitution_string: "$X".into(),
+                events: vec![(secs(0.5), RangeInvalidated(r(1, 9), Good))],
+            })
+        );
+
+        assert_eq!(
+            Recording::parse(&format!("{}\n{{\"schema\":1}}", text)),
+            Err("Line 4: Expected the seconds elapsed".into())
+        );
+        assert_eq!(
+            Recording::parse(&text.replace("\"schema\":1,", "\"schema\":2,")),
+            Err(
+                "Line 1: It was recorded by a newer parasect (schema 2, but only 1 is supported)"
+                    .into()
+            )
+        );
+        assert_eq!(
+            Recording::parse(&text.replace("search_started", "worker_message")),
+            Err("Line 1: Expected the search_started record first".into())
+        );
+        assert_eq!(Recording::parse("\n"), Err("The recording is empty".into()));
+    }
+
+    #[test]
+    fn test_playback() {
+        let mut playback = Playback::new(vec![secs(1.0), secs(2.0), secs(4.0)], 2.0, false);
+
+        playback.tick(secs(0.4));
+        assert_eq!(playback.position(), 0);
+        playback.tick(secs(0.6));
+        assert_eq!(playback.position(), 2);
+
+        playback.toggle_pause();
+        playback.tick(secs(5.0));
+        assert_eq!(playback.position(), 2);
+
+        playback.step(-1);
+        assert_eq!(playback.position(), 1);
+        playback.step(-10);
+        assert_eq!(playback.position(), 0);
+        playback.seek(usize::MAX);
+        assert!(playback.finished());
+
+        // resuming after a seek continues from the time of the last event shown
+        playback.seek(1);
+        playback.toggle_pause();
+        playback.change_speed(0.5);
+        playback.tick(secs(0.9));
+        assert_eq!(playback.position(), 1);
+        playback.tick(secs(0.1));
+        assert_eq!(playback.position(), 2);
+        playback.tick(secs(10.0));
+        assert!(playback.finished());
+        assert_eq!(playback.time, secs(4.0));
+    }
+}
@@ -2,6 +2,7 @@ use crate::messaging::fan::Fan;
 use crate::messaging::listener::Listener;
 use crate::messaging::periodic_notifier::PeriodicNotifier;
 use crate::parasect::event::Event;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
 use crate::ui::line::Line;
 use crate::ui::progress_bar::ProgressBar;
@@ -12,10 +13,12 @@ use crate::ui::ui_component::UiComponent;
 use crossbeam_channel::Receiver;
 use std::time::Duration;
 
-struct TtyPrinter<S: Screen> {
+/// Draws the title, the progress bar and the recent logs to a screen, based on the given `Event` stream.
+pub struct TtyPrinter<S: Screen> {
     _fan: Fan<'static, Event>,
     printer: LinePrinter<S>,
     title: Line,
+    status: Option<Line>,
     progress_bar: ProgressBar,
     recent_log_display: RecentLogDisplay,
 }
@@ -41,42 +44,53 @@ fn render_screen(components: &[&dyn UiComponent], dimensions: Dimensions) -> Vec
 }
 
 impl<S: Screen> TtyPrinter<S> {
-    fn print_frame(&mut self) {
+    pub fn print_frame(&mut self) {
         let dims = self.printer.dimensions();
-
-        let lines = render_screen(
-            &[
-                &self.title.center(dims.width).truncate(dims.width),
-                &self.progress_bar,
-                &self.recent_log_display,
-            ],
-            dims,
-        );
+        let title = self.title.center(dims.width).truncate(dims.width);
+
+        let lines = match &self.status {
+            Some(status) => render_screen(
+                &[&title, status, &self.progress_bar, &self.recent_log_display],
+                dims,
+            ),
+            None => render_screen(
+                &[&title, &self.progress_bar, &self.recent_log_display],
+                dims,
+            ),
+        };
 
         for (i, line) in lines.into_iter().enumerate() {
             self.printer.print_line_at(line, i);
         }
     }
 
-    fn new(
+    pub fn new(
         event_receiver: Receiver<Event>,
         title: Line,
         initial_range: NumericRange,
+        alignment: Alignment,
         screen: S,
     ) -> Self {
         let fan = Fan::new(event_receiver);
         let printer = LinePrinter::new(screen);
-        let progress_bar = ProgressBar::new(fan.subscribe(), initial_range);
+        let progress_bar =
+            ProgressBar::new(fan.subscribe(), initial_range).with_alignment(alignment);
         let recent_log_display = RecentLogDisplay::new(fan.subscribe());
 
         Self {
             _fan: fan,
             printer,
             title,
+            status: None,
             progress_bar,
             recent_log_display,
         }
     }
+
+    /// Shows `status` below the title.
+    pub fn set_status(&mut self, status: Line) {
+        self.status = Some(status);
+    }
 }
 
 /// Instantiation of this struct renders a full-fledged TUI for TTY interfaces based on the given `Event` stream. Use `NoTtyUi` for a traditional "stream of logs" interface.
@@ -95,11 +109,13 @@ impl TtyUi {
     /// Note that it's up to the caller to determine if the given `screen` is valid or not.
     pub fn start<S: Screen + Send + 'static>(
         initial_range: NumericRange,
+        alignment: Alignment,
         title: Line,
         event_receiver: Receiver<Event>,
         screen: S,
     ) -> Self {
-        let mut tty_printer = TtyPrinter::new(event_receiver, title, initial_range, screen);
+        let mut tty_printer =
+            TtyPrinter::new(event_receiver, title, initial_range, alignment, screen);
         let clock = PeriodicNotifier::new(Duration::from_millis(500));
         let frame_loop = Listener::spawn(clock.receiver(), move |_| tty_printer.print_frame());
 
@@ -124,6 +140,7 @@ mod tests {
     use crate::collections::collect_collection::CollectVec;
     use crate::messaging::mailbox::Mailbox;
     use crate::parasect::event::Event::{RangeInvalidated, WorkerMessageSent};
+    use crate::parasect::types::Annotations;
     use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
     use crate::parasect::types::ParasectPayloadResult::Continue;
     use crate::parasect::worker::PointCompletionMessageType::{Completed, Started};
@@ -179,6 +196,7 @@ mod tests {
             recv,
             mkline!(("foo", Color::Blue)),
             r(0, 40),
+            Alignment::default(),
             screen.clone(),
         );
 
@@ -209,6 +227,7 @@ mod tests {
             recv,
             mkline!(("foo", Color::Blue)),
             r(0, 40),
+            Alignment::default(),
             screen.clone(),
         );
 
@@ -240,6 +259,7 @@ mod tests {
             recv.clone(),
             mkline!(("foo", Color::Blue)),
             r(0, 40),
+            Alignment::default(),
             screen.clone(),
         );
 
@@ -258,7 +278,7 @@ mod tests {
             left: r(0, 19),
             point: ib(20),
             right: r(21, 40),
-            msg_type: Completed(Continue(Bad)),
+            msg_type: Completed(Continue(Bad), Annotations::default()),
         }));
 
         send.send_msg(RangeInvalidated(r(20, 40), Bad));
@@ -284,7 +304,7 @@ mod tests {
             left: r(11, 14),
             point: ib(15),
             right: r(16, 20),
-            msg_type: Completed(Continue(Good)),
+            msg_type: Completed(Continue(Good), Annotations::default()),
         }));
 
         send.send_msg(RangeInvalidated(r(11, 15), Good));
@@ -1,4 +1,5 @@
 use crate::parasect::event::Event;
+use crate::range::alignment::Alignment;
 use crate::range::numeric_range::NumericRange;
 use crate::ui::line::Line;
 use crate::ui::no_tty_ui::NoTtyUi;
@@ -15,6 +16,7 @@ pub enum Ui {
 impl Ui {
     pub fn start(
         initial_range: NumericRange,
+        alignment: Alignment,
         title: Line,
         event_receiver: Receiver<Event>,
         no_tty: bool,
@@ -22,12 +24,14 @@ impl Ui {
         if no_tty || !TerminalScreen::output_is_tty() {
             NoTty(NoTtyUi::start(
                 initial_range,
+                alignment,
                 title.plaintext(),
                 event_receiver,
             ))
         } else {
             Tty(TtyUi::start(
                 initial_range.clone(),
+                alignment,
                 title.clone(),
                 event_receiver.clone(),
                 TerminalScreen::new(),
@@ -0,0 +1,171 @@
+use crate::parasect::types::ParasectPayloadAnswer::{Bad, Good};
+use crate::parasect::types::ParasectPayloadResult::{Continue, Skip, Stop};
+use crate::parasect::types::{Annotations, ParasectPayloadResult};
+use crate::range::numeric_range::NumericRange;
+use ibig::IBig;
+use serde_json::{json, Value};
+
+/// A number if JSON parsers can read it exactly, a string otherwise.
+pub fn ibig_to_json(n: &IBig) -> Value {
+    match i64::try_from(n) {
+        Ok(n) if n.unsigned_abs() < 1 << 53 => json!(n),
+        _ => json!(n.to_string()),
+    }
+}
+
+/// Reads a number written by `ibig_to_json`.
+pub fn ibig_from_json(value: &Value) -> Result<IBig, String> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .map(IBig::from)
+            .ok_or_else(|| format!("Expected an integer (got {})", n)),
+        Value::String(s) => s
+            .parse()
+            .map_err(|_| format!("Expected an integer (got \"{}\")", s)),
+        _ => Err(format!("Expected an integer (got {})", value)),
+    }
+}
+
+/// `[first, last]`, or null if the range is empty.
+pub fn range_to_json(range: &NumericRange) -> Value {
+    match range.as_tuple() {
+        Some((first, last)) => json!([ibig_to_json(&first), ibig_to_json(&last)]),
+        None => Value::Null,
+    }
+}
+
+/// Reads a range written by `range_to_json`.
+pub fn range_from_json(value: &Value) -> Result<NumericRange, String> {
+    match value {
+        Value::Null => Ok(NumericRange::empty()),
+        Value::Array(ends) if ends.len() == 2 => Ok(NumericRange::from_endpoints_inclusive(
+            ibig_from_json(&ends[0])?,
+            ibig_from_json(&ends[1])?,
+        )),
+        _ => Err(format!("Expected [first, last] or null (got {})", value)),
+    }
+}
+
+/// `{"verdict": ..., "detail": ..., "annotations": ...}`, where the verdict is `good`, `bad`, `skip` or `stop`, the detail is the reason for the latter two, and the annotations are null if there are none.
+pub fn result_to_json(result: &ParasectPayloadResult, annotations: &Annotations) -> Value {
+    let (verdict, detail) = match result {
+        Continue(Good) => ("good", None),
+        Continue(Bad) => ("bad", None),
+        Skip(detail) => ("skip", Some(detail)),
+        Stop(detail) => ("stop", Some(detail)),
+    };
+    let annotations = (!annotations.is_empty())
+        .then(|| json!({"note": annotations.note, "metrics": annotations.metrics}));
+
+    json!({"verdict": verdict, "detail": detail, "annotations": annotations})
+}
+
+/// Reads a result and its annotations written by `result_to_json`.
+pub fn result_from_json(value: &Value) -> Result<(ParasectPayloadResult, Annotations), String> {
+    let field = |name| value.get(name).unwrap_or(&Value::Null);
+    let detail = || {
+        field("detail")
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| format!("Expected a detail (got {})", field("detail")))
+    };
+
+    let result = match field("verdict").as_str() {
+        Some("good") => Continue(Good),
+        Some("bad") => Continue(Bad),
+        Some("skip") => Skip(detail()?),
+        Some("stop") => Stop(detail()?),
+        _ => return Err(format!("Unknown verdict {}", field("verdict"))),
+    };
+
+    let annotations = field("annotations");
+    let metrics = annotations.get("metrics").and_then(|m| m.as_object());
+    Ok((
+        result,
+        Annotations {
+            note: annotations
+                .get("note")
+                .and_then(|n| n.as_str())
+                .map(String::from),
+            metrics: metrics
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| (k.clone(), v.as_str().map_or(v.to_string(), String::from)))
+                .collect(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::test_util::test_util::{empty, ib, r};
+
+    #[test]
+    fn test_ibig_to_json() {
+        assert_eq!(ibig_to_json(&ib(-3)), json!(-3));
+        assert_eq!(ibig_to_json(&(ib(1) << 53)), json!("9007199254740992"));
+        assert_eq!(ibig_from_json(&json!(-3)), Ok(ib(-3)));
+        assert_eq!(ibig_from_json(&json!("9007199254740992")), Ok(ib(1) << 53));
+        assert_eq!(
+            ibig_from_json(&json!(1.5)),
+            Err("Expected an integer (got 1.5)".into())
+        );
+    }
+
+    #[test]
+    fn test_range_to_json() {
+        assert_eq!(range_to_json(&r(2, 5)), json!([2, 5]));
+        assert_eq!(range_to_json(&empty()), Value::Null);
+        assert_eq!(range_from_json(&json!([2, 5])), Ok(r(2, 5)));
+        assert_eq!(range_from_json(&Value::Null), Ok(empty()));
+        assert!(range_from_json(&json!([2])).is_err());
+    }
+
+    #[test]
+    fn test_result_roundtrip() {
+        for (result, annotations) in [
+            (Continue(Bad), Annotations::default()),
+            (Stop("nope".into()), Annotations::default()),
+            (
+                Continue(Good),
+                Annotations {
+                    note: None,
+                    metrics: [("wall".to_string(), "2ms".to_string())].into(),
+                },
+            ),
+        ] {
+            assert_eq!(
+                result_from_json(&result_to_json(&result, &annotations)),
+                Ok((result, annotations))
+            );
+        }
+        assert_eq!(
+            result_from_json(&json!({"verdict": "skip"})),
+            Err("Expected a detail (got null)".into())
+        );
+    }
+
+    #[test]
+    fn test_result_to_json() {
+        assert_eq!(
+            result_to_json(&Continue(Good), &Annotations::default()),
+            json!({"verdict": "good", "detail": null, "annotations": null})
+        );
+        assert_eq!(
+            result_to_json(
+                &Skip("flaky".into()),
+                &Annotations {
+                    note: Some("infra".into()),
+                    metrics: [("ms".to_string(), "12".to_string())].into(),
+                }
+            ),
+            json!({
+                "verdict": "skip",
+                "detail": "flaky",
+                "annotations": {"note": "infra", "metrics": {"ms": "12"}},
+            })
+        );
+    }
+}
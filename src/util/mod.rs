@@ -1,3 +1,4 @@
 pub mod functional;
+pub mod json;
 pub mod macros;
 pub mod string;
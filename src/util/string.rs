@@ -11,6 +11,29 @@ pub fn truncate_elipsis(s: &str, width: usize) -> String {
     }
 }
 
+/// Quotes `s` so a POSIX shell reads it as a single literal word.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Escapes `s` for XML text and attribute values. Characters XML can't contain at all, like most control characters, become U+FFFD.
+pub fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -22,4 +45,18 @@ mod tests {
         assert_eq!(truncate_elipsis("amogus", 5), "amog…");
         assert_eq!(truncate_elipsis("amogus", 6), "amogus");
     }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("123"), "'123'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(xml_escape("a < b && c"), "a &lt; b &amp;&amp; c");
+        assert_eq!(xml_escape("'\"\n\t"), "&apos;&quot;\n\t");
+        assert_eq!(xml_escape("\x1b[31mred"), "\u{fffd}[31mred");
+    }
 }